@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::ops::AddAssign;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use common::*;
 pub use geometry::{
@@ -17,8 +17,13 @@ use specs::prelude::*;
 use specs_derive::Component;
 pub const FINGER_NUMBER: usize = 20;
 use rand::prelude::*;
+pub use sdl2::controller::Button as GamepadButton;
+use sdl2::keyboard::Keycode;
 use sdl2::mixer::Channel;
 
+#[cfg(test)]
+mod test;
+
 pub const ASTEROID_MAX_LIFES: usize = 180usize;
 
 pub const BULLET_SPEED_INIT: f32 = 0.5;
@@ -26,7 +31,7 @@ pub const THRUST_FORCE_INIT: f32 = 0.01;
 pub const SHIP_ROTATION_SPEED_INIT: f32 = 1.0;
 
 pub type Canvas = ThreadPin<SDLCanvas>;
-pub type SpawnedUpgrades = Vec<[usize; 2]>;
+pub type SpawnedUpgrades = Vec<Vec<usize>>;
 
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
@@ -41,6 +46,10 @@ pub static TRACKER: Lazy<Mutex<TimeTracker>> = Lazy::new(|| {
 pub struct UpgradesStats {
     pub coins_mult: usize,
     pub exp_mult: usize,
+    /// added to the radius in which collectables are attracted
+    pub magnet_bonus: f32,
+    /// multiplier of abilities lifetime
+    pub ability_duration: f32,
 }
 
 impl Default for UpgradesStats {
@@ -48,6 +57,8 @@ impl Default for UpgradesStats {
         UpgradesStats {
             coins_mult: 1,
             exp_mult: 1,
+            magnet_bonus: 0.0,
+            ability_duration: 1.0,
         }
     }
 }
@@ -55,7 +66,13 @@ impl Default for UpgradesStats {
 pub struct TimeTracker {
     timestamp: Instant,
     game_timestamp: Instant,
+    // game time without slow down, player timers run on it
+    unscaled_timestamp: Instant,
     delta: Duration,
+    // game time runs slower or faster than wall time
+    scale: f32,
+    // game time at which the scale goes back to normal
+    slowed_until: Option<Instant>,
 }
 
 impl TimeTracker {
@@ -63,7 +80,10 @@ impl TimeTracker {
         TimeTracker {
             timestamp: Instant::now(),
             game_timestamp: Instant::now(),
+            unscaled_timestamp: Instant::now(),
             delta: Duration::from_secs(0),
+            scale: 1.0,
+            slowed_until: None,
         }
     }
 
@@ -73,9 +93,16 @@ impl TimeTracker {
         if res > Duration::from_millis(800) {
             res = Duration::from_millis(0);
         }
+        self.unscaled_timestamp += res;
+        let res = res.mul_f32(self.scale);
         self.game_timestamp += res;
         self.timestamp = now;
         self.delta = res;
+        if let Some(slowed_until) = self.slowed_until {
+            if self.game_timestamp >= slowed_until {
+                self.reset_scale();
+            }
+        }
         res
     }
 
@@ -83,9 +110,41 @@ impl TimeTracker {
         self.delta
     }
 
+    /// keeps game time still, wall time passed since the last update
+    /// is dropped
+    pub fn hold(&mut self) {
+        self.timestamp = Instant::now();
+        self.delta = Duration::from_secs(0);
+    }
+
     pub fn now(&self) -> Instant {
         self.game_timestamp
     }
+
+    /// time of player timers, it isn't slowed by `slow_down`
+    pub fn unscaled_now(&self) -> Instant {
+        self.unscaled_timestamp
+    }
+
+    /// current time of the timers of the given side
+    pub fn now_for(&self, entity_type: EntityType) -> Instant {
+        match entity_type {
+            EntityType::Player => self.unscaled_now(),
+            EntityType::Enemy => self.now(),
+        }
+    }
+
+    /// game time runs with `scale` for `duration` of game time
+    pub fn slow_down(&mut self, scale: f32, duration: Duration) {
+        self.scale = scale;
+        self.slowed_until = Some(self.game_timestamp + duration);
+    }
+
+    /// called when the run ends, slow down isn't carried to the next one
+    pub fn reset_scale(&mut self) {
+        self.scale = 1.0;
+        self.slowed_until = None;
+    }
 }
 
 pub struct DevInfo {
@@ -130,6 +189,18 @@ impl GlobalParams {
     }
 }
 
+/// Accumulated shift of the world. We move the world, not the character,
+/// so positions which are remembered for a while should be stored
+/// with this shift added
+#[derive(Debug, Clone, Copy)]
+pub struct WorldShift(pub Vector2);
+
+impl Default for WorldShift {
+    fn default() -> Self {
+        WorldShift(Vector2::new(0f32, 0f32))
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct CurrentWave {
     pub id: usize,
@@ -142,12 +213,114 @@ pub struct Waves(pub Vec<Wave>);
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct WavesSave(pub Vec<WaveSave>);
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AsteroidMaterial {
+    Rock,
+    /// drops extra coins when fully destroyed
+    Ore { coins: usize },
+    /// applies effect to the ship it hits
+    Ice { effect: StatusEffect },
+    /// explodes on the first destruction
+    Volatile { blast: Blast },
+    /// shards stay armored for `stages` destructions, then they are rock
+    Armored { stages: usize },
+}
+
+/// Parameters of procedural asteroid outline
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AsteroidShape {
+    pub samples: usize,
+    /// amplitude of radial noise relative to asteroid size
+    pub roughness: f32,
+    /// 0.0 is round, bigger values stretch the shape
+    pub elongation: f32,
+    pub craters: usize,
+}
+
+impl Default for AsteroidShape {
+    fn default() -> Self {
+        AsteroidShape {
+            samples: 20,
+            roughness: 0.3,
+            elongation: 0.2,
+            craters: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AsteroidKind {
+    pub name: String,
+    pub material: AsteroidMaterial,
+    pub color: (f32, f32, f32),
+    pub health_per_area: f32,
+    pub spawn_weight: f32,
+    #[serde(default)]
+    pub shape: AsteroidShape,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AsteroidKinds(pub Vec<AsteroidKind>);
+
+/// Pulls physics bodies inside `radius` towards its center
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GravityWell {
+    /// acceleration per frame at unit distance
+    pub strength: f32,
+    pub radius: f32,
+    /// bodies closer than that are destroyed
+    #[serde(default)]
+    pub event_horizon: Option<f32>,
+    /// extra acceleration of the character along its velocity
+    #[serde(default)]
+    pub slingshot: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GravityWellKind {
+    pub name: String,
+    pub well: GravityWell,
+    /// black holes have no image and are drawn by their event horizon
+    pub image: Option<AtlasImage>,
+    pub size: f32,
+    pub spawn_weight: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GravityWellKindSave {
+    pub name: String,
+    pub well: GravityWell,
+    #[serde(default)]
+    pub image_name: Option<String>,
+    pub size: f32,
+    pub spawn_weight: f32,
+}
+
+impl GravityWellKindSave {
+    pub fn load(
+        self,
+        name_to_image: &HashMap<String, AtlasImage>,
+    ) -> GravityWellKind {
+        GravityWellKind {
+            name: self.name,
+            well: self.well,
+            image: self.image_name.map(|image_name| name_to_image[&image_name]),
+            size: self.size,
+            spawn_weight: self.spawn_weight,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GravityWellKinds(pub Vec<GravityWellKind>);
+
 pub struct Pallete {
     pub life_color: Point3,
     pub shield_color: Point3,
     pub experience_color: Point3,
     pub white_color: Point3,
     pub grey_color: Point3,
+    pub dash_color: Point3,
 }
 
 impl Pallete {
@@ -157,6 +330,7 @@ impl Pallete {
         let experience_color = Point3::new(0.8, 0.8, 0.8);
         let white_color = Point3::new(1.0, 1.0, 1.0);
         let grey_color = Point3::new(0.5, 0.5, 0.5);
+        let dash_color = Point3::new(0.9, 0.8, 0.5);
 
         Pallete {
             life_color: life_color,
@@ -164,6 +338,7 @@ impl Pallete {
             experience_color: experience_color,
             white_color: white_color,
             grey_color: grey_color,
+            dash_color: dash_color,
         }
     }
 }
@@ -174,6 +349,9 @@ pub struct Wave {
     pub ships_number: usize,
     pub const_distribution: Vec<(usize, usize)>,
     pub iterations: usize,
+    pub squads: Vec<Squad>,
+    /// asteroid kinds weights, default spawn weights are used if empty
+    pub asteroids: Vec<(usize, f32)>,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -182,6 +360,65 @@ pub struct WaveSave {
     pub ships_number: usize,
     pub const_distribution: Vec<(String, usize)>,
     pub iterations: usize,
+    #[serde(default)]
+    pub squads: Vec<SquadSave>,
+    #[serde(default)]
+    pub asteroids: Vec<(String, f32)>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Formation {
+    V,
+    Ring,
+    Line,
+    Escort,
+}
+
+impl Formation {
+    /// Offset of follower slot in leader's local coordinates
+    /// (leader looks at -y, so positive y is behind him)
+    pub fn slot(&self, id: usize, followers_num: usize, spacing: f32) -> Vector2 {
+        let side = if id % 2 == 0 { 1.0 } else { -1.0 };
+        let row = (id / 2 + 1) as f32;
+        match self {
+            Formation::V => Vector2::new(side * row * spacing, row * spacing),
+            Formation::Line => Vector2::new(side * row * spacing, 0.0),
+            Formation::Ring => {
+                let angle = 2.0 * std::f32::consts::PI * id as f32
+                    / followers_num.max(1) as f32;
+                spacing * Vector2::new(angle.cos(), angle.sin())
+            }
+            Formation::Escort => {
+                // two wingmen, others cover the back
+                if id < 2 {
+                    Vector2::new(side * spacing, 0.0)
+                } else {
+                    Vector2::new(0.0, (id - 1) as f32 * spacing)
+                }
+            }
+        }
+    }
+}
+
+fn default_spacing() -> f32 {
+    2.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Squad {
+    pub leader: usize,
+    pub followers: Vec<(usize, usize)>,
+    pub formation: Formation,
+    pub spacing: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SquadSave {
+    pub leader: String,
+    pub followers: Vec<(String, usize)>,
+    pub formation: Formation,
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
 }
 
 #[derive(Debug, Default)]
@@ -216,6 +453,17 @@ pub struct Description {
     pub player_ships: Vec<(ShipKind)>,
     pub player_guns: Vec<GunKind>,
     pub enemies: Vec<EnemyKind>,
+    pub drones: Vec<DroneKind>,
+}
+
+impl Description {
+    /// lazer of the player, guns are looked up by kind not by position
+    pub fn player_lazer(&self) -> Option<&GunKind> {
+        self.player_guns.iter().find(|gun| match gun {
+            GunKind::MultyLazer(_) => true,
+            _ => false,
+        })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -227,6 +475,13 @@ pub struct EnemyKind {
     pub image: AtlasImage,
     pub snake: Option<usize>,
     pub rift: Option<Rift>,
+    pub steering: Steering,
+    pub skill: Skill,
+    pub perception: Perception,
+    pub resistances: Resistances,
+    /// sprite outline for size 1
+    pub outline: Polygon,
+    pub hardpoints: Vec<Hardpoint>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -241,12 +496,16 @@ pub enum InsertEvent {
         gun_kind: GunKind,
         ship_stats: ShipStats,
         image: AtlasImage,
+        resistances: Resistances,
+        hardpoints: Vec<Hardpoint>,
+        player: usize,
     },
     Asteroid {
         iso: Point3,
         velocity: Velocity2,
         polygon: Polygon,
         spin: f32,
+        kind: Asteroid,
     },
     Ship {
         iso: Point3,
@@ -259,6 +518,15 @@ pub enum InsertEvent {
         size: f32,
         snake: Option<usize>,
         rift: Option<Rift>,
+        steering: Steering,
+        skill: Skill,
+        perception: Perception,
+        resistances: Resistances,
+        outline: Polygon,
+        hardpoints: Vec<Hardpoint>,
+        // preallocated entity, used to reference squad leader before insert
+        entity: Option<specs::Entity>,
+        squad: Option<SquadMember>,
     },
     Bullet {
         kind: EntityType,
@@ -276,6 +544,8 @@ pub enum InsertEvent {
         kind: EntityType,
         iso: Point3,
         damage: usize,
+        speed: f32,
+        lifetime: Duration,
         owner: specs::Entity,
         rocket_image: AtlasImage,
     },
@@ -283,6 +553,21 @@ pub enum InsertEvent {
         value: usize,
         position: Point2,
     },
+    /// explosion without projectile, e.g. from volatile asteroid
+    Blast {
+        position: Point2,
+        blast: Blast,
+        /// blast of the owner doesn't hurt its team
+        owner: Option<specs::Entity>,
+    },
+    /// piece of destroyed ship
+    Debris {
+        iso: Point3,
+        velocity: Velocity2,
+        polygon: Polygon,
+        image: AtlasImage,
+        size: f32,
+    },
     DoubleCoinsAbility,
     DoubleCoinsCollectable {
         position: Point2,
@@ -299,6 +584,17 @@ pub enum InsertEvent {
         position: Point2,
     },
     SideBulletAbility,
+    DroneCollectable {
+        position: Point2,
+    },
+    Drone {
+        // preallocated, so the bay knows its drone before insert
+        entity: specs::Entity,
+        owner: specs::Entity,
+        slot: usize,
+        kind: DroneKind,
+        position: Point2,
+    },
     Exp {
         value: usize,
         position: Point2,
@@ -332,6 +628,10 @@ pub enum InsertEvent {
     Planet {
         iso: Point3,
     },
+    GravityWell {
+        position: Point2,
+        kind: usize,
+    },
     Wobble(f32),
     Animation {
         animation: Animation,
@@ -350,10 +650,23 @@ pub struct UpgradeCard {
     pub image: AtlasImage,
     pub name: String,
     pub description: String,
+    /// ids of cards in `AvaliableUpgrades`
+    pub requires: Vec<usize>,
+    pub excludes: Vec<usize>,
+    pub max_rank: usize,
+    pub rarity: f32,
 }
 
 pub type AvaliableUpgrades = Vec<UpgradeCard>;
 
+/// Upgrades taken during the current run
+#[derive(Debug, Default, Clone)]
+pub struct RunUpgrades {
+    /// ids of taken cards in order of choice, repeated ids are ranks
+    pub picked: Vec<usize>,
+    pub rerolls: usize,
+}
+
 // #[derive(Default)]
 // pub struct AvaliableUpgrades {
 //     pub list: Vec<UpgradeCard>
@@ -371,6 +684,13 @@ pub enum UpgradeType {
     LazerLength,
     // BulletReflection,
     Maneuverability,
+    RocketDamage,
+    RocketFuel,
+    /// index of drone kind in description
+    Drone(usize),
+    DashCharges,
+    DashCooldown,
+    ActiveAbility(ActiveAbilityKind),
 }
 
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
@@ -393,6 +713,133 @@ pub enum AIType {
     },
 }
 
+/// Weights and radii of steering behaviours, tweaked per enemy in desc.ron
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Steering {
+    pub seek: f32,
+    pub arrive: f32,
+    pub flee: f32,
+    pub separation: f32,
+    pub avoidance: f32,
+    // distance at which arrive starts to slow down
+    pub slowing_radius: f32,
+    // flee only if target is closer than that
+    pub flee_radius: f32,
+    pub separation_radius: f32,
+    // how far ahead we cast rays to find asteroids
+    pub avoidance_distance: f32,
+}
+
+impl Default for Steering {
+    fn default() -> Self {
+        Steering {
+            seek: 1.0,
+            arrive: 0.0,
+            flee: 0.0,
+            separation: 1.0,
+            avoidance: 1.5,
+            slowing_radius: 5.0,
+            flee_radius: 3.0,
+            separation_radius: 1.5,
+            avoidance_distance: 4.0,
+        }
+    }
+}
+
+/// How good enemy pilot is at shooting
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Skill {
+    // fraction of predicted lead used for aiming, 0 -- aim at current position
+    pub accuracy: f32,
+    // fire only if ship is aligned with aim direction within this angle(degrees)
+    pub fire_angle: f32,
+}
+
+impl Default for Skill {
+    fn default() -> Self {
+        Skill {
+            accuracy: 0.0,
+            fire_angle: 180.0,
+        }
+    }
+}
+
+/// What enemy knows about the player
+#[derive(Component, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Perception {
+    pub view_range: f32,
+    // field of view cone(degrees)
+    pub fov: f32,
+    // how long enemy remembers where player was
+    pub memory: Duration,
+    // allies in this radius are alerted when player is spotted
+    pub alert_radius: f32,
+    #[serde(skip)]
+    pub sees: bool,
+    // knows about player from allies, not by itself
+    #[serde(skip)]
+    pub alerted: bool,
+    #[serde(skip)]
+    last_known_position: Option<Point2>,
+    #[serde(skip)]
+    last_seen: Option<Instant>,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Perception {
+            view_range: 20.0,
+            fov: 360.0,
+            memory: Duration::from_secs(3),
+            alert_radius: 8.0,
+            sees: false,
+            alerted: false,
+            last_known_position: None,
+            last_seen: None,
+        }
+    }
+}
+
+impl Perception {
+    pub fn see(&mut self, position: Point2, shift: Vector2, now: Instant) {
+        self.sees = true;
+        self.alerted = false;
+        self.last_known_position = Some(position + shift);
+        self.last_seen = Some(now);
+    }
+
+    pub fn lose(&mut self) {
+        self.sees = false;
+    }
+
+    pub fn alert(&mut self, position: Point2, shift: Vector2, now: Instant) {
+        if !self.sees {
+            self.alerted = true;
+            self.last_known_position = Some(position + shift);
+            self.last_seen = Some(now);
+        }
+    }
+
+    /// last known position of the player if it's not forgotten yet
+    pub fn last_known_position(
+        &self,
+        shift: Vector2,
+        now: Instant,
+    ) -> Option<Point2> {
+        match (self.last_known_position, self.last_seen) {
+            (Some(position), Some(last_seen))
+                if now - last_seen < self.memory =>
+            {
+                Some(position - shift)
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Component, Serialize, Deserialize)]
 pub struct ShipStats {
     pub thrust_force: f32,
@@ -405,24 +852,54 @@ pub struct ShipStats {
     pub damage: usize,
 }
 
+impl ShipStats {
+    /// durability and damage multiplied by `k`, movement is kept
+    pub fn scaled(&self, k: f32) -> ShipStats {
+        let scale = |value: usize| (value as f32 * k) as usize;
+        ShipStats {
+            health_regen: scale(self.health_regen),
+            shield_regen: scale(self.shield_regen),
+            max_health: scale(self.max_health).max(1),
+            max_shield: scale(self.max_shield),
+            damage: scale(self.damage),
+            ..*self
+        }
+    }
+}
+
 #[derive(Debug, Clone, Component, Serialize, Deserialize)]
 pub struct ShipKindSave {
     ship_stats: ShipStats,
     image: String,
+    #[serde(default)]
+    resistances: Resistances,
+    #[serde(default)]
+    hardpoints: Vec<HardpointSave>,
 }
 
 #[derive(Debug, Clone, Component)]
 pub struct ShipKind {
     pub ship_stats: ShipStats,
     pub image: AtlasImage,
+    pub resistances: Resistances,
+    pub hardpoints: Vec<Hardpoint>,
 }
 
 impl ShipKindSave {
-    pub fn load(self, name_to_image: &HashMap<String, AtlasImage>) -> ShipKind {
-        ShipKind {
+    pub fn load(
+        self,
+        name_to_image: &HashMap<String, AtlasImage>,
+    ) -> Result<ShipKind, String> {
+        Ok(ShipKind {
             ship_stats: self.ship_stats,
             image: name_to_image[&self.image],
-        }
+            resistances: self.resistances,
+            hardpoints: self
+                .hardpoints
+                .iter()
+                .map(|hardpoint| hardpoint.load(name_to_image))
+                .collect::<Result<_, _>>()?,
+        })
     }
 }
 
@@ -456,6 +933,9 @@ pub enum AppState {
     DeadScreen,
     Play(PlayState),
     ScoreTable,
+    Shop,
+    Controls,
+    Paused,
 }
 
 impl Default for AppState {
@@ -464,11 +944,24 @@ impl Default for AppState {
     }
 }
 
+/// Set to close the game at the end of the frame
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExitGame(pub bool);
+
 #[derive(Component, Debug, Clone, Copy)]
 pub struct Chain {
     pub follow: specs::Entity,
 }
 
+/// Follower in squad, keeps its slot relative to the leader
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SquadMember {
+    pub leader: specs::Entity,
+    pub slot: Vector2,
+    // formation is broken while fighting
+    pub engaged: bool,
+}
+
 #[derive(Component, Debug, Clone, Copy)]
 pub struct LazerConnect(pub specs::Entity);
 
@@ -487,7 +980,7 @@ pub struct Charge {
 impl Charge {
     pub fn new(recharge_time: Duration) -> Self {
         Charge {
-            recharge_start: Instant::now(),
+            recharge_start: TRACKER.lock().unwrap().now(),
             recharge_time: recharge_time,
         }
     }
@@ -523,6 +1016,169 @@ impl Gun for Charge {
 #[derive(Component, Debug, Clone, Copy)]
 pub struct DamageFlash(pub f32);
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// damage `magnitude` every tick, no regeneration while burning
+    Burn,
+    /// thrust and torque are decreased by `magnitude` fraction
+    Slow,
+    /// no thrust, no rotation and no shooting
+    Stun,
+    /// incoming damage is increased by `magnitude` fraction
+    ArmorBreak,
+}
+
+impl StatusKind {
+    /// tint for rendering of affected ship
+    pub fn color(&self) -> (f32, f32, f32) {
+        match self {
+            StatusKind::Burn => (1.0, 0.4, 0.0),
+            StatusKind::Slow => (0.3, 0.6, 1.0),
+            StatusKind::Stun => (1.0, 1.0, 0.3),
+            StatusKind::ArmorBreak => (0.7, 0.2, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Stacking {
+    /// restart duration of already applied effect
+    Refresh,
+    /// add stack up to the limit and restart duration
+    Stack(usize),
+    /// keep already applied effect as is
+    Ignore,
+}
+
+impl Default for Stacking {
+    fn default() -> Self {
+        Stacking::Refresh
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: Duration,
+    #[serde(default)]
+    pub tick: Duration,
+    pub magnitude: f32,
+    #[serde(default)]
+    pub stacking: Stacking,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ActiveStatus {
+    pub effect: StatusEffect,
+    pub stacks: usize,
+    start_time: Instant,
+    last_tick: Instant,
+}
+
+impl ActiveStatus {
+    pub fn expired(&self, now: Instant) -> bool {
+        now - self.start_time > self.effect.duration
+    }
+
+    /// how many ticks passed since the last call
+    pub fn ticks(&mut self, now: Instant) -> usize {
+        if self.effect.tick.as_millis() == 0 {
+            return 0;
+        }
+        let mut res = 0;
+        while now - self.last_tick >= self.effect.tick {
+            self.last_tick += self.effect.tick;
+            res += 1;
+        }
+        res
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        self.stacks as f32 * self.effect.magnitude
+    }
+}
+
+/// Stack of status effects applied to the ship.
+/// Ship's own stats are never changed, effects are applied on use
+#[derive(Component, Debug, Clone, Default)]
+pub struct StatusEffects {
+    pub active: Vec<ActiveStatus>,
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: StatusEffect, now: Instant) {
+        if let Some(status) =
+            self.active.iter_mut().find(|s| s.effect.kind == effect.kind)
+        {
+            match effect.stacking {
+                Stacking::Refresh => {
+                    status.effect = effect;
+                    status.start_time = now;
+                }
+                Stacking::Stack(limit) => {
+                    status.effect = effect;
+                    status.stacks = (status.stacks + 1).min(limit.max(1));
+                    status.start_time = now;
+                }
+                Stacking::Ignore => (),
+            }
+            return;
+        }
+        self.active.push(ActiveStatus {
+            effect,
+            stacks: 1,
+            start_time: now,
+            last_tick: now,
+        });
+    }
+
+    pub fn magnitude(&self, kind: StatusKind) -> f32 {
+        self.active
+            .iter()
+            .filter(|s| s.effect.kind == kind)
+            .map(|s| s.magnitude())
+            .sum()
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.active.iter().any(|s| s.effect.kind == kind)
+    }
+
+    pub fn stunned(&self) -> bool {
+        self.has(StatusKind::Stun)
+    }
+
+    pub fn damage_multiplier(&self) -> f32 {
+        1.0 + self.magnitude(StatusKind::ArmorBreak)
+    }
+
+    pub fn color(&self) -> Option<(f32, f32, f32)> {
+        self.active.last().map(|s| s.effect.kind.color())
+    }
+
+    /// drop expired effects
+    pub fn update(&mut self, now: Instant) {
+        self.active.retain(|s| !s.expired(now));
+    }
+
+    /// stats of the ship with active effects applied
+    pub fn affect(&self, stats: &ShipStats) -> ShipStats {
+        let mut stats = *stats;
+        let slow = (1.0 - self.magnitude(StatusKind::Slow)).max(0.0);
+        stats.thrust_force *= slow;
+        stats.torque *= slow;
+        if self.has(StatusKind::Burn) {
+            stats.health_regen = 0;
+            stats.shield_regen = 0;
+        }
+        if self.stunned() {
+            stats.thrust_force = 0.0;
+            stats.torque = 0.0;
+        }
+        stats
+    }
+}
+
 #[derive(Component, Debug, Clone, Copy)]
 pub struct Size(pub f32);
 
@@ -544,12 +1200,371 @@ pub struct Shield(pub usize);
 #[derive(Component, Clone, Copy)]
 pub struct Damage(pub usize);
 
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DamageType {
+    Kinetic,
+    Energy,
+    Explosive,
+    Collision,
+}
+
+/// Multiplier for each damage type, 1.0 is full damage
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DamageTable {
+    pub kinetic: f32,
+    pub energy: f32,
+    pub explosive: f32,
+    pub collision: f32,
+}
+
+impl Default for DamageTable {
+    fn default() -> Self {
+        DamageTable {
+            kinetic: 1.0,
+            energy: 1.0,
+            explosive: 1.0,
+            collision: 1.0,
+        }
+    }
+}
+
+impl DamageTable {
+    pub fn get(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Energy => self.energy,
+            DamageType::Explosive => self.explosive,
+            DamageType::Collision => self.collision,
+        }
+    }
+}
+
+/// Per ship resistances, separately for shield and hull
+#[derive(Component, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Resistances {
+    pub shield: DamageTable,
+    pub hull: DamageTable,
+}
+
+/// Permanent bonuses bought in the shop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Perk {
+    StartingHealth,
+    ShieldRegen,
+    MagnetRadius,
+    StartingLevel,
+    RerollTokens,
+    AbilityDuration,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShopItem {
+    pub perk: Perk,
+    pub name: String,
+    pub description: String,
+    pub base_price: usize,
+    /// price is multiplied by that for each bought level
+    pub price_growth: f32,
+    pub max_level: usize,
+    /// bonus of one level, units depend on perk
+    pub step: f32,
+}
+
+impl ShopItem {
+    pub fn price(&self, level: usize) -> usize {
+        (self.base_price as f32 * self.price_growth.powi(level as i32)) as usize
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Shop(pub Vec<ShopItem>);
+
+impl Shop {
+    pub fn bonus(&self, perk: Perk, macro_game: &MacroGame) -> f32 {
+        self.0
+            .iter()
+            .filter(|item| item.perk == perk)
+            .map(|item| item.step * macro_game.perk_level(perk) as f32)
+            .sum()
+    }
+}
+
+/// Rule of the daily run, the same for everyone during the day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DailyModifier {
+    DoubleEnemySpeed,
+    NoShields,
+    OnlyLazers,
+    DenseAsteroids,
+}
+
+impl DailyModifier {
+    pub const ALL: [DailyModifier; 4] = [
+        DailyModifier::DoubleEnemySpeed,
+        DailyModifier::NoShields,
+        DailyModifier::OnlyLazers,
+        DailyModifier::DenseAsteroids,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            DailyModifier::DoubleEnemySpeed => "Double enemy speed",
+            DailyModifier::NoShields => "No shields",
+            DailyModifier::OnlyLazers => "Only lazers",
+            DailyModifier::DenseAsteroids => "Dense asteroids",
+        }
+    }
+}
+
+/// Days since unix epoch
+pub fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / (24 * 60 * 60))
+        .unwrap_or(0)
+}
+
+/// splitmix64, so close days don't get similar seeds
+fn day_seed(day: u64) -> u64 {
+    let mut z = day.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunMode {
+    Normal,
+    Daily { day: u64, modifier: DailyModifier },
+}
+
+impl Default for RunMode {
+    fn default() -> Self {
+        RunMode::Normal
+    }
+}
+
+impl RunMode {
+    pub fn daily(day: u64) -> Self {
+        let id = (day_seed(day) % DailyModifier::ALL.len() as u64) as usize;
+        RunMode::Daily {
+            day: day,
+            modifier: DailyModifier::ALL[id],
+        }
+    }
+
+    pub fn modifier(&self) -> Option<DailyModifier> {
+        match self {
+            RunMode::Normal => None,
+            RunMode::Daily { modifier, .. } => Some(*modifier),
+        }
+    }
+}
+
+/// Separate random streams for asteroids, waves and upgrade offers,
+/// so one of them can't shift the others.
+/// Daily runs seed them from the date
+pub struct RunRngs {
+    pub asteroids: StdRng,
+    pub waves: StdRng,
+    pub upgrades: StdRng,
+    /// fracture and drops, separate so kills don't shift spawns
+    pub drops: StdRng,
+    pub gravity_wells: StdRng,
+}
+
+impl RunRngs {
+    pub fn new(run_mode: RunMode) -> Self {
+        match run_mode {
+            RunMode::Normal => RunRngs {
+                asteroids: StdRng::from_entropy(),
+                waves: StdRng::from_entropy(),
+                upgrades: StdRng::from_entropy(),
+                drops: StdRng::from_entropy(),
+                gravity_wells: StdRng::from_entropy(),
+            },
+            RunMode::Daily { day, .. } => {
+                let seed = day_seed(day);
+                RunRngs {
+                    asteroids: StdRng::seed_from_u64(seed),
+                    waves: StdRng::seed_from_u64(seed ^ 1),
+                    upgrades: StdRng::seed_from_u64(seed ^ 2),
+                    drops: StdRng::seed_from_u64(seed ^ 3),
+                    gravity_wells: StdRng::seed_from_u64(seed ^ 4),
+                }
+            }
+        }
+    }
+}
+
+impl Default for RunRngs {
+    fn default() -> Self {
+        RunRngs::new(RunMode::Normal)
+    }
+}
+
+/// Length of time attack run
+pub const TIME_ATTACK_SECS: u64 = 5 * 60;
+/// Time attack multiplier grows by one every this many seconds
+pub const TIME_ATTACK_MULTIPLIER_SECS: u64 = 60;
+/// Survival difficulty grows by one every this many seconds
+pub const SURVIVAL_RAMP_SECS: u64 = 90;
+/// Survival moves to the next wave every this many seconds
+pub const SURVIVAL_WAVE_SECS: u64 = 45;
+
+/// Rules of the run, chosen in the menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    /// waves escalate with kills until death
+    Classic,
+    /// score as much as possible before the time is up
+    TimeAttack,
+    /// difficulty scales continuously with time
+    Survival,
+    /// asteroids only, no enemies
+    Zen,
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Classic
+    }
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::Survival,
+        GameMode::Zen,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack => "Time Attack",
+            GameMode::Survival => "Survival",
+            GameMode::Zen => "Zen",
+        }
+    }
+
+    pub fn spawns_enemies(&self) -> bool {
+        *self != GameMode::Zen
+    }
+
+    /// run ends as finished after this time
+    pub fn time_limit(&self) -> Option<Duration> {
+        match self {
+            GameMode::TimeAttack => Some(Duration::from_secs(TIME_ATTACK_SECS)),
+            _ => None,
+        }
+    }
+
+    /// multiplier of enemies number
+    pub fn difficulty(&self, elapsed: Duration) -> f32 {
+        match self {
+            GameMode::Survival => {
+                1.0 + elapsed.as_secs() as f32 / SURVIVAL_RAMP_SECS as f32
+            }
+            _ => 1.0,
+        }
+    }
+
+    pub fn score_multiplier(&self, elapsed: Duration) -> f32 {
+        match self {
+            GameMode::TimeAttack => {
+                1.0 + (elapsed.as_secs() / TIME_ATTACK_MULTIPLIER_SECS) as f32
+            }
+            GameMode::Survival => self.difficulty(elapsed),
+            _ => 1.0,
+        }
+    }
+}
+
+/// Clock of the current run
+#[derive(Debug, Clone, Copy)]
+pub struct RunTimer {
+    pub start: Instant,
+    /// run ended by mode time limit, not by death
+    pub time_up: bool,
+}
+
+impl Default for RunTimer {
+    fn default() -> Self {
+        RunTimer {
+            start: TRACKER.lock().unwrap().now(),
+            time_up: false,
+        }
+    }
+}
+
+impl RunTimer {
+    pub fn elapsed(&self) -> Duration {
+        TRACKER.lock().unwrap().now() - self.start
+    }
+}
+
+/// Summary of finished run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub score: usize,
+    pub level: usize,
+    /// names of taken upgrades in order of choice
+    pub upgrades: Vec<String>,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct MacroGame {
     pub score_table: Vec<usize>,
     pub coins: usize,
     pub ships_unlocked: Vec<bool>,
     pub guns_unlocked: Vec<bool>,
+    #[serde(default)]
+    pub runs: Vec<RunRecord>,
+    /// bought levels of shop perks
+    #[serde(default)]
+    pub perks: HashMap<Perk, usize>,
+    /// best score of daily runs by day
+    #[serde(default)]
+    pub daily_best: HashMap<u64, usize>,
+    /// leaderboards of modes other than classic
+    #[serde(default)]
+    pub mode_scores: HashMap<GameMode, Vec<usize>>,
+    /// sticks deflection which is ignored
+    #[serde(default = "default_dead_zone")]
+    pub dead_zone: f32,
+}
+
+fn default_dead_zone() -> f32 {
+    GAMEPAD_DEAD_ZONE
+}
+
+impl MacroGame {
+    pub fn perk_level(&self, perk: Perk) -> usize {
+        self.perks.get(&perk).cloned().unwrap_or(0)
+    }
+
+    pub fn scores(&self, mode: GameMode) -> &[usize] {
+        match mode {
+            GameMode::Classic => &self.score_table,
+            _ => self
+                .mode_scores
+                .get(&mode)
+                .map(|scores| scores.as_slice())
+                .unwrap_or(&[]),
+        }
+    }
+
+    pub fn add_score(&mut self, mode: GameMode, score: usize) {
+        let table = match mode {
+            GameMode::Classic => &mut self.score_table,
+            _ => self.mode_scores.entry(mode).or_insert_with(Vec::new),
+        };
+        table.push(score);
+        table.sort_by(|a, b| b.cmp(a));
+    }
 }
 
 impl Default for MacroGame {
@@ -559,11 +1574,16 @@ impl Default for MacroGame {
             coins: 0,
             ships_unlocked: vec![true, false, false],
             guns_unlocked: vec![true, false, false],
+            runs: vec![],
+            perks: HashMap::new(),
+            daily_best: HashMap::new(),
+            mode_scores: HashMap::new(),
+            dead_zone: GAMEPAD_DEAD_ZONE,
         }
     }
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct Progress {
     pub experience: usize,
     pub level: usize,
@@ -619,6 +1639,9 @@ pub struct PreloadedImages {
     pub double_coin: AtlasImage,
     pub double_exp: AtlasImage,
     pub side_bullet_ability: AtlasImage,
+    pub bomb_ability: AtlasImage,
+    pub shield_burst_ability: AtlasImage,
+    pub time_slow_ability: AtlasImage,
     pub bar: AtlasImage,
     pub upg_bar: AtlasImage,
     pub transparent_sqr: AtlasImage,
@@ -686,6 +1709,365 @@ impl Finger {
 
 pub type Touches = [Option<Finger>; FINGER_NUMBER];
 
+/// What player wants to do, independent of input device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    ThrustUp,
+    ThrustDown,
+    ThrustLeft,
+    ThrustRight,
+    TurnLeft,
+    TurnRight,
+    Fire,
+    /// thrust backwards relative to the ship
+    Reverse,
+    Dash,
+    /// active abilities by slot
+    Ability1,
+    Ability2,
+    Ability3,
+    OpenUpgrades,
+    ToggleTelemetry,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::ThrustUp,
+        Action::ThrustDown,
+        Action::ThrustLeft,
+        Action::ThrustRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Fire,
+        Action::Reverse,
+        Action::Dash,
+        Action::Ability1,
+        Action::Ability2,
+        Action::Ability3,
+        Action::OpenUpgrades,
+        Action::ToggleTelemetry,
+        Action::ZoomIn,
+        Action::ZoomOut,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::ThrustUp => "Thrust up",
+            Action::ThrustDown => "Thrust down",
+            Action::ThrustLeft => "Thrust left",
+            Action::ThrustRight => "Thrust right",
+            Action::TurnLeft => "Turn left",
+            Action::TurnRight => "Turn right",
+            Action::Fire => "Fire",
+            Action::Reverse => "Reverse",
+            Action::Dash => "Dash",
+            Action::Ability1 => "Ability 1",
+            Action::Ability2 => "Ability 2",
+            Action::Ability3 => "Ability 3",
+            Action::OpenUpgrades => "Upgrades",
+            Action::ToggleTelemetry => "Telemetry",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+        }
+    }
+}
+
+/// Digital input which can be bound to an action.
+/// Keys and buttons are stored by SDL names to keep config readable
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(String),
+    MouseLeft,
+    MouseRight,
+    Pad(String),
+}
+
+impl Binding {
+    pub fn key(key: Keycode) -> Self {
+        Binding::Key(key.name())
+    }
+
+    pub fn pad(button: GamepadButton) -> Self {
+        Binding::Pad(button.string())
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(name) => name.clone(),
+            Binding::MouseLeft => "Mouse left".to_string(),
+            Binding::MouseRight => "Mouse right".to_string(),
+            Binding::Pad(name) => format!("Pad {}", name),
+        }
+    }
+
+    fn same_device(&self, other: &Binding) -> bool {
+        match (self, other) {
+            (Binding::Key(_), Binding::Key(_)) => true,
+            (Binding::Pad(_), Binding::Pad(_)) => true,
+            (Binding::MouseLeft, Binding::MouseLeft)
+            | (Binding::MouseLeft, Binding::MouseRight)
+            | (Binding::MouseRight, Binding::MouseLeft)
+            | (Binding::MouseRight, Binding::MouseRight) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Rebindable controls, saved to config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bindings(pub HashMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert(Action::ThrustUp, vec![Binding::key(Keycode::W)]);
+        bindings.insert(Action::ThrustDown, vec![Binding::key(Keycode::S)]);
+        bindings.insert(Action::ThrustLeft, vec![Binding::key(Keycode::A)]);
+        bindings.insert(Action::ThrustRight, vec![Binding::key(Keycode::D)]);
+        bindings.insert(Action::TurnLeft, vec![Binding::key(Keycode::Left)]);
+        bindings.insert(Action::TurnRight, vec![Binding::key(Keycode::Right)]);
+        bindings.insert(
+            Action::Fire,
+            vec![Binding::MouseLeft, Binding::key(Keycode::Up)],
+        );
+        bindings.insert(Action::Reverse, vec![Binding::MouseRight]);
+        bindings.insert(
+            Action::Dash,
+            vec![
+                Binding::key(Keycode::LShift),
+                Binding::pad(GamepadButton::LeftShoulder),
+            ],
+        );
+        bindings.insert(
+            Action::Ability1,
+            vec![Binding::key(Keycode::Num1), Binding::pad(GamepadButton::X)],
+        );
+        bindings.insert(
+            Action::Ability2,
+            vec![Binding::key(Keycode::Num2), Binding::pad(GamepadButton::Y)],
+        );
+        bindings.insert(
+            Action::Ability3,
+            vec![Binding::key(Keycode::Num3), Binding::pad(GamepadButton::B)],
+        );
+        bindings.insert(
+            Action::OpenUpgrades,
+            vec![
+                Binding::key(Keycode::Space),
+                Binding::pad(GamepadButton::Start),
+            ],
+        );
+        bindings
+            .insert(Action::ToggleTelemetry, vec![Binding::key(Keycode::T)]);
+        bindings
+            .insert(Action::ZoomIn, vec![Binding::key(Keycode::LeftBracket)]);
+        bindings
+            .insert(Action::ZoomOut, vec![Binding::key(Keycode::RightBracket)]);
+        Bindings(bindings)
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0
+            .get(&action)
+            .map(|bindings| bindings.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// actions added after the config was saved get default bindings
+    pub fn add_missing(&mut self) {
+        for (action, bindings) in Bindings::default().0.into_iter() {
+            self.0.entry(action).or_insert(bindings);
+        }
+    }
+
+    /// replaces bindings of the same device, others are kept
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.0.entry(action).or_insert_with(Vec::new);
+        bindings.retain(|old| !old.same_device(&binding));
+        bindings.push(binding);
+    }
+
+    /// actions which share the same binding
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<Action>)> {
+        let mut users: HashMap<&Binding, Vec<Action>> = HashMap::new();
+        for action in Action::ALL.iter() {
+            for binding in self.get(*action).iter() {
+                users.entry(binding).or_insert_with(Vec::new).push(*action);
+            }
+        }
+        users
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|(binding, actions)| (binding.clone(), actions))
+            .collect()
+    }
+}
+
+/// Where the ship should look
+#[derive(Debug, Clone, Copy)]
+pub enum Aim {
+    /// point in the world, e.g. mouse cursor
+    At(Point2),
+    /// direction on the screen, e.g. stick
+    Direction(Vector2),
+}
+
+/// Input of the first player mapped to actions, filled every frame
+#[derive(Debug)]
+pub struct Actions {
+    /// sum of thrust directions, screen oriented
+    pub thrust: Vector2,
+    /// -1 to turn left, 1 to turn right
+    pub turn: f32,
+    pub aim: Option<Aim>,
+    pub fire: bool,
+    pub reverse: bool,
+    held: Vec<Action>,
+    pressed: Vec<Action>,
+}
+
+impl Default for Actions {
+    fn default() -> Self {
+        Actions {
+            thrust: Vector2::new(0.0, 0.0),
+            turn: 0.0,
+            aim: None,
+            fire: false,
+            reverse: false,
+            held: vec![],
+            pressed: vec![],
+        }
+    }
+}
+
+impl Actions {
+    pub fn set_held(&mut self, held: Vec<Action>) {
+        self.pressed = held
+            .iter()
+            .cloned()
+            .filter(|action| !self.held.contains(action))
+            .collect();
+        self.held = held;
+    }
+
+    pub fn held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+}
+
+/// Default part of stick deflection which is ignored
+pub const GAMEPAD_DEAD_ZONE: f32 = 0.2;
+/// Dead zone settings to cycle through in menu
+pub const GAMEPAD_DEAD_ZONES: [f32; 4] = [0.1, 0.2, 0.3, 0.4];
+const GAMEPAD_TRIGGER_THRESHOLD: f32 = 0.5;
+const GAMEPAD_AXIS_MAX: f32 = 32767.0;
+
+/// State of the first connected game controller, updated every frame
+#[derive(Debug)]
+pub struct Gamepad {
+    pub connected: bool,
+    pub dead_zone: f32,
+    // normalized, with dead zone already applied
+    pub left_stick: Vector2,
+    pub right_stick: Vector2,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    buttons: Vec<GamepadButton>,
+    // buttons pressed during the last frame
+    pressed: Vec<GamepadButton>,
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Gamepad {
+            connected: false,
+            dead_zone: GAMEPAD_DEAD_ZONE,
+            left_stick: Vector2::new(0.0, 0.0),
+            right_stick: Vector2::new(0.0, 0.0),
+            left_trigger: 0.0,
+            right_trigger: 0.0,
+            buttons: vec![],
+            pressed: vec![],
+        }
+    }
+}
+
+impl Gamepad {
+    /// raw SDL axes values
+    pub fn set_axes(
+        &mut self,
+        left: (i16, i16),
+        right: (i16, i16),
+        triggers: (i16, i16),
+    ) {
+        let axis = |value: i16| value as f32 / GAMEPAD_AXIS_MAX;
+        self.left_stick = self.filter(Vector2::new(axis(left.0), axis(left.1)));
+        self.right_stick =
+            self.filter(Vector2::new(axis(right.0), axis(right.1)));
+        self.left_trigger = axis(triggers.0).max(0.0);
+        self.right_trigger = axis(triggers.1).max(0.0);
+    }
+
+    pub fn set_buttons(&mut self, buttons: Vec<GamepadButton>) {
+        self.pressed = buttons
+            .iter()
+            .cloned()
+            .filter(|button| !self.buttons.contains(button))
+            .collect();
+        self.buttons = buttons;
+    }
+
+    pub fn disconnect(&mut self) {
+        *self = Gamepad {
+            dead_zone: self.dead_zone,
+            ..Gamepad::default()
+        };
+    }
+
+    pub fn held(&self, button: GamepadButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: GamepadButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_pressed_buttons(&self) -> &[GamepadButton] {
+        &self.pressed
+    }
+
+    pub fn fire(&self) -> bool {
+        self.right_trigger > GAMEPAD_TRIGGER_THRESHOLD
+    }
+
+    pub fn ability(&self) -> bool {
+        self.left_trigger > GAMEPAD_TRIGGER_THRESHOLD
+    }
+
+    pub fn aiming(&self) -> bool {
+        self.right_stick.norm() > 0.0
+    }
+
+    /// radial dead zone, the rest of deflection is rescaled to 0..1
+    fn filter(&self, stick: Vector2) -> Vector2 {
+        let deflection = stick.norm();
+        if deflection <= self.dead_zone {
+            return Vector2::new(0.0, 0.0);
+        }
+        let scaled =
+            ((deflection - self.dead_zone) / (1.0 - self.dead_zone)).min(1.0);
+        stick * (scaled / deflection)
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct Mouse {
     // normalized coordinates
@@ -738,21 +2120,223 @@ impl Mouse {
         self.y = point.y;
     }
 
-    pub fn set_left(&mut self, is_left: bool) {
-        self.left_released = self.left && !is_left;
-        self.left = is_left;
+    pub fn set_left(&mut self, is_left: bool) {
+        self.left_released = self.left && !is_left;
+        self.left = is_left;
+    }
+
+    pub fn set_right(&mut self, is_right: bool) {
+        self.right_released = self.right && !is_right;
+        self.right = is_right;
+    }
+}
+
+#[derive(Default, Component)]
+#[storage(NullStorage)]
+pub struct CharacterMarker;
+
+/// Local player owning the character.
+/// Experience is per player, score and coins are shared in `Progress`
+#[derive(Debug, Default, Component, Clone, Copy)]
+pub struct Player {
+    pub id: usize,
+    pub progress: Progress,
+}
+
+/// Local co-op. Character out of lifes is downed while someone
+/// else is standing, and gets back when a teammate stays near it
+#[derive(Debug)]
+pub struct Coop {
+    /// number of players chosen in the menu
+    pub local_players: usize,
+    pub players: Vec<Entity>,
+    /// downed characters with revive progress from 0 to 1
+    pub downed: Vec<(Entity, f32)>,
+}
+
+impl Default for Coop {
+    fn default() -> Self {
+        Coop {
+            local_players: 1,
+            players: vec![],
+            downed: vec![],
+        }
+    }
+}
+
+impl Coop {
+    pub fn is_downed(&self, entity: Entity) -> bool {
+        self.downed.iter().any(|(downed, _)| *downed == entity)
+    }
+
+    /// number of players which are not downed
+    pub fn standing(&self) -> usize {
+        self.players.len() - self.downed.len()
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        self.players.retain(|player| *player != entity);
+        self.downed.retain(|(downed, _)| *downed != entity);
+    }
+}
+
+/// each near-death in the period counts as this much of struggle
+const NEAR_DEATH_STRUGGLE: f32 = 0.5;
+
+/// Designer set limits of the difficulty director.
+/// Knobs are multipliers, 1.0 is the game as designed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectorBounds {
+    /// seconds of play between adjustments
+    pub period_secs: u64,
+    /// max intensity change per adjustment
+    pub step: f32,
+    /// knob values at the lowest and the highest intensity
+    pub spawn_count: (f32, f32),
+    pub enemy_stats: (f32, f32),
+    pub drop_rate: (f32, f32),
+    pub asteroid_density: (f32, f32),
+    /// performance of a player who is fine with the game as designed
+    pub target_damage_per_minute: f32,
+    pub target_time_to_kill_secs: f32,
+    pub target_shield_uptime: f32,
+}
+
+/// Performance of the players during the current period
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectorStats {
+    pub damage_taken: usize,
+    pub kills: usize,
+    pub time_to_kill: Duration,
+    pub shield_samples: usize,
+    pub shield_up_samples: usize,
+    pub near_deaths: usize,
+}
+
+/// One adjustment of the director, kept for balance review after the run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectorRecord {
+    /// seconds since the run started
+    pub secs: f32,
+    pub stats: DirectorStats,
+    pub struggle: f32,
+    pub intensity: f32,
+    pub spawn_count: f32,
+    pub enemy_stats: f32,
+    pub drop_rate: f32,
+    pub asteroid_density: f32,
+}
+
+/// Dynamic difficulty. Watches how the run goes and moves intensity
+/// from -1 (struggling) to 1 (bored), knobs follow the intensity
+#[derive(Debug, Clone)]
+pub struct Director {
+    pub bounds: DirectorBounds,
+    /// off for daily runs, they must be the same for everyone
+    pub enabled: bool,
+    pub intensity: f32,
+    pub stats: DirectorStats,
+    /// adjustments of the current run
+    pub history: Vec<DirectorRecord>,
+    run_start: Instant,
+    period_start: Instant,
+    pub spawn_count: f32,
+    pub enemy_stats: f32,
+    pub drop_rate: f32,
+    pub asteroid_density: f32,
+}
+
+impl Director {
+    pub fn new(bounds: DirectorBounds) -> Self {
+        let now = TRACKER.lock().unwrap().now();
+        Director {
+            bounds: bounds,
+            enabled: true,
+            intensity: 0.0,
+            stats: DirectorStats::default(),
+            history: vec![],
+            run_start: now,
+            period_start: now,
+            spawn_count: 1.0,
+            enemy_stats: 1.0,
+            drop_rate: 1.0,
+            asteroid_density: 1.0,
+        }
+    }
+
+    /// forget the previous run
+    pub fn restart(&mut self, enabled: bool) {
+        *self = Director::new(self.bounds.clone());
+        self.enabled = enabled;
+    }
+
+    /// how much the players struggle in the finished period,
+    /// positive when they are worse than targets
+    pub fn struggle(&self, period: Duration) -> f32 {
+        let bounds = &self.bounds;
+        let stats = &self.stats;
+        let minutes = period.as_millis() as f32 / 60_000.0;
+        let damage_per_minute = stats.damage_taken as f32 / minutes;
+        let damage_ratio = damage_per_minute / bounds.target_damage_per_minute;
+        let mut res = (damage_ratio - 1.0).min(1.0);
+        if stats.kills > 0 {
+            let time_to_kill = stats.time_to_kill.as_millis() as f32
+                / 1000.0
+                / stats.kills as f32;
+            res += (time_to_kill / bounds.target_time_to_kill_secs - 1.0)
+                .max(-1.0)
+                .min(1.0);
+        }
+        if stats.shield_samples > 0 {
+            let uptime =
+                stats.shield_up_samples as f32 / stats.shield_samples as f32;
+            res += (bounds.target_shield_uptime - uptime)
+                / bounds.target_shield_uptime;
+        }
+        res + stats.near_deaths as f32 * NEAR_DEATH_STRUGGLE
     }
 
-    pub fn set_right(&mut self, is_right: bool) {
-        self.right_released = self.right && !is_right;
-        self.right = is_right;
+    /// Moves intensity at the end of the period.
+    /// Returns the record of the adjustment if there was one
+    pub fn update(&mut self, now: Instant) -> Option<&DirectorRecord> {
+        let period = now - self.period_start;
+        let period_secs = Duration::from_secs(self.bounds.period_secs);
+        if !self.enabled || period < period_secs {
+            return None;
+        }
+        let struggle = self.struggle(period);
+        let step = self.bounds.step;
+        let change = (step * struggle).max(-step).min(step);
+        self.intensity = (self.intensity - change).max(-1.0).min(1.0);
+        let intensity = self.intensity;
+        let knob = |(easy, hard): (f32, f32)| {
+            if intensity >= 0.0 {
+                1.0 + (hard - 1.0) * intensity
+            } else {
+                1.0 + (easy - 1.0) * -intensity
+            }
+        };
+        self.spawn_count = knob(self.bounds.spawn_count);
+        self.enemy_stats = knob(self.bounds.enemy_stats);
+        self.drop_rate = knob(self.bounds.drop_rate);
+        self.asteroid_density = knob(self.bounds.asteroid_density);
+        let stats =
+            std::mem::replace(&mut self.stats, DirectorStats::default());
+        self.history.push(DirectorRecord {
+            secs: (now - self.run_start).as_millis() as f32 / 1000.0,
+            stats: stats,
+            struggle: struggle,
+            intensity: self.intensity,
+            spawn_count: self.spawn_count,
+            enemy_stats: self.enemy_stats,
+            drop_rate: self.drop_rate,
+            asteroid_density: self.asteroid_density,
+        });
+        self.period_start = now;
+        self.history.last()
     }
 }
 
-#[derive(Default, Component)]
-#[storage(NullStorage)]
-pub struct CharacterMarker;
-
 #[derive(Default, Component, Clone, Copy)]
 #[storage(NullStorage)]
 pub struct NebulaMarker;
@@ -781,6 +2365,30 @@ pub struct EnemyMarker;
 #[storage(NullStorage)]
 pub struct AsteroidMarker;
 
+/// Kind of the asteroid and how many times it was already destroyed,
+/// default kind is the plain rock, the first one in asteroids.ron
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Asteroid {
+    pub kind: usize,
+    pub stage: usize,
+}
+
+/// Ship lost all lifes and is deleted this frame.
+/// Its components are still readable until the world is maintained
+#[derive(Debug, Clone, Copy)]
+pub struct ShipDestroyed(pub specs::Entity);
+
+/// Sprite outline of the ship in its local coordinates
+#[derive(Component, Debug, Clone)]
+pub struct ShipOutline(pub Polygon);
+
+/// Textured piece of destroyed ship
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Debris {
+    pub image: AtlasImage,
+    pub size: f32,
+}
+
 #[derive(Default, Component)]
 #[storage(NullStorage)]
 pub struct LightMarker;
@@ -797,8 +2405,12 @@ pub struct Reflection {
     pub times: Option<usize>,
 }
 
-#[derive(Component)]
-pub struct Rocket(pub Instant);
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Rocket {
+    pub kind: EntityType,
+    pub speed: f32,
+    pub target: Option<specs::Entity>,
+}
 
 #[derive(Component)]
 pub struct Coin(pub usize);
@@ -827,6 +2439,9 @@ pub struct SideBulletCollectable;
 #[derive(Component)]
 pub struct SideBulletAbility;
 
+#[derive(Component)]
+pub struct DroneCollectable;
+
 #[derive(Component)]
 pub struct Exp(pub usize);
 
@@ -855,6 +2470,11 @@ impl Lifetime {
         self.rest() > self.lifetime
     }
 
+    /// make it deleted on the next check
+    pub fn expire(&mut self) {
+        self.lifetime = Duration::from_secs(0);
+    }
+
     pub fn rest(&self) -> Duration {
         TRACKER.lock().unwrap().now() - self.start_time
     }
@@ -898,6 +2518,25 @@ pub struct UpgradeCardRaw {
     pub name: String,
     pub description: String,
     pub assigned: Vec<Assigned>,
+    /// names of the cards which should be taken before this one
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// names of the cards which close this branch once taken
+    #[serde(default)]
+    pub excludes: Vec<String>,
+    #[serde(default = "default_max_rank")]
+    pub max_rank: usize,
+    /// weight in offers, rare cards have small values
+    #[serde(default = "default_rarity")]
+    pub rarity: f32,
+}
+
+fn default_max_rank() -> usize {
+    5
+}
+
+fn default_rarity() -> f32 {
+    1.0
 }
 
 pub fn get_avaliable_cards(
@@ -906,29 +2545,115 @@ pub fn get_avaliable_cards(
     name_to_image: &HashMap<String, AtlasImage>,
 ) -> Vec<UpgradeCard> {
     let gun_marker: GunKindMarker = gun.into();
-    let avaliable_cards: Vec<UpgradeCard> = cards
+    let mut raw_cards: Vec<&UpgradeCardRaw> = cards
         .iter()
         .filter(|raw_card| {
             raw_card.assigned.contains(&Assigned::General)
                 || raw_card.assigned.contains(&Assigned::ToGun(gun_marker))
         })
-        .map(|upgrade| {
-            dbg!(&upgrade.name);
-            UpgradeCard {
-                upgrade_type: upgrade.upgrade_type,
-                image: name_to_image[&upgrade.image],
-                name: upgrade.name.clone(),
-                description: upgrade.description.clone(),
-            }
+        .collect();
+    // cards which require unavaliable cards can't be reached
+    loop {
+        let names: Vec<&str> =
+            raw_cards.iter().map(|card| card.name.as_str()).collect();
+        let cards_num = raw_cards.len();
+        raw_cards.retain(|card| {
+            card.requires
+                .iter()
+                .all(|name| names.contains(&name.as_str()))
+        });
+        if raw_cards.len() == cards_num {
+            break;
+        }
+    }
+    load_upgrade_cards(&raw_cards, name_to_image)
+}
+
+/// Converts cards and resolves names of related cards into ids
+pub fn load_upgrade_cards(
+    raw_cards: &[&UpgradeCardRaw],
+    name_to_image: &HashMap<String, AtlasImage>,
+) -> Vec<UpgradeCard> {
+    let name_to_id: HashMap<&str, usize> = raw_cards
+        .iter()
+        .enumerate()
+        .map(|(id, card)| (card.name.as_str(), id))
+        .collect();
+    let ids = |names: &[String]| -> Vec<usize> {
+        names
+            .iter()
+            .filter_map(|name| name_to_id.get(name.as_str()).cloned())
+            .collect()
+    };
+    raw_cards
+        .iter()
+        .map(|upgrade| UpgradeCard {
+            upgrade_type: upgrade.upgrade_type,
+            image: name_to_image[&upgrade.image],
+            name: upgrade.name.clone(),
+            description: upgrade.description.clone(),
+            requires: ids(&upgrade.requires),
+            excludes: ids(&upgrade.excludes),
+            max_rank: upgrade.max_rank,
+            rarity: upgrade.rarity,
+        })
+        .collect()
+}
+
+/// Ids of the cards which can be taken after `picked` ones
+pub fn eligible_upgrades(
+    cards: &[UpgradeCard],
+    picked: &[usize],
+) -> Vec<usize> {
+    let rank = |id: usize| picked.iter().filter(|&&x| x == id).count();
+    (0..cards.len())
+        .filter(|&id| {
+            let card = &cards[id];
+            // exclusion works both ways
+            let excluded = card.excludes.iter().any(|&other| rank(other) > 0)
+                || picked
+                    .iter()
+                    .any(|&other| cards[other].excludes.contains(&id));
+            rank(id) < card.max_rank
+                && card.requires.iter().all(|&required| rank(required) > 0)
+                && !excluded
         })
+        .collect()
+}
+
+/// Draws up to `num` different eligible cards weighted by rarity
+pub fn offer_upgrades<R: Rng>(
+    cards: &[UpgradeCard],
+    picked: &[usize],
+    num: usize,
+    rng: &mut R,
+) -> Vec<usize> {
+    // cards without positive rarity are never offered
+    let mut eligible: Vec<usize> = eligible_upgrades(cards, picked)
+        .into_iter()
+        .filter(|&id| cards[id].rarity > 0.0)
         .collect();
-    avaliable_cards
+    let mut offer = vec![];
+    while offer.len() < num && eligible.len() > 0 {
+        let choosed =
+            match eligible.choose_weighted(rng, |&id| cards[id].rarity) {
+                Ok(&id) => id,
+                Err(_) => break,
+            };
+        eligible.retain(|&id| id != choosed);
+        offer.push(choosed);
+    }
+    offer
 }
 
 /// attach entity positions to some other entity position
 #[derive(Component, Debug, Clone)]
 pub struct AttachPosition(pub specs::Entity);
 
+/// shift from the attached position
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AttachOffset(pub Vector2);
+
 #[derive(Component, Debug, Clone)]
 pub struct AttachAim(pub specs::Entity);
 
@@ -940,6 +2665,56 @@ pub enum GunKind {
     RocketGun(RocketGun),
 }
 
+impl GunKind {
+    /// shoots if the gun is recharged, lazers are handled by their own system
+    pub fn fire(
+        &mut self,
+        entity_type: EntityType,
+        isometry: Isometry3,
+        velocity: Vector2,
+        owner: specs::Entity,
+    ) -> Vec<InsertEvent> {
+        // players guns aren't slowed by time slow
+        let now = TRACKER.lock().unwrap().now_for(entity_type);
+        match self {
+            GunKind::ShotGun(gun) if gun.is_ready_at(now) => {
+                gun.shoot_at(now);
+                gun.spawn_bullets(
+                    entity_type,
+                    isometry,
+                    gun.bullet_speed,
+                    gun.bullets_damage,
+                    velocity,
+                    owner,
+                )
+            }
+            GunKind::Cannon(gun) if gun.is_ready_at(now) => {
+                gun.shoot_at(now);
+                gun.spawn_bullets(
+                    entity_type,
+                    isometry,
+                    gun.bullet_speed,
+                    gun.bullets_damage,
+                    velocity,
+                    owner,
+                )
+            }
+            GunKind::RocketGun(gun) if gun.is_ready_at(now) => {
+                gun.shoot_at(now);
+                gun.spawn_bullets(
+                    entity_type,
+                    isometry,
+                    gun.bullet_speed,
+                    gun.bullets_damage,
+                    velocity,
+                    owner,
+                )
+            }
+            _ => vec![],
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GunKindSave {
     ShotGun(ShotGunSave),
@@ -971,6 +2746,489 @@ impl GunKindSave {
     }
 }
 
+/// Mount turns to the nearest target within its arc
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Turret {
+    /// radians per frame at 60 fps
+    pub turn_rate: f32,
+    /// max turn from the base angle in degrees
+    pub arc: f32,
+    pub range: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HardpointSave {
+    /// in ship sizes, ship looks at -y
+    pub offset: (f32, f32),
+    /// degrees from the ship forward
+    #[serde(default)]
+    pub angle: f32,
+    #[serde(default)]
+    pub turret: Option<Turret>,
+    pub gun: GunKindSave,
+}
+
+impl HardpointSave {
+    /// lazers are beams of the ship itself, they can't be mounted
+    pub fn load(
+        &self,
+        name_to_image: &HashMap<String, AtlasImage>,
+    ) -> Result<Hardpoint, String> {
+        let gun = self.gun.convert(name_to_image);
+        if let GunKind::MultyLazer(_) = gun {
+            return Err("lazers can't be mounted on hardpoints".to_string());
+        }
+        Ok(Hardpoint {
+            offset: Vector2::new(self.offset.0, self.offset.1),
+            angle: self.angle.to_radians(),
+            turret: self.turret,
+            turret_angle: 0.0,
+            gun: gun,
+        })
+    }
+}
+
+/// Gun mount of the ship, the main gun is a mount at the center
+#[derive(Debug, Clone)]
+pub struct Hardpoint {
+    pub offset: Vector2,
+    pub angle: f32,
+    pub turret: Option<Turret>,
+    /// current turn of the turret from the base angle
+    pub turret_angle: f32,
+    pub gun: GunKind,
+}
+
+impl Hardpoint {
+    /// mount at the center of the ship looking forward
+    pub fn main(gun: GunKind) -> Self {
+        Hardpoint {
+            offset: Vector2::new(0.0, 0.0),
+            angle: 0.0,
+            turret: None,
+            turret_angle: 0.0,
+            gun: gun,
+        }
+    }
+
+    /// transform of the mount in the world
+    pub fn isometry(&self, ship: &Isometry3, size: f32) -> Isometry3 {
+        let offset =
+            ship.rotation * Vector3::new(self.offset.x, self.offset.y, 0.0);
+        let angle =
+            ship.rotation.euler_angles().2 + self.angle + self.turret_angle;
+        Isometry3::new(
+            ship.translation.vector + size * offset,
+            Vector3::new(0f32, 0f32, angle),
+        )
+    }
+
+    /// shoots if the gun is recharged
+    pub fn fire(
+        &mut self,
+        entity_type: EntityType,
+        ship: &Isometry3,
+        size: f32,
+        ship_velocity: Vector2,
+        owner: specs::Entity,
+    ) -> Vec<InsertEvent> {
+        let isometry = self.isometry(ship, size);
+        self.gun.fire(entity_type, isometry, ship_velocity, owner)
+    }
+}
+
+/// Projectile guns of the ship, each one fires from its own mount.
+/// Lazers are not mounted, they stay `MultyLazer` of the ship
+#[derive(Component, Debug, Clone, Default)]
+pub struct Hardpoints {
+    pub mounts: Vec<Hardpoint>,
+    /// set by controls or AI, mounts fire while it's on
+    pub firing: bool,
+    /// fixed mounts hold fire, e.g. while AI turns to the target.
+    /// Turrets aim on their own
+    pub hold_fixed: bool,
+}
+
+impl Hardpoints {
+    pub fn new(mounts: Vec<Hardpoint>) -> Self {
+        Hardpoints {
+            mounts: mounts,
+            firing: false,
+            hold_fixed: false,
+        }
+    }
+
+    pub fn shotguns_mut(&mut self) -> impl Iterator<Item = &mut ShotGun> + '_ {
+        self.mounts.iter_mut().filter_map(|mount| match mount.gun {
+            GunKind::ShotGun(ref mut gun) => Some(gun),
+            _ => None,
+        })
+    }
+
+    pub fn rocket_guns_mut(
+        &mut self,
+    ) -> impl Iterator<Item = &mut RocketGun> + '_ {
+        self.mounts.iter_mut().filter_map(|mount| match mount.gun {
+            GunKind::RocketGun(ref mut gun) => Some(gun),
+            _ => None,
+        })
+    }
+
+    /// speed of the main gun bullets to lead targets,
+    /// rockets are guided on their own
+    pub fn bullet_speed(&self) -> Option<f32> {
+        self.mounts.first().and_then(|mount| match mount.gun {
+            GunKind::ShotGun(ref gun) => Some(gun.bullet_speed),
+            GunKind::Cannon(ref gun) => Some(gun.bullet_speed),
+            _ => None,
+        })
+    }
+}
+
+/// How drone keeps near its owner
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum DroneFormation {
+    /// circles around the owner, radius in owner sizes,
+    /// speed in rad/frame at 60 fps
+    Orbit { radius: f32, speed: f32 },
+    /// holds a place relative to the owner, in owner sizes, ship looks at -y
+    Slot { offset: (f32, f32) },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DroneKindSave {
+    pub name: String,
+    pub image: String,
+    pub size: f32,
+    pub health: usize,
+    pub respawn_time: Duration,
+    /// distance at which drone picks targets
+    pub range: f32,
+    pub formation: DroneFormation,
+    pub gun: GunKindSave,
+}
+
+impl DroneKindSave {
+    /// lazers are beams of the ship itself, drones can't carry them
+    pub fn load(
+        &self,
+        name_to_image: &HashMap<String, AtlasImage>,
+    ) -> Result<DroneKind, String> {
+        let gun = self.gun.convert(name_to_image);
+        if let GunKind::MultyLazer(_) = gun {
+            return Err(format!(
+                "lazers can't be mounted on drones, see drone {}",
+                self.name
+            ));
+        }
+        Ok(DroneKind {
+            name: self.name.clone(),
+            image: name_to_image[&self.image],
+            size: self.size,
+            health: self.health,
+            respawn_time: self.respawn_time,
+            range: self.range,
+            formation: self.formation,
+            gun: gun,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DroneKind {
+    pub name: String,
+    pub image: AtlasImage,
+    pub size: f32,
+    pub health: usize,
+    pub respawn_time: Duration,
+    pub range: f32,
+    pub formation: DroneFormation,
+    pub gun: GunKind,
+}
+
+/// shift of orbit phase between drones of one ship
+const DRONE_PHASE_SHIFT: f32 = 2.4;
+
+/// Companion attached to a ship, see `DroneBay`, health is in `Lifes`
+#[derive(Component, Debug, Clone)]
+pub struct Drone {
+    pub owner: specs::Entity,
+    /// index in the owner `DroneBay`
+    pub slot: usize,
+    pub range: f32,
+    pub formation: DroneFormation,
+    /// current angle on the orbit
+    pub phase: f32,
+    pub gun: GunKind,
+}
+
+impl Drone {
+    pub fn new(owner: specs::Entity, slot: usize, kind: &DroneKind) -> Self {
+        Drone {
+            owner: owner,
+            slot: slot,
+            range: kind.range,
+            formation: kind.formation,
+            phase: slot as f32 * DRONE_PHASE_SHIFT,
+            gun: kind.gun.clone(),
+        }
+    }
+
+    /// position relative to the owner
+    pub fn offset(&self, owner_rotation: f32, owner_size: f32) -> Vector2 {
+        let offset = match self.formation {
+            DroneFormation::Orbit { radius, .. } => {
+                radius * Vector2::new(self.phase.cos(), self.phase.sin())
+            }
+            DroneFormation::Slot { offset } => {
+                Rotation2::new(owner_rotation)
+                    * Vector2::new(offset.0, offset.1)
+            }
+        };
+        owner_size * offset
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DroneSlot {
+    pub kind: DroneKind,
+    pub entity: Option<specs::Entity>,
+    pub respawn_at: Instant,
+}
+
+/// Drones of the ship, destroyed ones are respawned on timer
+#[derive(Component, Debug, Clone, Default)]
+pub struct DroneBay {
+    pub slots: Vec<DroneSlot>,
+}
+
+impl DroneBay {
+    pub fn add(&mut self, kind: DroneKind) {
+        self.slots.push(DroneSlot {
+            kind: kind,
+            entity: None,
+            respawn_at: TRACKER.lock().unwrap().now(),
+        });
+    }
+
+    pub fn destroyed(&mut self, slot: usize) {
+        let slot = &mut self.slots[slot];
+        slot.entity = None;
+        slot.respawn_at =
+            TRACKER.lock().unwrap().now() + slot.kind.respawn_time;
+    }
+}
+
+pub const DASH_CHARGES: usize = 1;
+pub const DASH_COOLDOWN_MS: u64 = 2000;
+/// velocity change of the dash
+pub const DASH_IMPULSE: f32 = 0.9;
+/// ship isn't limited by max velocity while dashing
+pub const DASH_DURATION_MS: u64 = 150;
+pub const DASH_INVULNERABILITY_MS: u64 = 300;
+
+/// Quick burst of speed with invulnerability frames,
+/// spent charges are restored one by one
+#[derive(Component, Debug, Clone)]
+pub struct Dash {
+    pub charges: usize,
+    pub max_charges: usize,
+    pub cooldown: Duration,
+    /// charges are restored on unscaled time, dash itself lasts game time
+    recharge_start: Instant,
+    dash_start: Option<Instant>,
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        Dash {
+            charges: DASH_CHARGES,
+            max_charges: DASH_CHARGES,
+            cooldown: Duration::from_millis(DASH_COOLDOWN_MS),
+            recharge_start: TRACKER.lock().unwrap().unscaled_now(),
+            dash_start: None,
+        }
+    }
+}
+
+impl Dash {
+    /// restores charges, called every frame
+    pub fn update(&mut self) {
+        let now = TRACKER.lock().unwrap().unscaled_now();
+        if self.charges >= self.max_charges {
+            self.recharge_start = now;
+            return;
+        }
+        if now.duration_since(self.recharge_start) >= self.cooldown {
+            self.charges += 1;
+            self.recharge_start = now;
+        }
+    }
+
+    /// returns true if there was a charge to spend
+    pub fn dash(&mut self) -> bool {
+        if self.charges == 0 || self.is_active() {
+            return false;
+        }
+        self.charges -= 1;
+        self.dash_start = Some(TRACKER.lock().unwrap().now());
+        true
+    }
+
+    fn since_dash(&self) -> Option<Duration> {
+        self.dash_start
+            .map(|start| TRACKER.lock().unwrap().now().duration_since(start))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.since_dash().map_or(false, |since| {
+            since < Duration::from_millis(DASH_DURATION_MS)
+        })
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        self.since_dash().map_or(false, |since| {
+            since < Duration::from_millis(DASH_INVULNERABILITY_MS)
+        })
+    }
+
+    /// progress of the next charge from 0 to 1
+    pub fn recharge(&self) -> f32 {
+        if self.charges >= self.max_charges {
+            return 1.0;
+        }
+        let elapsed = TRACKER
+            .lock()
+            .unwrap()
+            .unscaled_now()
+            .duration_since(self.recharge_start);
+        (elapsed.as_millis() as f32 / self.cooldown.as_millis() as f32).min(1.0)
+    }
+}
+
+pub const ABILITY_SLOTS: usize = 3;
+pub const BOMB_DAMAGE: usize = 500;
+pub const BOMB_RADIUS: f32 = 12.0;
+/// shield is filled over its maximum
+pub const SHIELD_BURST_OVERCHARGE: f32 = 1.5;
+pub const TIME_SLOW_SCALE: f32 = 0.4;
+/// measured in slowed game time
+pub const TIME_SLOW_DURATION_MS: u64 = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ActiveAbilityKind {
+    /// clears the screen from enemies and their projectiles
+    Bomb,
+    ShieldBurst,
+    TimeSlow,
+}
+
+impl ActiveAbilityKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ActiveAbilityKind::Bomb => "Bomb",
+            ActiveAbilityKind::ShieldBurst => "Shield burst",
+            ActiveAbilityKind::TimeSlow => "Time slow",
+        }
+    }
+
+    fn charges(&self) -> usize {
+        match self {
+            ActiveAbilityKind::Bomb => 1,
+            ActiveAbilityKind::ShieldBurst => 2,
+            ActiveAbilityKind::TimeSlow => 1,
+        }
+    }
+
+    fn cooldown(&self) -> Duration {
+        match self {
+            ActiveAbilityKind::Bomb => Duration::from_secs(40),
+            ActiveAbilityKind::ShieldBurst => Duration::from_secs(15),
+            ActiveAbilityKind::TimeSlow => Duration::from_secs(25),
+        }
+    }
+}
+
+/// Ability triggered by player, spent charges are restored one by one
+#[derive(Debug, Clone)]
+pub struct ActiveAbility {
+    pub kind: ActiveAbilityKind,
+    pub charges: usize,
+    pub max_charges: usize,
+    pub cooldown: Duration,
+    recharge_start: Instant,
+}
+
+impl ActiveAbility {
+    pub fn new(kind: ActiveAbilityKind) -> Self {
+        ActiveAbility {
+            kind: kind,
+            charges: kind.charges(),
+            max_charges: kind.charges(),
+            cooldown: kind.cooldown(),
+            recharge_start: TRACKER.lock().unwrap().unscaled_now(),
+        }
+    }
+
+    /// restores charges, called every frame with `unscaled_now`
+    pub fn update(&mut self, now: Instant) {
+        if self.charges >= self.max_charges {
+            self.recharge_start = now;
+            return;
+        }
+        if now.duration_since(self.recharge_start) >= self.cooldown {
+            self.charges += 1;
+            self.recharge_start = now;
+        }
+    }
+
+    /// returns true if there was a charge to spend
+    pub fn activate(&mut self) -> bool {
+        if self.charges == 0 {
+            return false;
+        }
+        self.charges -= 1;
+        true
+    }
+
+    /// progress of the next charge from 0 to 1
+    pub fn recharge(&self, now: Instant) -> f32 {
+        if self.charges >= self.max_charges {
+            return 1.0;
+        }
+        let elapsed = now.duration_since(self.recharge_start);
+        (elapsed.as_millis() as f32 / self.cooldown.as_millis() as f32).min(1.0)
+    }
+}
+
+/// Active abilities of the character, up to `ABILITY_SLOTS`
+#[derive(Component, Debug, Clone)]
+pub struct AbilityBar {
+    pub slots: Vec<ActiveAbility>,
+}
+
+impl Default for AbilityBar {
+    fn default() -> Self {
+        AbilityBar {
+            slots: vec![ActiveAbility::new(ActiveAbilityKind::Bomb)],
+        }
+    }
+}
+
+impl AbilityBar {
+    /// known ability gets one more charge, new one takes a free slot
+    pub fn add(&mut self, kind: ActiveAbilityKind) {
+        if let Some(ability) =
+            self.slots.iter_mut().find(|ability| ability.kind == kind)
+        {
+            ability.max_charges += 1;
+            ability.charges += 1;
+        } else if self.slots.len() < ABILITY_SLOTS {
+            self.slots.push(ActiveAbility::new(kind));
+        }
+    }
+}
+
 #[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct MultyLazer {
     pub lazers: Vec<Lazer>,
@@ -1044,6 +3302,14 @@ impl MultyLazer {
 pub struct Blast {
     pub blast_damage: usize,
     pub blast_radius: f32,
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+    #[serde(default = "blast_damage_type")]
+    pub damage_type: DamageType,
+}
+
+fn blast_damage_type() -> DamageType {
+    DamageType::Explosive
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -1052,6 +3318,14 @@ pub struct Lazer {
     pub active: bool,
     pub distance: f32,
     pub current_distance: f32,
+    #[serde(default)]
+    pub effect: Option<StatusEffect>,
+    #[serde(default = "lazer_damage_type")]
+    pub damage_type: DamageType,
+}
+
+fn lazer_damage_type() -> DamageType {
+    DamageType::Energy
 }
 
 impl Lazer {
@@ -1061,6 +3335,8 @@ impl Lazer {
             active: false,
             distance: distance,
             current_distance: distance,
+            effect: None,
+            damage_type: DamageType::Energy,
         }
     }
 }
@@ -1073,14 +3349,21 @@ pub trait Gun {
     fn recharge_time(&self) -> Duration;
 
     fn is_ready(&self) -> bool {
-        Instant::now().duration_since(self.recharge_start())
-            >= self.recharge_time()
+        self.is_ready_at(TRACKER.lock().unwrap().now())
+    }
+
+    fn is_ready_at(&self, now: Instant) -> bool {
+        now.duration_since(self.recharge_start()) >= self.recharge_time()
     }
 
     fn shoot(&mut self) -> bool {
-        let result = self.is_ready();
+        self.shoot_at(TRACKER.lock().unwrap().now())
+    }
+
+    fn shoot_at(&mut self, now: Instant) -> bool {
+        let result = self.is_ready_at(now);
         if result {
-            self.set_recharge_start(Instant::now());
+            self.set_recharge_start(now);
         };
         result
     }
@@ -1163,7 +3446,7 @@ impl ShotGun {
         bullet_image: AtlasImage,
     ) -> Self {
         Self {
-            recharge_start: Instant::now(),
+            recharge_start: TRACKER.lock().unwrap().now(),
             recharge_time: recharge_time,
             bullets_damage: bullets_damage,
             side_projectiles_number: side_projectiles_number,
@@ -1290,6 +3573,7 @@ pub struct RocketGun {
     pub recharge_time: Duration,
     pub bullets_damage: usize,
     pub bullet_speed: f32,
+    pub bullet_lifetime: Duration,
     pub bullet_image: AtlasImage,
 }
 
@@ -1298,13 +3582,15 @@ impl RocketGun {
         recharge_time: Duration,
         bullets_damage: usize,
         bullet_speed: f32,
+        bullet_lifetime: Duration,
         bullet_image: AtlasImage,
     ) -> Self {
         Self {
-            recharge_start: Instant::now(),
+            recharge_start: TRACKER.lock().unwrap().now(),
             recharge_time: recharge_time,
             bullets_damage: bullets_damage,
             bullet_speed,
+            bullet_lifetime,
             bullet_image,
         }
     }
@@ -1328,6 +3614,7 @@ impl RocketGunSave {
             self.recharge_time,
             self.bullets_damage,
             self.bullet_speed,
+            self.bullet_lifetime,
             name_to_image[&self.bullet_image],
         )
     }
@@ -1372,7 +3659,7 @@ impl Cannon {
         bullet_image: AtlasImage,
     ) -> Self {
         Self {
-            recharge_start: Instant::now(),
+            recharge_start: TRACKER.lock().unwrap().now(),
             recharge_time: recharge_time,
             bullet_size: bullet_size,
             bullets_damage: bullets_damage,
@@ -1401,7 +3688,7 @@ impl Gun for RocketGun {
         &self,
         entity_type: EntityType,
         isometry: Isometry3,
-        _bullet_speed: f32,
+        bullet_speed: f32,
         bullet_damage: usize,
         _ship_velocity: Vector2,
         owner: specs::Entity,
@@ -1425,6 +3712,8 @@ impl Gun for RocketGun {
                     isometry.rotation.euler_angles().2,
                 ),
                 damage: bullet_damage,
+                speed: bullet_speed,
+                lifetime: self.bullet_lifetime,
                 owner: owner,
                 rocket_image: self.bullet_image,
             };
@@ -0,0 +1,351 @@
+use super::*;
+use geometry::EPS;
+
+fn ship_stats() -> ShipStats {
+    ShipStats {
+        thrust_force: 1.0,
+        torque: 1.0,
+        maneuverability: None,
+        health_regen: 1,
+        shield_regen: 1,
+        max_health: 100,
+        max_shield: 100,
+        damage: 10,
+    }
+}
+
+fn status(kind: StatusKind, magnitude: f32) -> StatusEffect {
+    StatusEffect {
+        kind,
+        duration: Duration::from_secs(1),
+        tick: Duration::from_secs(0),
+        magnitude,
+        stacking: Stacking::Refresh,
+    }
+}
+
+// effects must not overwrite upgraded stats
+#[test]
+fn status_effects_keep_ship_stats() {
+    let now = Instant::now();
+    let mut effects = StatusEffects::default();
+    effects.apply(status(StatusKind::Slow, 0.5), now);
+    let mut stats = ship_stats();
+    // upgrade while the effect is active
+    stats.thrust_force += 1.0;
+    let affected = effects.affect(&stats);
+    assert_eq!(affected.thrust_force, 1.0);
+    assert_eq!(stats.thrust_force, 2.0);
+    effects.update(now + Duration::from_secs(2));
+    assert!(effects.active.is_empty());
+    assert_eq!(effects.affect(&stats).thrust_force, 2.0);
+}
+
+#[test]
+fn stun_and_burn_stats() {
+    let now = Instant::now();
+    let mut effects = StatusEffects::default();
+    effects.apply(status(StatusKind::Stun, 0.0), now);
+    effects.apply(status(StatusKind::Burn, 1.0), now);
+    let affected = effects.affect(&ship_stats());
+    assert_eq!(affected.thrust_force, 0.0);
+    assert_eq!(affected.torque, 0.0);
+    assert_eq!(affected.shield_regen, 0);
+    assert_eq!(affected.health_regen, 0);
+}
+
+fn card(
+    requires: Vec<usize>,
+    excludes: Vec<usize>,
+    rarity: f32,
+) -> UpgradeCard {
+    UpgradeCard {
+        upgrade_type: UpgradeType::AttackSpeed,
+        image: AtlasImage::default(),
+        name: "card".to_string(),
+        description: String::new(),
+        requires,
+        excludes,
+        max_rank: 2,
+        rarity,
+    }
+}
+
+// requirements, exclusions and ranks
+#[test]
+fn upgrades_eligibility() {
+    let cards = vec![
+        card(vec![], vec![], 1.0),
+        card(vec![0], vec![], 1.0),
+        card(vec![], vec![1], 1.0),
+    ];
+    assert_eq!(eligible_upgrades(&cards, &[]), vec![0, 2]);
+    assert_eq!(eligible_upgrades(&cards, &[0]), vec![0, 1, 2]);
+    // max rank is reached
+    assert_eq!(eligible_upgrades(&cards, &[0, 0]), vec![1, 2]);
+    // exclusion works both ways
+    assert_eq!(eligible_upgrades(&cards, &[0, 1]), vec![0, 1]);
+    assert_eq!(eligible_upgrades(&cards, &[0, 2]), vec![0, 2]);
+}
+
+// offered cards are different and eligible
+#[test]
+fn upgrades_offer() {
+    let cards = vec![
+        card(vec![], vec![], 1.0),
+        card(vec![0], vec![], 1.0),
+        card(vec![], vec![], 0.5),
+        card(vec![], vec![], 0.0),
+    ];
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..100 {
+        let offer = offer_upgrades(&cards, &[], 3, &mut rng);
+        assert_eq!(offer.len(), 2);
+        assert!(offer.contains(&0) && offer.contains(&2));
+    }
+    // zero rarity is never offered and doesn't break the draw
+    let cards = vec![card(vec![], vec![], 0.0), card(vec![], vec![], -1.0)];
+    assert!(offer_upgrades(&cards, &[], 3, &mut rng).is_empty());
+}
+
+fn shop_item(perk: Perk) -> ShopItem {
+    ShopItem {
+        perk,
+        name: String::new(),
+        description: String::new(),
+        base_price: 100,
+        price_growth: 1.5,
+        max_level: 3,
+        step: 2.0,
+    }
+}
+
+// price grows with every bought level
+#[test]
+fn shop_price() {
+    let item = shop_item(Perk::ShieldRegen);
+    assert_eq!(item.price(0), 100);
+    assert_eq!(item.price(1), 150);
+    assert_eq!(item.price(2), 225);
+}
+
+#[test]
+fn shop_bonus() {
+    let shop = Shop(vec![shop_item(Perk::ShieldRegen)]);
+    let mut perks = HashMap::new();
+    perks.insert(Perk::ShieldRegen, 2);
+    let macro_game = MacroGame {
+        score_table: vec![],
+        coins: 0,
+        ships_unlocked: vec![],
+        guns_unlocked: vec![],
+        runs: vec![],
+        perks,
+        daily_best: HashMap::new(),
+        mode_scores: HashMap::new(),
+        dead_zone: GAMEPAD_DEAD_ZONE,
+    };
+    assert_eq!(shop.bonus(Perk::ShieldRegen, &macro_game), 4.0);
+    assert_eq!(shop.bonus(Perk::MagnetRadius, &macro_game), 0.0);
+}
+
+// radial dead zone with rescaled deflection
+#[test]
+fn gamepad_dead_zone() {
+    let gamepad = Gamepad {
+        dead_zone: 0.2,
+        ..Gamepad::default()
+    };
+    assert_eq!(gamepad.filter(Vector2::new(0.1, 0.1)).norm(), 0.0);
+    let half = gamepad.filter(Vector2::new(0.0, 0.6));
+    assert!((half - Vector2::new(0.0, 0.5)).norm() < EPS);
+    let diagonal = gamepad.filter(Vector2::new(1.0, 1.0));
+    assert!((diagonal.norm() - 1.0).abs() < EPS);
+    assert!((diagonal.x - diagonal.y).abs() < EPS);
+}
+
+// buttons are pressed only on the first frame
+#[test]
+fn gamepad_just_pressed() {
+    let mut gamepad = Gamepad::default();
+    gamepad.set_buttons(vec![GamepadButton::A]);
+    assert!(gamepad.just_pressed(GamepadButton::A));
+    gamepad.set_buttons(vec![GamepadButton::A, GamepadButton::B]);
+    assert!(!gamepad.just_pressed(GamepadButton::A));
+    assert!(gamepad.just_pressed(GamepadButton::B));
+    assert!(gamepad.held(GamepadButton::A));
+}
+
+// shared bindings are reported once per binding
+#[test]
+fn bindings_conflicts() {
+    let mut bindings = Bindings::default();
+    assert!(bindings.conflicts().is_empty());
+    bindings.bind(Action::Dash, Binding::key(Keycode::W));
+    let conflicts = bindings.conflicts();
+    assert_eq!(conflicts.len(), 1);
+    let (binding, actions) = &conflicts[0];
+    assert_eq!(*binding, Binding::key(Keycode::W));
+    assert_eq!(*actions, vec![Action::ThrustUp, Action::Dash]);
+}
+
+// old configs get defaults only for missing actions
+#[test]
+fn bindings_add_missing() {
+    let mut bindings = Bindings(HashMap::new());
+    bindings
+        .0
+        .insert(Action::Fire, vec![Binding::key(Keycode::F)]);
+    bindings.add_missing();
+    assert_eq!(bindings.get(Action::Fire), &[Binding::key(Keycode::F)]);
+    assert_eq!(
+        bindings.get(Action::ThrustUp),
+        Bindings::default().get(Action::ThrustUp)
+    );
+    assert!(Action::ALL
+        .iter()
+        .all(|action| bindings.0.contains_key(action)));
+}
+
+// struggling players get an easier run, adjustments are recorded
+#[test]
+fn director_update() {
+    let bounds: DirectorBounds =
+        ron::de::from_str(include_str!("../../rons/director.ron")).unwrap();
+    let start = TRACKER.lock().unwrap().now();
+    let mut director = Director::new(bounds.clone());
+    let period = Duration::from_secs(bounds.period_secs);
+    director.stats.damage_taken = 10_000;
+    assert!(director.update(start + period / 2).is_none());
+    let record = director.update(start + period * 2).unwrap().clone();
+    assert!(record.struggle > 0.0);
+    assert_eq!(record.stats.damage_taken, 10_000);
+    assert!((director.intensity + bounds.step).abs() < EPS);
+    let easy = bounds.spawn_count.0;
+    let spawn_count = 1.0 + (easy - 1.0) * bounds.step;
+    assert!((director.spawn_count - spawn_count).abs() < EPS);
+    assert_eq!(director.stats.damage_taken, 0);
+    assert_eq!(director.history.len(), 1);
+    director.restart(false);
+    assert!(director.history.is_empty());
+    director.stats.damage_taken = 10_000;
+    assert!(director.update(start + period * 4).is_none());
+}
+
+fn drone_kind(formation: DroneFormation) -> DroneKind {
+    let gun = ShotGun::new(
+        Duration::from_millis(500),
+        1,
+        0,
+        None,
+        0.0,
+        1.0,
+        0.1,
+        None,
+        Duration::from_secs(1),
+        Duration::from_secs(1),
+        AtlasImage::default(),
+    );
+    DroneKind {
+        name: "drone".to_string(),
+        image: AtlasImage::default(),
+        size: 0.5,
+        health: 10,
+        respawn_time: Duration::from_secs(5),
+        range: 10.0,
+        formation,
+        gun: GunKind::ShotGun(gun),
+    }
+}
+
+// orbit drones are spread by slot, slot drones turn with the owner
+#[test]
+fn drone_offset() {
+    let mut world = specs::World::new();
+    let owner = world.create_entity().build();
+    let orbit = DroneFormation::Orbit {
+        radius: 2.0,
+        speed: 0.1,
+    };
+    let first = Drone::new(owner, 0, &drone_kind(orbit));
+    let offset = first.offset(1.0, 3.0);
+    assert!((offset - Vector2::new(6.0, 0.0)).norm() < EPS);
+    let second = Drone::new(owner, 1, &drone_kind(orbit));
+    let offset = second.offset(0.0, 3.0);
+    assert!((offset.norm() - 6.0).abs() < EPS);
+    assert!((offset - Vector2::new(6.0, 0.0)).norm() > 1.0);
+    let slot = DroneFormation::Slot {
+        offset: (0.0, -1.0),
+    };
+    let wing = Drone::new(owner, 0, &drone_kind(slot));
+    let offset = wing.offset(std::f32::consts::PI / 2.0, 3.0);
+    assert!((offset - Vector2::new(3.0, 0.0)).norm() < EPS);
+}
+
+// dash spends charges, protects for a while and recharges on timer
+#[test]
+fn dash_charges() {
+    let mut dash = Dash {
+        charges: 2,
+        max_charges: 2,
+        ..Dash::default()
+    };
+    let now = TRACKER.lock().unwrap().now();
+    let unscaled_now = TRACKER.lock().unwrap().unscaled_now();
+    assert!(dash.dash());
+    assert_eq!(dash.charges, 1);
+    assert!(dash.is_active() && dash.invulnerable());
+    // no chained dashes while the first one lasts
+    assert!(!dash.dash());
+    assert_eq!(dash.charges, 1);
+    let expired = Duration::from_millis(DASH_INVULNERABILITY_MS + 1);
+    dash.dash_start = Some(now - expired);
+    assert!(!dash.is_active() && !dash.invulnerable());
+    assert!(dash.dash());
+    assert_eq!(dash.charges, 0);
+    dash.dash_start = Some(now - expired);
+    assert!(!dash.dash());
+    assert!(dash.recharge() < 1.0);
+    dash.recharge_start = unscaled_now - dash.cooldown;
+    dash.update();
+    assert_eq!(dash.charges, 1);
+    dash.recharge_start = unscaled_now - dash.cooldown;
+    dash.update();
+    dash.update();
+    assert_eq!(dash.charges, 2);
+    assert!((dash.recharge() - 1.0).abs() < EPS);
+}
+
+// charges are spent one by one and restored one per cooldown
+#[test]
+fn active_ability_charges() {
+    let mut ability = ActiveAbility::new(ActiveAbilityKind::ShieldBurst);
+    let now = TRACKER.lock().unwrap().unscaled_now();
+    assert_eq!(ability.charges, ability.max_charges);
+    assert!((ability.recharge(now) - 1.0).abs() < EPS);
+    while ability.charges > 0 {
+        assert!(ability.activate());
+    }
+    assert!(!ability.activate());
+    ability.update(now);
+    assert_eq!(ability.charges, 0);
+    assert!(ability.recharge(now + ability.cooldown / 2) < 1.0);
+    ability.update(now + ability.cooldown);
+    assert_eq!(ability.charges, 1);
+    ability.update(now + ability.cooldown * 3 / 2);
+    assert_eq!(ability.charges, 1);
+    ability.update(now + ability.cooldown * 2);
+    assert_eq!(ability.charges, ability.max_charges);
+    assert!(ability.activate());
+}
+
+// time slow isn't carried over to the next run
+#[test]
+fn time_slow_reset() {
+    let mut tracker = TimeTracker::new();
+    tracker.slow_down(TIME_SLOW_SCALE, Duration::from_secs(1));
+    assert!(tracker.slowed_until.is_some());
+    tracker.reset_scale();
+    assert!(tracker.slowed_until.is_none());
+    assert!((tracker.scale - 1.0).abs() < EPS);
+}
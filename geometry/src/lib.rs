@@ -1,14 +1,21 @@
 use common::*;
 use ncollide2d::transformation::convex_hull_idx;
+use noise::{NoiseFn, Perlin, Seedable};
 // use ncollide2d::query::closest_points_line_line_parameters;
 use rand::prelude::*;
 use specs::prelude::*;
 use specs_derive::Component;
-use voronois::destruction;
+
+#[cfg(test)]
+mod test;
 
 pub const EPS: f32 = 1E-3;
 pub const SHADOW_LENGTH: f32 = 100f32;
 pub const DECONSTRUCT_SHADR_MIN_R: f32 = 0.1f32;
+/// how many noise features fit along the outline of a shape
+pub const SHAPE_NOISE_FREQUENCY: f64 = 1.5;
+/// shape radius never goes below this fraction of the size
+pub const SHAPE_MIN_RADIUS: f32 = 0.35;
 
 #[derive(Component, Debug, Clone)]
 pub enum Geometry {
@@ -197,6 +204,71 @@ pub fn generate_convex_polygon(samples_num: usize, size: f32) -> Polygon {
     Polygon::new(points)
 }
 
+/// Radial noise outline: star shaped from the origin but may be concave.
+/// `roughness` scales noise, `elongation` stretches the shape along random
+/// axis and `craters` cuts smooth notches into the outline
+pub fn generate_noise_polygon<R: Rng>(
+    samples_num: usize,
+    size: f32,
+    roughness: f32,
+    elongation: f32,
+    craters: usize,
+    rng: &mut R,
+) -> Polygon {
+    let perlin = Perlin::new().set_seed(rng.gen());
+    let offset: f64 = rng.gen_range(0.0, 100.0);
+    let two_pi = 2.0 * std::f32::consts::PI;
+    let craters: Vec<(f32, f32, f32)> = (0..craters)
+        .map(|_| {
+            (
+                rng.gen_range(0.0, two_pi),
+                rng.gen_range(0.2, 0.5),
+                rng.gen_range(0.15, 0.35),
+            )
+        })
+        .collect();
+    let stretch = Rotation2::new(rng.gen_range(0.0, two_pi));
+    let mut points = vec![];
+    for i in 0..samples_num {
+        let angle = two_pi * i as f32 / samples_num as f32;
+        let (x, y) = (angle.cos(), angle.sin());
+        // sample noise along circle so outline is closed
+        let noise = perlin.get([
+            offset + SHAPE_NOISE_FREQUENCY * x as f64,
+            offset + SHAPE_NOISE_FREQUENCY * y as f64,
+        ]) as f32;
+        let mut r = 1.0 + roughness * noise;
+        for &(crater_angle, width, depth) in craters.iter() {
+            let diff = (angle - crater_angle).abs() % two_pi;
+            let diff = diff.min(two_pi - diff);
+            if diff < width {
+                let t = diff / width;
+                r -= depth * (1.0 - t * t);
+            }
+        }
+        let r = r.max(SHAPE_MIN_RADIUS);
+        let mut p = stretch.inverse() * Vector2::new(x, y) * r * size;
+        p.x *= 1.0 + elongation;
+        p.y /= 1.0 + elongation;
+        points.push(Point2::from(stretch * p));
+    }
+    Polygon::new(points)
+}
+
+fn is_convex(points: &[Point2]) -> bool {
+    let n = points.len();
+    let (mut positive, mut negative) = (false, false);
+    for i in 0..n {
+        let a = points[i];
+        let b = points[(i + 1) % n];
+        let c = points[(i + 2) % n];
+        let cross = (b - a).perp(&(c - b));
+        positive |= cross > EPS;
+        negative |= cross < -EPS;
+    }
+    !(positive && negative)
+}
+
 // @vlad TODO refactor (it's copy paste from stack overflow)
 /// get tangent to circle from point
 pub fn get_tangent(
@@ -399,52 +471,301 @@ impl Polygon {
         }
     }
 
+    pub fn area(&self) -> f32 {
+        let n = self.points.len();
+        let mut res = 0f32;
+        for i in 0..n {
+            let p1 = self.points[i];
+            let p2 = self.points[(i + 1) % n];
+            res += p1.x * p2.y - p2.x * p1.y;
+        }
+        (res / 2.0).abs()
+    }
+
+    /// Rotates points by `rot` and moves the mass center to the origin.
+    /// Mass center is rotated too, so callers should offset the body
+    /// position by `rot * center()` to keep the shape in place
     pub fn centralize(&mut self, rot: Rotation2<f32>) {
+        let center = rot * self.mass_center;
         for p in self.points.iter_mut() {
             *p = rot * *p;
-            p.x -= self.mass_center.x;
-            p.y -= self.mass_center.y;
+            p.x -= center.x;
+            p.y -= center.y;
         }
         self.mass_center = Point2::new(0f32, 0f32);
     }
 
-    pub fn deconstruct(&self, bullet: Point2, sites: usize) -> Vec<Polygon> {
+    pub fn is_convex(&self) -> bool {
+        is_convex(&self.points)
+    }
+
+    pub fn convex_hull(&self) -> Polygon {
+        let ids = convex_hull_idx(&self.points);
+        Polygon::new(ids.iter().map(|&i| self.points[i]).collect())
+    }
+
+    /// Split polygon into convex parts (for physics colliders).
+    /// Ear clipping triangulation, then neighbouring parts are merged
+    /// while the result stays convex
+    pub fn convex_parts(&self) -> Vec<Vec<Point2>> {
+        if self.is_convex() {
+            return vec![self.points.clone()];
+        }
+        let points = &self.points;
+        // decomposition works with counter clockwise order
+        let clockwise = signed_area(points) < 0.0;
+        let mut ids: Vec<usize> = (0..points.len()).collect();
+        if clockwise {
+            ids.reverse();
+        }
+        let mut parts: Vec<Vec<usize>> = triangulate(points, ids)
+            .iter()
+            .map(|triangle| triangle.to_vec())
+            .collect();
+        let mut merged = true;
+        while merged {
+            merged = false;
+            'search: for i in 0..parts.len() {
+                for j in i + 1..parts.len() {
+                    if let Some(part) =
+                        merge_parts(points, &parts[i], &parts[j])
+                    {
+                        parts[i] = part;
+                        parts.swap_remove(j);
+                        merged = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+        parts
+            .into_iter()
+            .map(|mut part| {
+                if clockwise {
+                    part.reverse();
+                }
+                part.into_iter().map(|id| points[id]).collect()
+            })
+            .collect()
+    }
+
+    /// Ear clipping triangulation, unlike `TriangulateFromCenter`
+    /// it works for any simple outline, concave ones included
+    pub fn triangulate_concave(&self) -> Triangulation {
+        let points = &self.points;
+        let mut ids: Vec<usize> = (0..points.len()).collect();
+        if signed_area(points) < 0.0 {
+            ids.reverse();
+        }
+        let indicies = triangulate(points, ids)
+            .iter()
+            .flat_map(|triangle| triangle.iter().map(|&id| id as u16))
+            .collect();
+        Triangulation {
+            points: points.clone(),
+            indicies: indicies,
+        }
+    }
+
+    /// Voronoi fracture with sites concentrated around `impact`
+    /// (in polygon coordinates)
+    pub fn deconstruct<R: Rng>(
+        &self,
+        impact: Point2,
+        sites: usize,
+        rng: &mut R,
+    ) -> Vec<Polygon> {
         if self.max_r < 1.2 {
             return vec![];
         }
-        let mut transofrmed_points = self.points.clone();
-        let w_div = self.width + 0.05;
-        let h_div = self.height + 0.05;
-        for p in transofrmed_points.iter_mut() {
-            p.x += self.width / 2.0;
-            p.x /= w_div;
-            p.y += self.height / 2.0;
-            p.y /= h_div;
-        }
-        let mut bullet =
-            bullet + Vector2::new(self.width / 2.0, self.height / 2.0);
-        bullet.x /= w_div;
-        bullet.y /= h_div;
-        let bullet = Point2::from(5.0 * bullet.coords);
-        let (polys, _, _) = destruction(&transofrmed_points, bullet, sites);
+        self.fracture(impact, sites, rng)
+    }
+
+    /// Same as `deconstruct` but without minimal size check
+    pub fn fracture<R: Rng>(
+        &self,
+        impact: Point2,
+        sites: usize,
+        rng: &mut R,
+    ) -> Vec<Polygon> {
+        let sites = impact_sites(impact, 2.0 * self.max_r, sites, rng);
         let mut res = vec![];
-        for poly in polys.iter() {
-            let mut poly = poly.clone();
-            for p in poly.iter_mut() {
-                p.x *= w_div;
-                p.x -= self.width / 2.0;
-                p.y *= h_div;
-                p.y -= self.height / 2.0;
-            }
-            let poly = Polygon::new(poly.to_vec());
-            if poly.min_r > DECONSTRUCT_SHADR_MIN_R {
-                res.push(poly);
+        // clipping works with convex outlines, so concave polygon
+        // is fractured part by part and shards don't fill its pockets
+        for part in self.convex_parts().iter() {
+            for (i, site) in sites.iter().enumerate() {
+                let mut cell = part.clone();
+                for (j, other) in sites.iter().enumerate() {
+                    if i == j || cell.len() < 3 {
+                        continue;
+                    }
+                    let middle =
+                        Point2::from((site.coords + other.coords) / 2.0);
+                    cell = clip_half_plane(&cell, middle, other - site);
+                }
+                if cell.len() < 3 {
+                    continue;
+                }
+                let poly = Polygon::new(cell);
+                if poly.min_r > DECONSTRUCT_SHADR_MIN_R {
+                    res.push(poly);
+                }
             }
         }
         res
     }
 }
 
+/// Twice the signed area, positive for counter clockwise order
+fn signed_area(points: &[Point2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].coords.perp(&points[(i + 1) % n].coords))
+        .sum()
+}
+
+fn in_triangle(p: Point2, a: Point2, b: Point2, c: Point2) -> bool {
+    (b - a).perp(&(p - a)) >= 0.0
+        && (c - b).perp(&(p - b)) >= 0.0
+        && (a - c).perp(&(p - c)) >= 0.0
+}
+
+/// Ear clipping of simple polygon given by counter clockwise `ids`
+fn triangulate(points: &[Point2], mut ids: Vec<usize>) -> Vec<[usize; 3]> {
+    let mut res = vec![];
+    while ids.len() > 3 {
+        let m = ids.len();
+        let mut ear = None;
+        for k in 0..m {
+            let (prev, cur, next) =
+                (ids[(k + m - 1) % m], ids[k], ids[(k + 1) % m]);
+            let (a, b, c) = (points[prev], points[cur], points[next]);
+            let cross = (b - a).perp(&(c - b));
+            if cross.abs() <= EPS * EPS {
+                // degenerate vertex, drop it without triangle
+                ear = Some((k, None));
+                break;
+            }
+            if cross < 0.0 {
+                continue;
+            }
+            let blocked = ids.iter().any(|&id| {
+                let p = points[id];
+                id != prev
+                    && id != cur
+                    && id != next
+                    && p != a
+                    && p != b
+                    && p != c
+                    && in_triangle(p, a, b, c)
+            });
+            if !blocked {
+                ear = Some((k, Some([prev, cur, next])));
+                break;
+            }
+        }
+        match ear {
+            Some((k, triangle)) => {
+                res.extend(triangle);
+                ids.remove(k);
+            }
+            None => {
+                // self intersecting outline, just fan the rest
+                for k in 1..m - 1 {
+                    res.push([ids[0], ids[k], ids[k + 1]]);
+                }
+                return res;
+            }
+        }
+    }
+    if ids.len() == 3 {
+        res.push([ids[0], ids[1], ids[2]]);
+    }
+    res
+}
+
+/// Joins two counter clockwise parts along their common edge
+/// if the result is convex
+fn merge_parts(
+    points: &[Point2],
+    first: &[usize],
+    second: &[usize],
+) -> Option<Vec<usize>> {
+    let (n, m) = (first.len(), second.len());
+    for i in 0..n {
+        let (a, b) = (first[i], first[(i + 1) % n]);
+        for j in 0..m {
+            if second[j] != b || second[(j + 1) % m] != a {
+                continue;
+            }
+            // walk first from b to a, then second past the edge
+            let mut part: Vec<usize> =
+                (1..=n).map(|k| first[(i + k) % n]).collect();
+            part.extend((2..m).map(|k| second[(j + k) % m]));
+            let part_points: Vec<Point2> =
+                part.iter().map(|&id| points[id]).collect();
+            if is_convex(&part_points) {
+                return Some(part);
+            }
+            return None;
+        }
+    }
+    None
+}
+
+/// Sites along random rays from the impact point. Spacing grows with
+/// distance, so cells are small near the impact and form radial cracks
+fn impact_sites<R: Rng>(
+    impact: Point2,
+    size: f32,
+    sites: usize,
+    rng: &mut R,
+) -> Vec<Point2> {
+    let two_pi = 2.0 * std::f32::consts::PI;
+    let rays = ((sites as f32).sqrt().ceil() as usize + 2).min(sites.max(1));
+    let per_ray = (sites + rays - 1) / rays;
+    let step_angle = two_pi / rays as f32;
+    let start_angle = rng.gen_range(0.0, two_pi);
+    let mut res = vec![];
+    for i in 0..rays {
+        let angle = start_angle
+            + step_angle * i as f32
+            + rng.gen_range(-0.3, 0.3) * step_angle;
+        let dir = Vector2::new(angle.cos(), angle.sin());
+        let mut r = 0f32;
+        for j in 0..per_ray {
+            let spacing =
+                2.0 * size * (j + 1) as f32 / (per_ray * per_ray) as f32;
+            r += spacing * rng.gen_range(0.7, 1.3);
+            res.push(impact + r * dir);
+        }
+    }
+    res
+}
+
+/// Keeps part of convex polygon where `(p - point).dot(normal) <= 0`
+fn clip_half_plane(
+    points: &[Point2],
+    point: Point2,
+    normal: Vector2,
+) -> Vec<Point2> {
+    let n = points.len();
+    let mut res = vec![];
+    for i in 0..n {
+        let a = points[i];
+        let b = points[(i + 1) % n];
+        let da = (a - point).dot(&normal);
+        let db = (b - point).dot(&normal);
+        if da <= 0.0 {
+            res.push(a);
+        }
+        if da < 0.0 && db > 0.0 || da > 0.0 && db < 0.0 {
+            res.push(a + (b - a) * (da / (da - db)));
+        }
+    }
+    res
+}
+
 impl TriangulateFromCenter for Polygon {
     fn points(&self) -> &[Point2] {
         &self.points
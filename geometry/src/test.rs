@@ -0,0 +1,137 @@
+use super::*;
+
+fn u_shape() -> Polygon {
+    // mass center is in the gap, so the shape isn't star shaped from it
+    Polygon::new(vec![
+        Point2::new(0.0, 0.0),
+        Point2::new(3.0, 0.0),
+        Point2::new(3.0, 3.0),
+        Point2::new(2.0, 3.0),
+        Point2::new(2.0, 1.0),
+        Point2::new(1.0, 1.0),
+        Point2::new(1.0, 3.0),
+        Point2::new(0.0, 3.0),
+    ])
+}
+
+#[test]
+fn convex_parts_of_convex() {
+    let square = Polygon::new(vec![
+        Point2::new(0.0, 0.0),
+        Point2::new(1.0, 0.0),
+        Point2::new(1.0, 1.0),
+        Point2::new(0.0, 1.0),
+    ]);
+    assert_eq!(square.convex_parts(), vec![square.points.clone()]);
+}
+
+// parts have to cover the polygon exactly
+#[test]
+fn convex_parts_of_concave() {
+    let polygon = u_shape();
+    assert!(!polygon.is_convex());
+    let parts = polygon.convex_parts();
+    assert!(parts.len() >= 3);
+    let mut area = 0.0;
+    for part in parts.iter() {
+        assert!(is_convex(part));
+        assert!(part.iter().all(|p| polygon.points.contains(p)));
+        area += signed_area(part).abs() / 2.0;
+    }
+    assert!((area - polygon.area()).abs() < EPS);
+}
+
+#[test]
+fn convex_parts_of_noise_shapes() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..20 {
+        let polygon = generate_noise_polygon(40, 1.0, 0.5, 0.3, 2, &mut rng);
+        let area: f32 = polygon
+            .convex_parts()
+            .iter()
+            .map(|part| {
+                assert!(is_convex(part));
+                signed_area(part).abs() / 2.0
+            })
+            .sum();
+        assert!((area - polygon.area()).abs() < EPS);
+    }
+}
+
+#[test]
+fn triangulate_concave_covers_polygon() {
+    let polygon = u_shape();
+    let triangulation = polygon.triangulate_concave();
+    let area: f32 = triangulation
+        .indicies
+        .chunks(3)
+        .map(|ids| {
+            let triangle: Vec<Point2> = ids
+                .iter()
+                .map(|&id| triangulation.points[id as usize])
+                .collect();
+            signed_area(&triangle).abs() / 2.0
+        })
+        .sum();
+    assert!((area - polygon.area()).abs() < EPS);
+}
+
+// shards stay inside the outline, pockets aren't filled
+#[test]
+fn fracture_of_concave() {
+    let polygon = u_shape();
+    let mut rng = StdRng::seed_from_u64(0);
+    let shards = polygon.fracture(Point2::new(0.5, 0.5), 6, &mut rng);
+    assert!(!shards.is_empty());
+    let area: f32 = shards.iter().map(|shard| shard.area()).sum();
+    assert!(area <= polygon.area() + EPS);
+    let pocket = Point2::new(1.5, 2.0);
+    for shard in shards.iter() {
+        assert!(shard.points.iter().all(|p| (p - pocket).norm() > 0.4));
+    }
+}
+
+#[test]
+fn clip_square_by_half_plane() {
+    let square = vec![
+        Point2::new(0.0, 0.0),
+        Point2::new(2.0, 0.0),
+        Point2::new(2.0, 2.0),
+        Point2::new(0.0, 2.0),
+    ];
+    let left =
+        clip_half_plane(&square, Point2::new(1.0, 0.0), Vector2::new(1.0, 0.0));
+    assert_eq!(left.len(), 4);
+    assert!(left.iter().all(|p| p.x <= 1.0));
+    assert!((signed_area(&left).abs() / 2.0 - 2.0).abs() < EPS);
+    // whole polygon is on the other side
+    let empty = clip_half_plane(
+        &square,
+        Point2::new(-1.0, 0.0),
+        Vector2::new(1.0, 0.0),
+    );
+    assert!(empty.is_empty());
+}
+
+// rotated shape stays around the rotated mass center
+#[test]
+fn centralize_rotated() {
+    let mut polygon = Polygon::new(vec![
+        Point2::new(1.0, 1.0),
+        Point2::new(3.0, 1.0),
+        Point2::new(3.0, 3.0),
+        Point2::new(1.0, 3.0),
+    ]);
+    let rotation = Rotation2::new(std::f32::consts::FRAC_PI_2);
+    let center = rotation * polygon.center();
+    let expected: Vec<Point2> = polygon
+        .points
+        .iter()
+        .map(|p| rotation * p - center.coords)
+        .collect();
+    polygon.centralize(rotation);
+    for (p, e) in polygon.points.iter().zip(expected.iter()) {
+        assert!((p - e).norm() < EPS);
+    }
+    assert_eq!(polygon.center(), Point2::origin());
+}
@@ -249,7 +249,25 @@ pub fn load_atlas_image(
     }
 }
 
+/// Square image of the whole texture, for entities without sprite
+impl Default for AtlasImage {
+    fn default() -> Self {
+        Self {
+            dim_scales: (1.0, 1.0),
+            fraction_wh: (1.0, 1.0),
+            offset: (0.0, 0.0),
+            transparency: 1.0,
+            color: (1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
 impl AtlasImage {
+    /// sprite quad [-1, 1] is scaled by this to keep aspect ratio
+    pub fn dim_scales(&self) -> (f32, f32) {
+        self.dim_scales
+    }
+
     pub fn new(
         image_name: &str,
         atlas: &SerializedSpriteSheet,
@@ -340,6 +358,37 @@ impl ImageData {
     }
 }
 
+/// Part of atlas sprite, e.g. debris of a ship
+pub struct AtlasPolygonData {
+    positions: VertexBuffer<Vertex>,
+    indices: red::buffer::IndexBuffer,
+}
+
+impl AtlasPolygonData {
+    /// positions and tex_coords are in sprite quad coordinates [-1, 1]
+    pub fn new(
+        gl: &red::GL,
+        positions: &[Point2],
+        tex_coords: &[Point2],
+        indices: &[u16],
+    ) -> Result<Self, String> {
+        let shape: Vec<Vertex> = positions
+            .iter()
+            .zip(tex_coords.iter())
+            .map(|(pos, tex)| Vertex {
+                position: (pos.x, pos.y).into(),
+                tex_coords: ((tex.x + 1.0) / 2.0, (tex.y + 1.0) / 2.0).into(),
+            })
+            .collect();
+        let vertex_buffer = VertexBuffer::new(gl, &shape)?;
+        let index_buffer = red::buffer::IndexBuffer::new(gl, indices)?;
+        Ok(AtlasPolygonData {
+            positions: vertex_buffer,
+            indices: index_buffer,
+        })
+    }
+}
+
 pub fn read_file(filename: &str) -> Result<String, IOError> {
     let mut result_str = String::new();
     let mut rw = RWops::from_file(Path::new(filename), "r")
@@ -619,11 +668,12 @@ impl Canvas {
         pos: Point2,
         speed_ratio: f32,
         direction: Vector2,
+        spread: f32,
     ) {
         self.observer.x = pos.x;
         self.observer.y = pos.y;
         self.observer.z = (1.0 - SPEED_EMA) * self.observer.z
-            + SPEED_EMA * (self.z_far + MAX_ADD_SPEED_Z * speed_ratio);
+            + SPEED_EMA * (self.z_far + MAX_ADD_SPEED_Z * speed_ratio + spread);
         self.perlin_time += 0.1;
         self.camera_wobble /= 2.0;
         self.direction = direction;
@@ -944,6 +994,59 @@ impl Canvas {
         scale: f32,
         with_lights: bool,
         blend: Option<red::Blend>,
+    ) {
+        self.render_atlas_model(
+            gl,
+            viewport,
+            frame,
+            atlas_image,
+            &self.image_model.positions,
+            &self.image_model.indices,
+            model,
+            scale,
+            with_lights,
+            blend,
+        )
+    }
+
+    pub fn render_atlas_polygon(
+        &self,
+        gl: &red::GL,
+        viewport: &red::Viewport,
+        frame: &mut red::Frame,
+        atlas_image: &AtlasImage,
+        polygon_data: &AtlasPolygonData,
+        model: &Isometry3,
+        scale: f32,
+        with_lights: bool,
+        blend: Option<red::Blend>,
+    ) {
+        self.render_atlas_model(
+            gl,
+            viewport,
+            frame,
+            atlas_image,
+            &polygon_data.positions,
+            &polygon_data.indices,
+            model,
+            scale,
+            with_lights,
+            blend,
+        )
+    }
+
+    fn render_atlas_model(
+        &self,
+        gl: &red::GL,
+        viewport: &red::Viewport,
+        frame: &mut red::Frame,
+        atlas_image: &AtlasImage,
+        positions: &VertexBuffer<Vertex>,
+        indices: &red::buffer::IndexBuffer,
+        model: &Isometry3,
+        scale: f32,
+        with_lights: bool,
+        blend: Option<red::Blend>,
     ) {
         let model: [[f32; 4]; 4] = model.to_homogeneous().into();
         let dims = viewport.dimensions();
@@ -957,7 +1060,7 @@ impl Canvas {
             perspective(dims.0, dims.1).to_homogeneous().into();
         let view: [[f32; 4]; 4] =
             get_view(self.observer()).to_homogeneous().into();
-        let vao = &self.image_model.positions.vao;
+        let vao = &positions.vao;
         let program = &self.program_atlas;
         program.set_uniform("model", model);
         program.set_uniform("view", view);
@@ -967,7 +1070,8 @@ impl Canvas {
         program.set_uniform("scale", scale);
         program.set_uniform("offset", atlas_image.offset);
         program.set_uniform("fraction_wh", atlas_image.fraction_wh);
-        program.set_layout(&gl, vao, &[&self.image_model.positions]);
+        program.set_uniform("transparency", atlas_image.transparency);
+        program.set_layout(&gl, vao, &[positions]);
         let draw_params = if with_lights {
             red::DrawParams {
                 draw_type: DrawType::Standart,
@@ -986,12 +1090,7 @@ impl Canvas {
                 ..Default::default()
             }
         };
-        frame.draw(
-            vao,
-            Some(&self.image_model.indices),
-            &program,
-            &draw_params,
-        );
+        frame.draw(vao, Some(indices), &program, &draw_params);
     }
 
     pub fn render(
@@ -1,8 +1,10 @@
 in mediump vec2 v_tex_coords;
 uniform  sampler2D tex;
+uniform mediump float transparency;
 out mediump vec4 fragColor;
 void main() {
     mediump vec4 texture_colors = vec4(texture(tex, v_tex_coords));
+	texture_colors.a = texture_colors.a * transparency;
 	mediump float alpha = texture_colors.a;
 	if (alpha < 0.0001) {
 		discard;
@@ -12,6 +12,7 @@ use sheep::{
     AmethystFormat, InputSprite, MaxrectsOptions, MaxrectsPacker, SimplePacker,
     SpriteAnchor,
 };
+use std::collections::HashMap;
 use std::fs::{self, DirEntry};
 use std::io::Result;
 use std::path::Path;
@@ -23,6 +24,10 @@ use texture_packer::{TexturePacker, TexturePackerConfig};
 
 type ImgBuf = image::ImageBuffer<image::Rgba<u8>, Vec<u8>>;
 
+/// pixels with smaller alpha don't belong to the sprite outline
+const OUTLINE_ALPHA_THRESHOLD: u8 = 100;
+const OUTLINE_MAX_POINTS: usize = 16;
+
 /// return files names and image buffers
 fn images_from_dir(
     dir: &Path,
@@ -71,6 +76,90 @@ where
     }
 }
 
+fn cross(o: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// monotone chain convex hull
+fn convex_hull(mut points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<(f32, f32)> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        for i in 0..points.len() {
+            let p = if pass == 0 {
+                points[i]
+            } else {
+                points[points.len() - 1 - i]
+            };
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Convex outline of opaque pixels in sprite quad coordinates [-1, 1]
+fn alpha_outline(img: &ImgBuf) -> Vec<(f32, f32)> {
+    let (w, h) = img.dimensions();
+    let mut points = vec![];
+    for y in 0..h {
+        let opaque: Vec<u32> = (0..w)
+            .filter(|&x| img.get_pixel(x, y).data[3] > OUTLINE_ALPHA_THRESHOLD)
+            .collect();
+        if let (Some(&left), Some(&right)) = (opaque.first(), opaque.last()) {
+            for &(x, y) in
+                [(left, y), (left, y + 1), (right + 1, y), (right + 1, y + 1)]
+                    .iter()
+            {
+                points.push((x as f32, y as f32));
+            }
+        }
+    }
+    let mut hull = convex_hull(points);
+    // drop vertices which add the least area
+    while hull.len() > OUTLINE_MAX_POINTS {
+        let n = hull.len();
+        let min_id = (0..n)
+            .min_by(|&i, &j| {
+                let area = |i: usize| {
+                    cross(hull[(i + n - 1) % n], hull[i], hull[(i + 1) % n])
+                };
+                area(i).partial_cmp(&area(j)).unwrap()
+            })
+            .unwrap();
+        hull.remove(min_id);
+    }
+    hull.iter()
+        .map(|&(x, y)| (2.0 * x / w as f32 - 1.0, 2.0 * y / h as f32 - 1.0))
+        .collect()
+}
+
+fn write_outlines(outlines_path: &str, names: &[String], images: &[ImgBuf]) {
+    let outlines: HashMap<String, Vec<(f32, f32)>> = names
+        .iter()
+        .cloned()
+        .zip(images.iter().map(alpha_outline))
+        .filter(|(_, outline)| outline.len() >= 3)
+        .collect();
+    let mut outlines_file =
+        File::create(outlines_path).expect("Failed to create outlines file");
+    let outlines_str =
+        ron::ser::to_string(&outlines).expect("Failed to encode outlines file");
+    outlines_file
+        .write_all(outlines_str.as_bytes())
+        .expect("Failed to write outlines file");
+}
+
 fn mimic_sheep(
     atlas_path: &str,
     meta_path: &str,
@@ -148,6 +237,7 @@ fn main() {
         images_from_dir(&assets, "").unwrap();
     names.extend(static_names.iter().cloned());
     paths.extend(static_paths);
+    write_outlines("../assets/outlines.ron", &static_names, &static_images);
     write_sprites(&mut sprites, static_images);
     // for animation in animations {
     //     write_sprites(&mut sprites, &animation)
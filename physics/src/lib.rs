@@ -45,6 +45,52 @@ pub fn calculate_player_ship_spin_for_aim(
     (angle_diff * 10.0 - speed * 55.0)
 }
 
+/// Calculate time when projectile will hit moving target.
+///
+/// `diff` is target position relative to the shooter, `relative_velocity` is
+/// target velocity relative to the shooter (projectiles inherit shooter velocity).
+/// Returns None if projectile is too slow to ever reach the target.
+pub fn intercept_time(
+    diff: Vector2,
+    relative_velocity: Vector2,
+    projectile_speed: f32,
+) -> Option<f32> {
+    // solve |diff + relative_velocity * t| = projectile_speed * t
+    let a = relative_velocity.dot(&relative_velocity)
+        - projectile_speed * projectile_speed;
+    let b = 2.0 * diff.dot(&relative_velocity);
+    let c = diff.dot(&diff);
+    if a.abs() < 1E-6 {
+        if b.abs() < 1E-6 {
+            return None;
+        }
+        let t = -c / b;
+        return if t > 0.0 { Some(t) } else { None };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let sqrt_d = discriminant.sqrt();
+    let t1 = (-b - sqrt_d) / (2.0 * a);
+    let t2 = (-b + sqrt_d) / (2.0 * a);
+    match (t1 > 0.0, t2 > 0.0) {
+        (true, true) => Some(t1.min(t2)),
+        (true, false) => Some(t1),
+        (false, true) => Some(t2),
+        (false, false) => None,
+    }
+}
+
+/// Check if ship with `rotation` looks at `aim` direction within `max_angle` radians
+pub fn is_aligned(aim: Vector2, rotation: f32, max_angle: f32) -> bool {
+    if aim.x == 0.0 && aim.y == 0.0 {
+        return true;
+    }
+    let target_rot = -(-aim.x).atan2(-aim.y);
+    angle_shortest_dist(rotation, target_rot).abs() <= max_angle
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 #[repr(usize)]
 pub enum CollisionId {
@@ -53,6 +99,7 @@ pub enum CollisionId {
     EnemyShip,
     PlayerBullet,
     EnemyBullet,
+    Debris,
 }
 
 #[derive(Deref, DerefMut, Default)]
@@ -2,7 +2,7 @@ use components::*;
 use log::info;
 use common::*;
 
-use std::time::{Instant, Duration};
+use std::time::Duration;
 use specs::prelude::*;
 use specs::Join;
 use nphysics2d::world::World;
@@ -28,14 +28,13 @@ impl<'a> System<'a> for PhysicsSystem {
         ReadStorage<'a, PhysicsComponent>,
         ReadStorage<'a, CharacterMarker>,
         ReadStorage<'a, EnemyMarker>,
-        ReadStorage<'a, Rocket>,
         ReadStorage<'a, Charge>,
         ReadStorage<'a, Chain>,
-        WriteStorage<'a, Spin>,
         Write<'a, World<f32>>,
         WriteExpect<'a, NebulaGrid>,
         WriteExpect<'a, PlanetGrid>,
         Read<'a, AppState>,
+        Write<'a, WorldShift>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -46,14 +45,13 @@ impl<'a> System<'a> for PhysicsSystem {
             physics, 
             character_markers,
             enemies,
-            rockets,
             chargings,
             chains,
-            mut spins,
             mut world,
             mut nebula_grid,
             mut planet_grid,
             app_state,
+            mut world_shift,
         ) = data;
         let time_scaler = normalize_60frame(TRACKER.lock().unwrap().update());
         world.set_timestep(PHYSICS_SIMULATION_TIME * time_scaler);
@@ -76,38 +74,6 @@ impl<'a> System<'a> for PhysicsSystem {
         info!("asteroids: physics started");
         flame::start("physics");
         let char_vec = character_position.translation.vector;
-        { // rockets movements logic
-            for (_entity, iso, vel, spin, phys, rocket) in (&entities, &isometries, &velocities, &mut spins, &physics, &rockets).join() {
-                let rocket_vec = iso.0.translation.vector;
-                let rocket_pos = Point2::new(rocket_vec.x, rocket_vec.y);
-                // let _middle = (rocket_pos + char_vec) / 2.0;
-                let direct = char_vec - rocket_pos.coords;
-                let near_vel = 0.13 * direct.normalize();
-                let rigid_body = world
-                    .rigid_body_mut(phys.body_handle).unwrap();
-                if Instant::now() - rocket.0 > Duration::from_secs(2) {
-                    rigid_body.set_velocity(nphysics2d::math::Velocity::linear(near_vel.x, near_vel.y))
-
-                } else {
-                    let maneuver = if direct.dot(&vel.0) < 0.0 { 20.0 } else {1.0};
-                    let force = Force2::new(maneuver * 0.00014 * (direct).normalize(), 0.0);
-                    rigid_body.apply_force(0, &force, ForceType::Force, true);
-                }
-                // rigid_body.activate();
-
-                let rocket_torque = DT
-                    * calculate_player_ship_spin_for_aim(
-                        Vector2::new(char_vec.x, char_vec.y)
-                            - Vector2::new(rocket_pos.x, rocket_pos.y),
-                        iso.rotation(),
-                        spin.0,
-                    );
-                spin.0 += rocket_torque.max(-MAX_TORQUE).min(MAX_TORQUE);
-                // TODO move to Kinematic system?
-                rigid_body.set_angular_velocity(spin.0);
-            }
-        }
-
         {   // Reactive enemies O(n^2)
             let mut enemies_entities = vec![];
             for (entity, _phys, _enemy) in (&entities, &physics, &enemies).join() {
@@ -171,6 +137,7 @@ impl<'a> System<'a> for PhysicsSystem {
         for (isometry, ()) in (&mut isometries, !&physics).join() {
             isometry.0.translation.vector -= diff;
         }
+        world_shift.0 += Vector2::new(diff.x, diff.y);
         nebula_grid.grid.shift(-diff.x, -diff.y);
         planet_grid.grid.shift(-diff.x, -diff.y);
         for (isometry, velocity, physics_component) in
@@ -112,6 +112,14 @@ pub struct UI {
     hover: Option<usize>,
     // TODO: just use usize instead of Option<usize> and delete entry if needed. wtf?
     pub selectors: HashMap<usize, Option<usize>>,
+    // gamepad controls
+    focused: Option<usize>,
+    confirm: bool,
+    // escape presses the back button of the current screen
+    back: bool,
+    // widgets placed on previous and current frames with their centers
+    focusables: Vec<(usize, Point2)>,
+    placed: Vec<(usize, Point2)>,
     // touch controls
     // for each finger we have id of pressed widget
     #[cfg(any(target_os = "android"))]
@@ -137,6 +145,74 @@ impl UI {
         !self.selectors.contains_key(&selector_id) ||
         self.selectors[&selector_id].is_none()
     }
+
+    /// Call once per frame before widgets are placed.
+    /// D-pad moves focus between widgets of the previous frame, A presses focused one
+    pub fn gamepad_navigation(&mut self, gamepad: &Gamepad) {
+        self.focusables = std::mem::replace(&mut self.placed, vec![]);
+        self.confirm = false;
+        if !gamepad.connected {
+            self.focused = None;
+            return;
+        }
+        let direction = if gamepad.just_pressed(GamepadButton::DPadUp) {
+            Some(Vector2::new(0.0, -1.0))
+        } else if gamepad.just_pressed(GamepadButton::DPadDown) {
+            Some(Vector2::new(0.0, 1.0))
+        } else if gamepad.just_pressed(GamepadButton::DPadLeft) {
+            Some(Vector2::new(-1.0, 0.0))
+        } else if gamepad.just_pressed(GamepadButton::DPadRight) {
+            Some(Vector2::new(1.0, 0.0))
+        } else {
+            None
+        };
+        if let Some(direction) = direction {
+            self.move_focus(direction);
+        }
+        self.confirm = gamepad.just_pressed(GamepadButton::A);
+    }
+
+    /// Escape was pressed on a screen with a back button
+    pub fn press_back(&mut self) {
+        self.back = true;
+    }
+
+    /// Screen with a back button consumes the press
+    pub fn take_back(&mut self) -> bool {
+        std::mem::replace(&mut self.back, false)
+    }
+
+    fn move_focus(&mut self, direction: Vector2) {
+        let current = self.focused.and_then(|focused| {
+            self.focusables.iter().find(|(id, _)| *id == focused)
+        });
+        let current = if let Some((_, position)) = current {
+            *position
+        } else {
+            // focus is lost after screen change, start from the first one
+            self.focused = self.focusables.first().map(|(id, _)| *id);
+            return;
+        };
+        // nearest widget in that direction, side shift costs more
+        let cost = |position: &Point2| {
+            let diff = position - current;
+            let along = diff.dot(&direction);
+            let side = (diff - along * direction).norm();
+            if along > 0.0 {
+                Some(along + 2.0 * side)
+            } else {
+                None
+            }
+        };
+        let next = self
+            .focusables
+            .iter()
+            .filter_map(|(id, position)| cost(position).map(|cost| (*id, cost)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        if let Some((id, _)) = next {
+            self.focused = Some(id);
+        }
+    }
 }
 
 pub struct Selector {
@@ -266,7 +342,13 @@ impl Button {
             }
             ui.hover = Some(self.id);
         }
-        mouse.left_released && hover
+        let center = self.position + Vector2::new(self.width, self.height) / 2.0;
+        ui.placed.push((self.id, center));
+        let focused = ui.focused == Some(self.id);
+        if focused {
+            ui.hover = Some(self.id);
+        }
+        mouse.left_released && hover || focused && ui.confirm
     }
 
     pub fn get_geometry(&self, ui: &UI) -> Vec<Primitive> {
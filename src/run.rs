@@ -2,6 +2,7 @@
 use backtrace::Backtrace;
 #[cfg(any(target_os = "android"))]
 use log::trace;
+use sdl2::controller::{Axis, GameController};
 use sdl2::filesystem::pref_path;
 use sdl2::keyboard::Keycode;
 use sdl2::mouse::MouseButton;
@@ -17,10 +18,14 @@ use std::sync::{Arc, Mutex};
 use crate::gui::{Primitive, UI};
 use crate::setup::*;
 use crate::systems::{
-    AISystem, CollisionSystem, CommonRespawn, ControlSystem, ControllingSystem,
-    DeadScreen, DestroySync, GUISystem, GamePlaySystem, InsertSystem,
-    KinematicSystem, MenuRenderingSystem, RenderingSystem, ScoreTableRendering,
-    SoundSystem, UpgradeGUI, UpgradeControlSystem, Upgrader
+    AISystem, AbilitySystem, ActionsSystem, CollisionSystem, CommonRespawn,
+    ControlSystem, ControllingSystem, ControlsRendering, CoopSystem,
+    DeadScreen, DebrisSystem, DestroySync, DirectorSystem, DroneSystem,
+    GUISystem, GamePlaySystem, GravitySystem, HardpointsSystem, InsertSystem,
+    KinematicSystem, MenuRenderingSystem, PauseMenu, RenderingSystem,
+    RocketGuidanceSystem, ScoreTableRendering, SecondPlayerControl,
+    ShopRendering, SoundSystem, StatusEffectsSystem, UpgradeControlSystem,
+    UpgradeGUI, Upgrader,
 };
 use common::*;
 use components::*;
@@ -35,6 +40,25 @@ use std::fs::File;
 use std::path::Path;
 use telemetry::TimeSpans;
 
+/// every controller button, any of them can be bound to an action
+const GAMEPAD_BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::A,
+    GamepadButton::B,
+    GamepadButton::X,
+    GamepadButton::Y,
+    GamepadButton::Back,
+    GamepadButton::Guide,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::LeftShoulder,
+    GamepadButton::RightShoulder,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+];
+
 pub fn run() -> Result<(), String> {
     #[cfg(any(target_os = "android"))]
     setup_android();
@@ -74,8 +98,11 @@ pub fn run() -> Result<(), String> {
         EventChannel::with_capacity(100);
     let mut primitives_channel: EventChannel<Primitive> =
         EventChannel::with_capacity(100);
+    let mut destroyed_channel: EventChannel<ShipDestroyed> =
+        EventChannel::with_capacity(30);
     let name_to_animation = load_animations(&atlas);
-    load_description(&mut specs_world, &name_to_atlas);
+    let outlines = read_outlines("assets/outlines.ron");
+    load_description(&mut specs_world, &name_to_atlas, &outlines);
     let preloaded_images = preloaded_images(&name_to_atlas, &name_to_animation);
     let size = 10f32;
     let movement_particles = ThreadPin::new(ParticlesData::MovementParticles(
@@ -117,8 +144,22 @@ pub fn run() -> Result<(), String> {
     let mut score_table_dispatcher = DispatcherBuilder::new()
         .with_thread_local(score_table_system)
         .build();
+    let shop_system = ShopRendering::new(primitives_channel.register_reader());
+    let mut shop_dispatcher = DispatcherBuilder::new()
+        .with_thread_local(shop_system)
+        .build();
+    let controls_system =
+        ControlsRendering::new(primitives_channel.register_reader());
+    let mut controls_dispatcher = DispatcherBuilder::new()
+        .with_thread_local(controls_system)
+        .build();
+    let mut pause_dispatcher = DispatcherBuilder::new()
+        .with_thread_local(PauseMenu::default())
+        .build();
     let sound_system = SoundSystem::new(sounds_channel.register_reader());
-    let control_system = ControlSystem::new(keys_channel.register_reader());
+    let control_system = ControlSystem::default();
+    let second_player_control =
+        SecondPlayerControl::new(keys_channel.register_reader());
     let upgrade_control_system = UpgradeControlSystem::default();
     let upgrader = Upgrader::default();
     let gameplay_sytem = GamePlaySystem::default();
@@ -158,11 +199,44 @@ pub fn run() -> Result<(), String> {
         .build();
     let mut dispatcher = DispatcherBuilder::new()
         // .with(control_system, "control_system", &[])
+        .with_thread_local(ActionsSystem::default())
         .with_thread_local(control_system)
+        .with_thread_local(second_player_control)
+        // fires what controls and AI asked for on this frame
+        .with_thread_local(HardpointsSystem::default())
+        // after all systems which can destroy ships
+        .with_thread_local(DebrisSystem::new(
+            destroyed_channel.register_reader(),
+        ))
         .with(gameplay_sytem, "gameplay_system", &[])
         .with(common_respawn, "common_respawn", &[])
         .with(ai_system, "ai_system", &[])
         .with(collision_system, "collision_system", &["ai_system"])
+        .with(
+            AbilitySystem::default(),
+            "ability_system",
+            &["gameplay_system", "collision_system"],
+        )
+        .with(
+            DroneSystem::default(),
+            "drone_system",
+            &["collision_system"],
+        )
+        .with(
+            RocketGuidanceSystem::default(),
+            "rocket_guidance_system",
+            &["collision_system"],
+        )
+        .with(
+            GravitySystem::default(),
+            "gravity_system",
+            &["collision_system"],
+        )
+        .with(
+            StatusEffectsSystem::default(),
+            "status_effects_system",
+            &["gameplay_system", "collision_system"],
+        )
         .with(
             physics_system,
             "physics_system",
@@ -171,6 +245,22 @@ pub fn run() -> Result<(), String> {
                 // "control_system",
                 "gameplay_system",
                 "collision_system",
+                "rocket_guidance_system",
+                "gravity_system",
+            ],
+        )
+        .with(
+            DirectorSystem::new(destroyed_channel.register_reader()),
+            "director_system",
+            &["gameplay_system", "collision_system"],
+        )
+        .with(
+            CoopSystem::default(),
+            "coop_system",
+            &[
+                "collision_system",
+                "gravity_system",
+                "status_effects_system",
             ],
         )
         .with(destroy_sync, "destroy_sync", &[])
@@ -198,15 +288,18 @@ pub fn run() -> Result<(), String> {
     specs_world.add_resource(keys_channel);
     specs_world.add_resource(sounds_channel);
     specs_world.add_resource(insert_channel);
+    specs_world.add_resource(destroyed_channel);
     specs_world.add_resource(ThreadPin::new(context));
     specs_world.add_resource(Mouse {
         wdpi: hdpi,
         hdpi: hdpi,
         ..Mouse::default()
     });
+    specs_world.add_resource(Gamepad::default());
     specs_world.add_resource(ThreadPin::new(canvas));
     specs_world.add_resource(preloaded_images);
     specs_world.add_resource(AppState::Menu);
+    specs_world.add_resource(ExitGame::default());
     specs_world.add_resource(UI::default());
     specs_world.add_resource(primitives_channel);
     specs_world.add_resource(Progress::default());
@@ -215,6 +308,10 @@ pub fn run() -> Result<(), String> {
     // ------------------------------
 
     let mut events_loop = sdl_context.event_pump().unwrap();
+    let game_controller_subsystem = sdl_context.game_controller()?;
+    // controllers are opened on hot plug events,
+    // SDL sends them for already connected ones too
+    let mut controllers: Vec<GameController> = vec![];
     insert_dispatcher.dispatch(&specs_world.res);
     safe_maintain(&mut specs_world);
 
@@ -314,10 +411,48 @@ pub fn run() -> Result<(), String> {
                 }
             }
         }
+        {
+            let dead_zone = specs_world.read_resource::<MacroGame>().dead_zone;
+            let mut gamepad = specs_world.write_resource::<Gamepad>();
+            gamepad.dead_zone = dead_zone;
+            if let Some(controller) = controllers.first() {
+                gamepad.connected = true;
+                gamepad.set_axes(
+                    (
+                        controller.axis(Axis::LeftX),
+                        controller.axis(Axis::LeftY),
+                    ),
+                    (
+                        controller.axis(Axis::RightX),
+                        controller.axis(Axis::RightY),
+                    ),
+                    (
+                        controller.axis(Axis::TriggerLeft),
+                        controller.axis(Axis::TriggerRight),
+                    ),
+                );
+                gamepad.set_buttons(
+                    GAMEPAD_BUTTONS
+                        .iter()
+                        .cloned()
+                        .filter(|button| controller.button(*button))
+                        .collect(),
+                );
+            } else if gamepad.connected {
+                gamepad.disconnect();
+            }
+            specs_world
+                .write_resource::<UI>()
+                .gamepad_navigation(&gamepad);
+        }
         flame::end("control crazyness");
         let app_state = *specs_world.read_resource::<AppState>();
         match app_state {
-            AppState::Menu => menu_dispatcher.dispatch(&specs_world.res),
+            AppState::Menu => {
+                // time slow ends with the run
+                TRACKER.lock().unwrap().reset_scale();
+                menu_dispatcher.dispatch(&specs_world.res);
+            }
             AppState::Play(play_state) => {
                 if let PlayState::Action = play_state {
                     flame::start("dispatch");
@@ -328,6 +463,7 @@ pub fn run() -> Result<(), String> {
                     gui_dispatcher.dispatch(&specs_world.res);
                     flame::end("dispatch");
                 } else {
+                    TRACKER.lock().unwrap().hold();
                     info!("asteroids: upgrade dispatcher");
                     upgrade_gui_dispatcher.dispatch(&specs_world.res);
                 }
@@ -339,8 +475,21 @@ pub fn run() -> Result<(), String> {
             AppState::ScoreTable => {
                 score_table_dispatcher.dispatch(&specs_world.res);
             }
+            AppState::Shop => {
+                shop_dispatcher.dispatch(&specs_world.res);
+            }
+            AppState::Controls => {
+                controls_dispatcher.dispatch(&specs_world.res);
+            }
+            AppState::Paused => {
+                // game clock is stopped, the frozen run is drawn under menu
+                TRACKER.lock().unwrap().hold();
+                pause_dispatcher.dispatch(&specs_world.res);
+                rendering_dispatcher.dispatch(&specs_world.res);
+            }
             AppState::DeadScreen => {
                 info!("dead screen");
+                TRACKER.lock().unwrap().reset_scale();
                 dead_screen_dispatcher.dispatch(&specs_world.res);
                 rendering_dispatcher.dispatch(&specs_world.res);
             }
@@ -359,52 +508,52 @@ pub fn run() -> Result<(), String> {
         flame::end("maintain");
         flame::start("events loop");
         info!("asteroids: events loop");
+        let mut quit = specs_world.read_resource::<ExitGame>().0;
         for event in events_loop.poll_iter() {
             use sdl2::event::Event;
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
+                Event::Quit { .. } => quit = true,
+                Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => {
-                    *running = false;
-                    use ron::ser::{to_string_pretty, PrettyConfig};
-                    use std::io::Write;
-                    // use serde::Serialize;
-                    let pretty = PrettyConfig {
-                        depth_limit: 2,
-                        separate_tuple_members: true,
-                        enumerate_arrays: true,
-                        ..PrettyConfig::default()
-                    };
-                    let s = to_string_pretty(
-                        &*specs_world.write_resource::<MacroGame>(),
-                        pretty,
-                    )
-                    .expect("Serialization failed");
-                    #[cfg(any(target_os = "android"))]
-                    let pref = pref_path("vlad", "twenty_ateroids")
-                        .expect("failed to get pref path");
-                    let file = "rons/macro_game.ron";
-                    #[cfg(any(target_os = "android"))]
-                    let file = format!("{}/{}", pref, file);
-                    // let mut rw = RWops::from_file(Path::new(&file), "r+").expect("failed to load macro game");
-                    #[cfg(any(target_os = "android"))]
-                    trace!("starting opening");
-                    eprintln!("{}", s);
-                    if let Ok(mut rw) = RWops::from_file(Path::new(&file), "w+")
-                    {
-                        rw.write(s.as_bytes())
-                            .expect("failed to load macro game");
-                    } else {
-                        let mut rw = RWops::from_file(Path::new(&file), "w")
-                            .expect("failed to load macro game");
-                        rw.write(s.as_bytes()).expect("failed to write");
+                    let mut app_state =
+                        specs_world.write_resource::<AppState>();
+                    match *app_state {
+                        AppState::Play(PlayState::Action) => {
+                            *app_state = AppState::Paused;
+                        }
+                        AppState::Paused => {
+                            *app_state = AppState::Play(PlayState::Action);
+                        }
+                        AppState::Menu => quit = true,
+                        // other screens go back with their own buttons
+                        _ => specs_world.write_resource::<UI>().press_back(),
                     }
-                    flame::dump_html(
-                        &mut File::create("flame-graph.html").unwrap(),
-                    )
-                    .unwrap();
+                }
+                // don't let the run go on while player is away
+                Event::Window {
+                    win_event: sdl2::event::WindowEvent::FocusLost,
+                    ..
+                }
+                | Event::AppWillEnterBackground { .. } => pause(&specs_world),
+                Event::ControllerDeviceAdded { which, .. } => {
+                    match game_controller_subsystem.open(which) {
+                        Ok(controller) => {
+                            info!(
+                                "asteroids: controller {}",
+                                controller.name()
+                            );
+                            controllers.push(controller);
+                        }
+                        Err(err) => {
+                            info!("asteroids: can't open controller {}", err);
+                        }
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    controllers
+                        .retain(|controller| controller.instance_id() != which);
                 }
                 sdl2::event::Event::Window {
                     win_event: sdl2::event::WindowEvent::Resized(w, h),
@@ -420,6 +569,10 @@ pub fn run() -> Result<(), String> {
                 _ => (),
             }
         }
+        if quit {
+            *running = false;
+            save_on_exit(&specs_world);
+        }
         flame::end("events loop");
         // ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
         flame::end("loop");
@@ -430,3 +583,45 @@ pub fn run() -> Result<(), String> {
 
     Ok(())
 }
+
+/// pauses the run, other states are left as is
+fn pause(specs_world: &SpecsWorld) {
+    let mut app_state = specs_world.write_resource::<AppState>();
+    if let AppState::Play(PlayState::Action) = *app_state {
+        *app_state = AppState::Paused;
+    }
+}
+
+/// writes progress of the macro game
+fn save_on_exit(specs_world: &SpecsWorld) {
+    use ron::ser::{to_string_pretty, PrettyConfig};
+    use std::io::Write;
+    // use serde::Serialize;
+    let pretty = PrettyConfig {
+        depth_limit: 2,
+        separate_tuple_members: true,
+        enumerate_arrays: true,
+        ..PrettyConfig::default()
+    };
+    let s =
+        to_string_pretty(&*specs_world.write_resource::<MacroGame>(), pretty)
+            .expect("Serialization failed");
+    #[cfg(any(target_os = "android"))]
+    let pref =
+        pref_path("vlad", "twenty_ateroids").expect("failed to get pref path");
+    let file = "rons/macro_game.ron";
+    #[cfg(any(target_os = "android"))]
+    let file = format!("{}/{}", pref, file);
+    // let mut rw = RWops::from_file(Path::new(&file), "r+").expect("failed to load macro game");
+    #[cfg(any(target_os = "android"))]
+    trace!("starting opening");
+    eprintln!("{}", s);
+    if let Ok(mut rw) = RWops::from_file(Path::new(&file), "w+") {
+        rw.write(s.as_bytes()).expect("failed to load macro game");
+    } else {
+        let mut rw = RWops::from_file(Path::new(&file), "w")
+            .expect("failed to load macro game");
+        rw.write(s.as_bytes()).expect("failed to write");
+    }
+    flame::dump_html(&mut File::create("flame-graph.html").unwrap()).unwrap();
+}
@@ -3,8 +3,8 @@ use backtrace::Backtrace;
 use common::*;
 use components::*;
 use gfx_h::{
-    load_atlas_image, Canvas, GeometryData, GlyphVertex, ParticlesData,
-    TextData, TextVertexBuffer, WorldTextData,
+    load_atlas_image, AtlasPolygonData, Canvas, GeometryData, GlyphVertex,
+    ParticlesData, TextData, TextVertexBuffer, WorldTextData,
 };
 use glyph_brush::*;
 #[cfg(any(target_os = "android"))]
@@ -27,6 +27,8 @@ use std::time::Duration;
 use telemetry::TeleGraph;
 
 const NEBULAS_NUM: usize = 2usize;
+const BINDINGS_FILE: &str = "bindings.ron";
+const DIRECTOR_HISTORY_FILE: &str = "director_history.ron";
 
 pub fn preloaded_images(
     name_to_atlas: &HashMap<String, AtlasImage>,
@@ -63,6 +65,9 @@ pub fn preloaded_images(
         coin: name_to_atlas["coin"],
         health: name_to_atlas["life"],
         side_bullet_ability: name_to_atlas["side_bullets_ability"],
+        bomb_ability: name_to_atlas["bomb"],
+        shield_burst_ability: name_to_atlas["shield_upgrade"],
+        time_slow_ability: name_to_atlas["rift"],
         exp: name_to_atlas["exp"],
         bar: name_to_atlas["bar"],
         upg_bar: name_to_atlas["upg_bar"],
@@ -209,6 +214,10 @@ pub fn setup_telegraph() -> TeleGraph {
         Point3::new(0.5, 0.1, 0.1),
     );
     telegraph.set_color("clear".to_string(), Point3::new(0.0, 0.6, 0.0));
+    telegraph.set_color(
+        "director intensity".to_string(),
+        Point3::new(1.0, 0.5, 0.0),
+    );
     telegraph
 }
 #[cfg(any(target_os = "android"))]
@@ -303,6 +312,20 @@ pub fn read_atlas(path: &str) -> SerializedSpriteSheet {
     parsed
 }
 
+/// convex sprite outlines in sprite quad coordinates, written by packer
+pub fn read_outlines(path: &str) -> HashMap<String, Vec<(f32, f32)>> {
+    let content = just_read(path).unwrap();
+    let parsed: HashMap<String, Vec<(f32, f32)>> = match from_str(&content) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Failed to load outlines: {}", e);
+
+            std::process::exit(1);
+        }
+    };
+    parsed
+}
+
 pub fn setup_images(
     atlas: &SerializedSpriteSheet,
 ) -> HashMap<String, AtlasImage> {
@@ -319,7 +342,9 @@ pub fn data_setup(specs_world: &mut SpecsWorld) {
     specs_world.register::<Isometry>();
     specs_world.register::<Velocity>();
     specs_world.register::<CharacterMarker>();
+    specs_world.register::<Player>();
     specs_world.register::<AsteroidMarker>();
+    specs_world.register::<Asteroid>();
     specs_world.register::<Rocket>();
     specs_world.register::<RocketGun>();
     specs_world.register::<Projectile>();
@@ -328,8 +353,16 @@ pub fn data_setup(specs_world: &mut SpecsWorld) {
     specs_world.register::<ThreadPin<ImageData>>();
     specs_world.register::<AtlasImage>();
     specs_world.register::<ThreadPin<GeometryData>>();
+    specs_world.register::<ThreadPin<AtlasPolygonData>>();
     specs_world.register::<Spin>();
     specs_world.register::<AttachPosition>();
+    specs_world.register::<AttachOffset>();
+    specs_world.register::<AttachAim>();
+    specs_world.register::<Hardpoints>();
+    specs_world.register::<Drone>();
+    specs_world.register::<DroneBay>();
+    specs_world.register::<Dash>();
+    specs_world.register::<AbilityBar>();
     specs_world.register::<ShotGun>();
     specs_world.register::<Cannon>();
     specs_world.register::<MultyLazer>();
@@ -343,6 +376,7 @@ pub fn data_setup(specs_world: &mut SpecsWorld) {
     specs_world.register::<Coin>();
     specs_world.register::<SideBulletCollectable>();
     specs_world.register::<SideBulletAbility>();
+    specs_world.register::<DroneCollectable>();
     specs_world.register::<DoubleCoinsCollectable>();
     specs_world.register::<DoubleCoinsAbility>();
     specs_world.register::<DoubleExpCollectable>();
@@ -352,6 +386,9 @@ pub fn data_setup(specs_world: &mut SpecsWorld) {
     specs_world.register::<CollectableMarker>();
     specs_world.register::<PhysicsComponent>();
     specs_world.register::<Polygon>();
+    specs_world.register::<ShipOutline>();
+    specs_world.register::<Debris>();
+    specs_world.register::<GravityWell>();
     specs_world.register::<ThreadPin<sdl2::mixer::Chunk>>();
     specs_world.register::<ThreadPin<SoundData>>();
     specs_world.register::<Lifes>();
@@ -362,15 +399,22 @@ pub fn data_setup(specs_world: &mut SpecsWorld) {
     specs_world.register::<PlanetMarker>();
     specs_world.register::<Damage>();
     specs_world.register::<AI>();
+    specs_world.register::<Steering>();
+    specs_world.register::<Skill>();
+    specs_world.register::<Perception>();
     specs_world.register::<ThreadPin<ParticlesData>>();
     specs_world.register::<ShipStats>();
     specs_world.register::<Animation>();
     specs_world.register::<Charge>();
     specs_world.register::<Chain>();
+    specs_world.register::<SquadMember>();
     specs_world.register::<LazerConnect>();
     specs_world.register::<SoundPlacement>();
     specs_world.register::<Rift>();
     specs_world.register::<DamageFlash>();
+    specs_world.register::<StatusEffects>();
+    specs_world.register::<Resistances>();
+    specs_world.register::<DamageType>();
     specs_world.register::<TextComponent>();
     specs_world.register::<Position2D>();
     specs_world.register::<ReflectBulletCollectable>();
@@ -381,6 +425,7 @@ pub fn data_setup(specs_world: &mut SpecsWorld) {
     specs_world.add_resource(Pallete::new());
     specs_world.add_resource(UIState::default());
     specs_world.add_resource(BodiesMap::new());
+    specs_world.add_resource(WorldShift::default());
     let spawned_upgrades: SpawnedUpgrades = vec![];
     specs_world.add_resource(spawned_upgrades);
     let touches: Touches = [None; FINGER_NUMBER];
@@ -390,6 +435,7 @@ pub fn data_setup(specs_world: &mut SpecsWorld) {
 pub fn load_description(
     specs_world: &mut SpecsWorld,
     name_to_atlas: &HashMap<String, AtlasImage>,
+    outlines: &HashMap<String, Vec<(f32, f32)>>,
 ) {
     // load .ron files with tweaks
     #[derive(Debug, Serialize, Deserialize)]
@@ -399,20 +445,23 @@ pub fn load_description(
         player_ships: Vec<ShipKindSave>,
         player_guns: Vec<GunKindSave>,
         enemies: Vec<EnemyKindSave>,
+        #[serde(default)]
+        drones: Vec<DroneKindSave>,
     }
 
     fn process_description(
         description_save: DescriptionSave,
         name_to_atlas: &HashMap<String, AtlasImage>,
-    ) -> Description {
-        Description {
+        outlines: &HashMap<String, Vec<(f32, f32)>>,
+    ) -> Result<Description, String> {
+        Ok(Description {
             gun_costs: description_save.gun_costs,
             ship_costs: description_save.ship_costs,
             player_ships: description_save
                 .player_ships
                 .iter()
                 .map(|x| x.clone().load(name_to_atlas))
-                .collect(),
+                .collect::<Result<_, _>>()?,
             player_guns: description_save
                 .player_guns
                 .iter()
@@ -421,25 +470,52 @@ pub fn load_description(
             enemies: description_save
                 .enemies
                 .iter()
-                .map(|enemy| load_enemy(enemy, name_to_atlas))
-                .collect(),
-        }
+                .map(|enemy| load_enemy(enemy, name_to_atlas, outlines))
+                .collect::<Result<_, _>>()?,
+            drones: description_save
+                .drones
+                .iter()
+                .map(|drone| drone.load(name_to_atlas))
+                .collect::<Result<_, _>>()?,
+        })
     }
 
     fn load_enemy(
         enemy_save: &EnemyKindSave,
         name_to_atlas: &HashMap<String, AtlasImage>,
-    ) -> EnemyKind {
+        outlines: &HashMap<String, Vec<(f32, f32)>>,
+    ) -> Result<EnemyKind, String> {
         dbg!(&enemy_save.image_name);
-        EnemyKind {
+        let image = name_to_atlas[&enemy_save.image_name];
+        // whole sprite quad if there is no outline
+        let quad = vec![(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        let outline = outlines.get(&enemy_save.image_name).unwrap_or(&quad);
+        let (w, h) = image.dim_scales();
+        let outline = Polygon::new(
+            outline
+                .iter()
+                .map(|&(x, y)| Point2::new(w * x, h * y))
+                .collect(),
+        );
+        Ok(EnemyKind {
             ai_kind: enemy_save.ai_kind.clone(),
             gun_kind: enemy_save.gun_kind.convert(name_to_atlas),
             ship_stats: enemy_save.ship_stats,
             size: enemy_save.size,
-            image: name_to_atlas[&enemy_save.image_name],
+            image: image,
             snake: enemy_save.snake,
             rift: enemy_save.rift.clone(),
-        }
+            steering: enemy_save.steering,
+            skill: enemy_save.skill,
+            perception: enemy_save.perception,
+            resistances: enemy_save.resistances,
+            outline: outline,
+            hardpoints: enemy_save
+                .hardpoints
+                .iter()
+                .map(|hardpoint| hardpoint.load(name_to_atlas))
+                .collect::<Result<_, _>>()?,
+        })
     }
     #[derive(Debug, Serialize, Deserialize)]
     pub struct EnemyKindSave {
@@ -451,6 +527,16 @@ pub fn load_description(
         pub snake: Option<usize>,
         #[serde(default)]
         pub rift: Option<Rift>,
+        #[serde(default)]
+        pub steering: Steering,
+        #[serde(default)]
+        pub skill: Skill,
+        #[serde(default)]
+        pub perception: Perception,
+        #[serde(default)]
+        pub resistances: Resistances,
+        #[serde(default)]
+        pub hardpoints: Vec<HardpointSave>,
     };
     #[cfg(not(target_os = "android"))]
     let file = just_read("rons/desc.ron").unwrap();
@@ -469,7 +555,14 @@ pub fn load_description(
     for (id, enemy) in desc.enemies.iter().enumerate() {
         enemy_name_to_id.insert(enemy.image_name.clone(), id);
     }
-    let desc = process_description(desc, &name_to_atlas);
+    let desc = match process_description(desc, &name_to_atlas, outlines) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Failed to load config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let drones_count = desc.drones.len();
     specs_world.add_resource(desc);
     let file = include_str!("../rons/upgrades.ron");
     let upgrades_all: Vec<UpgradeCardRaw> = match from_str(file) {
@@ -480,23 +573,33 @@ pub fn load_description(
             std::process::exit(1);
         }
     };
-    let upgrades: Vec<UpgradeCard> = upgrades_all
+    // drone cards point into description drones
+    let upgrades_all: Vec<&UpgradeCardRaw> = upgrades_all
         .iter()
-        .map(|upgrade| {
-            dbg!(&upgrade.image);
-            UpgradeCard {
-                upgrade_type: upgrade.upgrade_type,
-                image: name_to_atlas[&upgrade.image],
-                name: upgrade.name.clone(),
-                description: upgrade.description.clone(),
+        .filter(|card| match card.upgrade_type {
+            UpgradeType::Drone(kind) if kind >= drones_count => {
+                println!("Skipping upgrade {}: no drone {}", card.name, kind);
+                false
             }
+            _ => true,
         })
         .collect();
+    let upgrades: Vec<UpgradeCard> =
+        load_upgrade_cards(&upgrades_all, &name_to_atlas);
     let avaliable_upgrades = upgrades;
     specs_world.add_resource(avaliable_upgrades);
+    specs_world.add_resource(RunUpgrades::default());
+    specs_world.add_resource(RunMode::default());
+    specs_world.add_resource(RunRngs::default());
+    specs_world.add_resource(GameMode::default());
+    specs_world.add_resource(RunTimer::default());
+    specs_world.add_resource(Coop::default());
+    specs_world.add_resource(Actions::default());
+    specs_world.add_resource(load_bindings());
     pub fn wave_load(
         wave: &WaveSave,
         enemy_name_to_id: &HashMap<String, usize>,
+        asteroid_name_to_id: &HashMap<String, usize>,
     ) -> Wave {
         let distribution: Vec<(usize, f32)> = wave
             .distribution
@@ -511,14 +614,52 @@ pub fn load_description(
             .iter()
             .map(|p| (enemy_name_to_id[&p.0], p.1))
             .collect();
+        let squads: Vec<Squad> = wave
+            .squads
+            .iter()
+            .map(|squad| Squad {
+                leader: enemy_name_to_id[&squad.leader],
+                followers: squad
+                    .followers
+                    .iter()
+                    .map(|p| (enemy_name_to_id[&p.0], p.1))
+                    .collect(),
+                formation: squad.formation,
+                spacing: squad.spacing,
+            })
+            .collect();
+        let asteroids: Vec<(usize, f32)> = wave
+            .asteroids
+            .iter()
+            .map(|p| (asteroid_name_to_id[&p.0], p.1))
+            .collect();
         Wave {
             distribution: distribution,
             ships_number: wave.ships_number,
             const_distribution: const_distribution,
             iterations: wave.iterations,
+            squads: squads,
+            asteroids: asteroids,
         }
     }
     #[cfg(target_os = "android")]
+    let file = include_str!("../rons/asteroids.ron");
+    #[cfg(not(target_os = "android"))]
+    let file = &just_read("rons/asteroids.ron").unwrap();
+    let asteroid_kinds: AsteroidKinds = match from_str(file) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Failed to load config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let asteroid_name_to_id: HashMap<String, usize> = asteroid_kinds
+        .0
+        .iter()
+        .enumerate()
+        .map(|(id, kind)| (kind.name.clone(), id))
+        .collect();
+    #[cfg(target_os = "android")]
     let file = include_str!("../rons/waves.ron");
     #[cfg(not(target_os = "android"))]
     let file = &just_read("rons/waves.ron").unwrap();
@@ -533,10 +674,56 @@ pub fn load_description(
         waves
             .0
             .iter()
-            .map(|p| wave_load(p, &enemy_name_to_id))
+            .map(|p| wave_load(p, &enemy_name_to_id, &asteroid_name_to_id))
             .collect(),
     );
     specs_world.add_resource(waves);
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct GravityWellKindsSave(Vec<GravityWellKindSave>);
+    #[cfg(target_os = "android")]
+    let file = include_str!("../rons/gravity_wells.ron");
+    #[cfg(not(target_os = "android"))]
+    let file = &just_read("rons/gravity_wells.ron").unwrap();
+    let gravity_wells: GravityWellKindsSave = match from_str(file) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Failed to load config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let gravity_wells = GravityWellKinds(
+        gravity_wells
+            .0
+            .into_iter()
+            .map(|kind| kind.load(name_to_atlas))
+            .collect(),
+    );
+    specs_world.add_resource(asteroid_kinds);
+    specs_world.add_resource(gravity_wells);
+    #[cfg(target_os = "android")]
+    let file = include_str!("../rons/shop.ron");
+    #[cfg(not(target_os = "android"))]
+    let file = &just_read("rons/shop.ron").unwrap();
+    let shop: Shop = match from_str(file) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Failed to load config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    specs_world.add_resource(shop);
+    #[cfg(target_os = "android")]
+    let file = include_str!("../rons/director.ron");
+    #[cfg(not(target_os = "android"))]
+    let file = &just_read("rons/director.ron").unwrap();
+    let director_bounds: DirectorBounds = match from_str(file) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("Failed to load config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    specs_world.add_resource(Director::new(director_bounds));
     specs_world.add_resource(upgrades_all);
     specs_world.add_resource(CurrentWave::default());
 
@@ -568,3 +755,63 @@ pub fn load_description(
         specs_world.add_resource(macro_game);
     }
 }
+
+/// controls and run records are per user, so they are kept
+/// in the pref directory
+fn pref_file(name: &str) -> String {
+    match sdl2::filesystem::pref_path("vlad", "twenty_ateroids") {
+        Ok(pref) => Path::new(&pref).join(name).to_string_lossy().into_owned(),
+        Err(e) => {
+            println!("Failed to get pref path: {}", e);
+            format!("rons/{}", name)
+        }
+    }
+}
+
+/// saved controls or defaults if there are none yet
+fn load_bindings() -> Bindings {
+    let file = pref_file(BINDINGS_FILE);
+    let mut bindings_str = String::new();
+    if let Ok(mut rw) = RWops::from_file(Path::new(&file), "r") {
+        if rw.read_to_string(&mut bindings_str).is_ok() {
+            match from_str::<Bindings>(&bindings_str) {
+                Ok(mut bindings) => {
+                    bindings.add_missing();
+                    return bindings;
+                }
+                Err(e) => println!("Failed to load bindings: {}", e),
+            }
+        }
+    }
+    Bindings::default()
+}
+
+pub fn save_bindings(bindings: &Bindings) {
+    use ron::ser::{to_string_pretty, PrettyConfig};
+    use std::io::Write;
+    let s = to_string_pretty(bindings, PrettyConfig::default())
+        .expect("Serialization failed");
+    let file = pref_file(BINDINGS_FILE);
+    match RWops::from_file(Path::new(&file), "w") {
+        Ok(mut rw) => {
+            rw.write(s.as_bytes()).expect("failed to write bindings");
+        }
+        Err(e) => println!("Failed to save bindings: {}", e),
+    }
+}
+
+/// adjustments of the finished run for balance review
+pub fn save_director_history(director: &Director) {
+    use ron::ser::{to_string_pretty, PrettyConfig};
+    use std::io::Write;
+    let s = to_string_pretty(&director.history, PrettyConfig::default())
+        .expect("Serialization failed");
+    let file = pref_file(DIRECTOR_HISTORY_FILE);
+    match RWops::from_file(Path::new(&file), "w") {
+        Ok(mut rw) => {
+            rw.write(s.as_bytes())
+                .expect("failed to write director history");
+        }
+        Err(e) => println!("Failed to save director history: {}", e),
+    }
+}
@@ -0,0 +1,168 @@
+use sdl2::keyboard::Keycode;
+
+use super::*;
+use log::info;
+
+/// Keys of the second player abilities, by slot
+pub const SECOND_PLAYER_ABILITY_KEYS: [Keycode; ABILITY_SLOTS] =
+    [Keycode::Kp1, Keycode::Kp2, Keycode::Kp3];
+
+pub const ABILITY_ACTIONS: [Action; ABILITY_SLOTS] =
+    [Action::Ability1, Action::Ability2, Action::Ability3];
+
+/// Recharges active abilities and triggers them on player input
+#[derive(Default)]
+pub struct AbilitySystem {
+    // abilities are triggered on press, not while held
+    second_player_held: Vec<Keycode>,
+}
+
+impl<'a> System<'a> for AbilitySystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, CharacterMarker>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Projectile>,
+        ReadStorage<'a, Drone>,
+        ReadStorage<'a, ShipStats>,
+        WriteStorage<'a, Shield>,
+        WriteStorage<'a, AbilityBar>,
+        Read<'a, Actions>,
+        Read<'a, Vec<Keycode>>,
+        Read<'a, Coop>,
+        Write<'a, EventChannel<InsertEvent>>,
+        Write<'a, EventChannel<Sound>>,
+        ReadExpect<'a, PreloadedSounds>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: ability system started");
+        let (
+            entities,
+            isometries,
+            character_markers,
+            players,
+            projectiles,
+            drones,
+            ships_stats,
+            mut shields,
+            mut ability_bars,
+            actions,
+            keys,
+            coop,
+            mut insert_channel,
+            mut sounds_channel,
+            preloaded_sounds,
+        ) = data;
+        // charges are restored at the same pace during time slow
+        let now = TRACKER.lock().unwrap().unscaled_now();
+        let second_player_pressed: Vec<Keycode> = SECOND_PLAYER_ABILITY_KEYS
+            .iter()
+            .cloned()
+            .filter(|key| {
+                keys.contains(key) && !self.second_player_held.contains(key)
+            })
+            .collect();
+        self.second_player_held = SECOND_PLAYER_ABILITY_KEYS
+            .iter()
+            .cloned()
+            .filter(|key| keys.contains(key))
+            .collect();
+        let mut activated = vec![];
+        for (character, iso, bar, player, _) in (
+            &entities,
+            &isometries,
+            &mut ability_bars,
+            &players,
+            &character_markers,
+        )
+            .join()
+        {
+            for ability in bar.slots.iter_mut() {
+                ability.update(now);
+            }
+            if coop.is_downed(character) {
+                continue;
+            }
+            let position = iso.0.translation.vector;
+            let position = Point2::new(position.x, position.y);
+            for (slot, ability) in bar.slots.iter_mut().enumerate() {
+                let pressed = if player.id == 0 {
+                    actions.just_pressed(ABILITY_ACTIONS[slot])
+                } else {
+                    second_player_pressed
+                        .contains(&SECOND_PLAYER_ABILITY_KEYS[slot])
+                };
+                if !pressed {
+                    continue;
+                }
+                if ability.activate() {
+                    activated.push((character, position, ability.kind));
+                } else {
+                    sounds_channel
+                        .single_write(Sound(preloaded_sounds.deny, position));
+                }
+            }
+        }
+        let mut bombs = vec![];
+        for (character, position, kind) in activated.into_iter() {
+            match kind {
+                ActiveAbilityKind::Bomb => {
+                    // gameplay system explodes it and damages enemies
+                    insert_channel.single_write(InsertEvent::Blast {
+                        position: position,
+                        blast: Blast {
+                            blast_damage: BOMB_DAMAGE,
+                            blast_radius: BOMB_RADIUS,
+                            effect: None,
+                            damage_type: DamageType::Explosive,
+                        },
+                        owner: Some(character),
+                    });
+                    bombs.push(position);
+                }
+                ActiveAbilityKind::ShieldBurst => {
+                    if let (Some(shield), Some(ship_stats)) =
+                        (shields.get_mut(character), ships_stats.get(character))
+                    {
+                        let overcharged = (ship_stats.max_shield as f32
+                            * SHIELD_BURST_OVERCHARGE)
+                            as usize;
+                        shield.0 = shield.0.max(overcharged);
+                    }
+                    sounds_channel
+                        .single_write(Sound(preloaded_sounds.exp, position));
+                }
+                ActiveAbilityKind::TimeSlow => {
+                    TRACKER.lock().unwrap().slow_down(
+                        TIME_SLOW_SCALE,
+                        Duration::from_millis(TIME_SLOW_DURATION_MS),
+                    );
+                    sounds_channel
+                        .single_write(Sound(preloaded_sounds.play, position));
+                }
+            }
+        }
+        // enemy projectiles are cleared as well
+        if !bombs.is_empty() {
+            for (entity, projectile, iso) in
+                (&entities, &projectiles, &isometries).join()
+            {
+                let friendly =
+                    character_markers.get(projectile.owner).is_some()
+                        || drones.get(projectile.owner).is_some();
+                let position = iso.0.translation.vector;
+                let position = Point2::new(position.x, position.y);
+                if !friendly
+                    && bombs
+                        .iter()
+                        .any(|bomb| (*bomb - position).norm() < BOMB_RADIUS)
+                {
+                    entities.delete(entity).unwrap();
+                }
+            }
+        }
+        info!("asteroids: ability system ended");
+    }
+}
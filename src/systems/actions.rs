@@ -0,0 +1,156 @@
+use sdl2::keyboard::Keycode;
+
+use super::*;
+#[cfg(any(target_os = "android"))]
+use crate::gui::{Button, VecController};
+
+/// Maps keyboard, mouse, gamepad and touches of the first player to `Actions`
+#[derive(Default)]
+pub struct ActionsSystem;
+
+impl<'a> System<'a> for ActionsSystem {
+    type SystemData = (
+        Read<'a, Vec<Keycode>>,
+        Read<'a, Mouse>,
+        Read<'a, Gamepad>,
+        ReadExpect<'a, Touches>,
+        Read<'a, Bindings>,
+        Read<'a, Coop>,
+        Write<'a, Actions>,
+        Write<'a, UI>,
+        ReadExpect<'a, red::Viewport>,
+        ReadExpect<'a, PreloadedImages>,
+    );
+
+    // screen resources are needed only for touch sticks
+    #[cfg_attr(not(target_os = "android"), allow(unused_variables, unused_mut))]
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            keys,
+            mouse,
+            gamepad,
+            touches,
+            bindings,
+            coop,
+            mut actions,
+            mut ui,
+            viewport,
+            preloaded_images,
+        ) = data;
+        // arrows belong to the second player in co-op
+        let coop_keys = coop.local_players > 1;
+        let active = |binding: &Binding| match binding {
+            Binding::Key(name) => {
+                Keycode::from_name(name).map_or(false, |key| {
+                    keys.contains(&key)
+                        && !(coop_keys && SECOND_PLAYER_KEYS.contains(&key))
+                })
+            }
+            Binding::MouseLeft => mouse.left,
+            Binding::MouseRight => mouse.right,
+            Binding::Pad(name) => GamepadButton::from_string(name)
+                .map_or(false, |button| gamepad.held(button)),
+        };
+        let mut held: Vec<Action> = Action::ALL
+            .iter()
+            .cloned()
+            .filter(|action| bindings.get(*action).iter().any(&active))
+            .collect();
+        // ability buttons above the attack stick, taps come as mouse clicks
+        #[cfg(any(target_os = "android"))]
+        {
+            let dims = viewport.dimensions();
+            let (w, h) = (dims.0 as f32, dims.1 as f32);
+            let button_size = w / 14.0;
+            for (i, action) in ABILITY_ACTIONS.iter().enumerate() {
+                let ability_button = Button::new(
+                    Point2::new(
+                        w - (i as f32 + 1.0) * 1.5 * button_size,
+                        h / 3.0,
+                    ),
+                    button_size,
+                    button_size,
+                    None,
+                    false,
+                    Some(preloaded_images.circle),
+                    format!("{}", i + 1),
+                    ABILITY_WIDGETS[i] as usize,
+                    None,
+                    None,
+                );
+                if ability_button.place_and_check(&mut ui, &*mouse) {
+                    held.push(*action);
+                }
+            }
+        }
+        actions.set_held(held);
+        let axis = |negative: Action, positive: Action| {
+            let mut value = 0.0;
+            if actions.held(negative) {
+                value -= 1.0;
+            }
+            if actions.held(positive) {
+                value += 1.0;
+            }
+            value
+        };
+        let thrust = Vector2::new(
+            axis(Action::ThrustLeft, Action::ThrustRight),
+            axis(Action::ThrustUp, Action::ThrustDown),
+        );
+        let thrust = thrust + gamepad.left_stick;
+        let turn = axis(Action::TurnLeft, Action::TurnRight);
+        let fire = actions.held(Action::Fire) || gamepad.fire();
+        let reverse = actions.held(Action::Reverse) || gamepad.ability();
+        // right stick aims, otherwise ship turns to the mouse
+        // while fire is held by a mouse button
+        let is_mouse = |binding: &&Binding| match binding {
+            Binding::MouseLeft | Binding::MouseRight => true,
+            _ => false,
+        };
+        let mouse_fire = bindings
+            .get(Action::Fire)
+            .iter()
+            .filter(is_mouse)
+            .any(&active);
+        let aim = if gamepad.aiming() {
+            Some(Aim::Direction(gamepad.right_stick))
+        } else if mouse_fire {
+            Some(Aim::At(Point2::new(mouse.x, mouse.y)))
+        } else {
+            None
+        };
+        // mouse is emulated from touches on android, so sticks replace it
+        #[cfg(any(target_os = "android"))]
+        let (thrust, aim, fire) = {
+            let dims = viewport.dimensions();
+            let (w, h) = (dims.0 as f32, dims.1 as f32);
+            let stick_size = w / 80.0;
+            let ctrl_size = stick_size * 10.0;
+            let move_controller = VecController::new(
+                Point2::new(ctrl_size, h - ctrl_size),
+                ctrl_size,
+                stick_size,
+                preloaded_images.circle,
+            );
+            let attack_controller = VecController::new(
+                Point2::new(w - ctrl_size, h - ctrl_size),
+                ctrl_size,
+                stick_size,
+                preloaded_images.circle,
+            );
+            let move_dir = move_controller.set(0, &mut ui, &touches);
+            let attack_dir = attack_controller.set(1, &mut ui, &touches);
+            (
+                thrust + move_dir.unwrap_or(Vector2::new(0.0, 0.0)),
+                attack_dir.map(Aim::Direction),
+                fire || attack_dir.is_some(),
+            )
+        };
+        actions.thrust = thrust;
+        actions.turn = turn;
+        actions.aim = aim;
+        actions.fire = fire;
+        actions.reverse = reverse;
+    }
+}
@@ -3,7 +3,10 @@ use log::info;
 use physics::*;
 
 #[derive(Default)]
-pub struct AISystem;
+pub struct AISystem {
+    // next enemy to cast perception ray from
+    perception_cursor: usize,
+}
 
 impl<'a> System<'a> for AISystem {
     type SystemData = (
@@ -12,21 +15,25 @@ impl<'a> System<'a> for AISystem {
         WriteStorage<'a, Velocity>,
         ReadStorage<'a, PhysicsComponent>,
         WriteStorage<'a, Spin>,
-        WriteStorage<'a, ShotGun>,
         WriteStorage<'a, MultyLazer>,
-        WriteStorage<'a, Cannon>,
-        WriteStorage<'a, RocketGun>,
         WriteStorage<'a, EnemyMarker>,
         WriteStorage<'a, Charge>,
         ReadStorage<'a, CharacterMarker>,
         ReadStorage<'a, AI>,
+        ReadStorage<'a, Steering>,
+        ReadStorage<'a, Skill>,
+        WriteStorage<'a, Perception>,
         ReadStorage<'a, Chain>,
+        WriteStorage<'a, SquadMember>,
         ReadStorage<'a, ShipStats>,
         Write<'a, World<f32>>,
-        Write<'a, EventChannel<InsertEvent>>,
         Write<'a, BodiesMap>,
-        Write<'a, EventChannel<Sound>>,
-        ReadExpect<'a, PreloadedSounds>,
+        (
+            Read<'a, WorldShift>,
+            ReadStorage<'a, StatusEffects>,
+            Read<'a, Coop>,
+            WriteStorage<'a, Hardpoints>,
+        ),
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -37,30 +44,123 @@ impl<'a> System<'a> for AISystem {
             mut velocities,
             physics,
             mut spins,
-            mut shotguns,
             mut multy_lazers,
-            mut cannons,
-            mut rocket_guns,
             enemies,
             mut chargings,
             character_markers,
             ais,
+            steerings,
+            skills,
+            mut perceptions,
             chains,
+            mut squad_members,
             ship_stats,
             mut world,
-            mut insert_channel,
             bodies_map,
-            mut sounds_channel,
-            preloaded_sounds,
+            (world_shift, status_effects, coop, mut hardpoints),
         ) = data;
-        let (character_entity, character_position, _) = if let Some(value) =
-            (&entities, &isometries, &character_markers).join().next()
-        {
-            value
-        } else {
+        // each enemy hunts the nearest standing player
+        let targets: Vec<(Entity, Point2, Vector2)> =
+            (&entities, &isometries, &physics, &character_markers)
+                .join()
+                .filter(|(entity, _, _, _)| !coop.is_downed(*entity))
+                .map(|(entity, iso, physics_component, _)| {
+                    let position = iso.0.translation.vector;
+                    let body = world
+                        .rigid_body(physics_component.body_handle)
+                        .unwrap();
+                    let linear = body.velocity().linear;
+                    (
+                        entity,
+                        Point2::new(position.x, position.y),
+                        Vector2::new(linear.x, linear.y),
+                    )
+                })
+                .collect();
+        if targets.len() == 0 {
             return;
-        };
-        let character_position = character_position.0.translation.vector;
+        }
+        let neighbours: Vec<(Entity, Point2)> =
+            (&entities, &isometries, &enemies)
+                .join()
+                .map(|(entity, iso, _)| {
+                    let position = iso.0.translation.vector;
+                    (entity, Point2::new(position.x, position.y))
+                })
+                .collect();
+        let now = TRACKER.lock().unwrap().now();
+        let shift = world_shift.0;
+        {
+            // ray casts are expensive, so only part of enemies
+            // look around each frame
+            let watchers: Vec<Entity> = (&entities, &perceptions, &enemies)
+                .join()
+                .map(|(entity, _, _)| entity)
+                .collect();
+            let mut spotters = vec![];
+            if watchers.len() > 0 {
+                let budget = PERCEPTION_RAYS_PER_FRAME.min(watchers.len());
+                for i in 0..budget {
+                    let entity =
+                        watchers[(self.perception_cursor + i) % watchers.len()];
+                    let iso = isometries.get(entity).unwrap();
+                    let perception = perceptions.get_mut(entity).unwrap();
+                    let position = iso.0.translation.vector;
+                    let pos = Point2::new(position.x, position.y);
+                    let (character_entity, character_point, _) =
+                        nearest_target(&targets, pos);
+                    let diff = character_point - pos;
+                    let in_view = diff.norm() < perception.view_range
+                        && is_aligned(
+                            diff,
+                            iso.rotation(),
+                            (perception.fov / 2.0).to_radians(),
+                        );
+                    let mut sees = false;
+                    if in_view {
+                        let ray = Ray::new(pos, diff.normalize());
+                        let enemy_collision_groups =
+                            get_collision_groups(EntityType::Enemy);
+                        let nearby = get_min_dist(
+                            &mut world,
+                            ray,
+                            enemy_collision_groups,
+                        );
+                        if let Some(body) = nearby.1 {
+                            // body that we facing
+                            if bodies_map[&body] == character_entity {
+                                sees = true;
+                            }
+                        };
+                    }
+                    if sees {
+                        perception.see(character_point, shift, now);
+                        spotters.push((
+                            pos,
+                            perception.alert_radius,
+                            character_point,
+                        ));
+                    } else {
+                        perception.lose();
+                    }
+                }
+                self.perception_cursor =
+                    (self.perception_cursor + budget) % watchers.len();
+            }
+            // tell allies nearby where the player is
+            for (spotter_pos, alert_radius, character_point) in spotters.iter()
+            {
+                for (iso, perception, _) in
+                    (&isometries, &mut perceptions, &enemies).join()
+                {
+                    let position = iso.0.translation.vector;
+                    let pos = Point2::new(position.x, position.y);
+                    if (pos - *spotter_pos).norm() < *alert_radius {
+                        perception.alert(*character_point, shift, now);
+                    }
+                }
+            }
+        }
         for (entity, iso, vel, physics_component, spin, _enemy, ai) in (
             &entities,
             &isometries,
@@ -72,22 +172,127 @@ impl<'a> System<'a> for AISystem {
         )
             .join()
         {
+            if status_effects
+                .get(entity)
+                .map_or(false, |effects| effects.stunned())
+            {
+                if let Some(multy_lazer) = multy_lazers.get_mut(entity) {
+                    multy_lazer.set_all(false);
+                }
+                if let Some(ship_hardpoints) = hardpoints.get_mut(entity) {
+                    ship_hardpoints.firing = false;
+                }
+                continue;
+            }
+            let thrust_force = ship_stats.get(entity).map_or(0.0, |stats| {
+                effective_stats(stats, status_effects.get(entity)).thrust_force
+            });
             let isometry = iso.0;
             let position = isometry.translation.vector;
+            let pos = Point2::new(position.x, position.y);
+            let (_, character_point, character_velocity) =
+                nearest_target(&targets, pos);
+            let character_position =
+                Vector3::new(character_point.x, character_point.y, position.z);
             let diff = character_position - position;
             let dir = Vector2::new(diff.x, diff.y).normalize();
-            let pos = Point2::new(position.x, position.y);
-            let ray = Ray::new(pos, dir);
-            let enemy_collision_groups =
-                get_collision_groups(EntityType::Enemy);
-            let nearby = get_min_dist(&mut world, ray, enemy_collision_groups);
-            let mut character_noticed = false;
-            if let Some(body) = nearby.1 {
-                // body that we facing
-                if bodies_map[&body] == character_entity {
-                    character_noticed = true;
+            let steering = steerings.get(entity).cloned().unwrap_or_default();
+            let skill = skills.get(entity).cloned().unwrap_or_default();
+            // lead the target: aim where player will be when bullet arrives
+            let projectile_speed = hardpoints
+                .get(entity)
+                .and_then(|ship_hardpoints| ship_hardpoints.bullet_speed());
+            let target_diff = Vector2::new(diff.x, diff.y);
+            let relative_velocity = character_velocity - vel.0;
+            let aim = match projectile_speed.and_then(|speed| {
+                intercept_time(target_diff, relative_velocity, speed)
+            }) {
+                Some(time) => {
+                    target_diff + skill.accuracy * time * relative_velocity
+                }
+                None => target_diff,
+            };
+            let aligned =
+                is_aligned(aim, iso.rotation(), skill.fire_angle.to_radians());
+            let perception =
+                perceptions.get(entity).cloned().unwrap_or_default();
+            let character_noticed = perception.sees;
+            // if player is not visible search him at last known position
+            let target = if character_noticed {
+                Some(character_point)
+            } else if let Some(target) =
+                perception.last_known_position(shift, now)
+            {
+                let target_dir = target - pos;
+                if perception.alerted && target_dir.norm() > EPS {
+                    // alerted allies don't go straight, they flank
+                    let side = if entity.id() % 2 == 0 { 1.0 } else { -1.0 };
+                    let flank = Vector2::new(-target_dir.y, target_dir.x)
+                        .normalize();
+                    Some(target + side * FLANK_DISTANCE * flank)
+                } else {
+                    Some(target)
                 }
+            } else {
+                None
             };
+            let formation_target =
+                if let Some(member) = squad_members.get_mut(entity) {
+                    // break formation to fight, reform when player is lost
+                    if character_noticed && diff.norm() < SQUAD_ENGAGE_AREA {
+                        member.engaged = true;
+                    } else if !character_noticed
+                        || diff.norm() > 1.5 * SQUAD_ENGAGE_AREA
+                    {
+                        member.engaged = false;
+                    }
+                    if member.engaged {
+                        None
+                    } else {
+                        isometries.get(member.leader).map(|leader_iso| {
+                            let leader_pos = leader_iso.0.translation.vector;
+                            let slot = Rotation2::new(leader_iso.rotation())
+                                * member.slot;
+                            Point2::new(
+                                leader_pos.x + slot.x,
+                                leader_pos.y + slot.y,
+                            )
+                        })
+                    }
+                } else {
+                    None
+                };
+            let in_formation = formation_target.is_some();
+            if let Some(slot_pos) = formation_target {
+                let speed = FORMATION_SPEED_MULTIPLIER * thrust_force;
+                let formation_steering = Steering {
+                    seek: 0.0,
+                    arrive: 1.0,
+                    flee: 0.0,
+                    ..steering
+                };
+                let ai_vel = steer(
+                    &formation_steering,
+                    &world,
+                    &SteeringInput {
+                        entity: entity,
+                        pos: pos,
+                        velocity: vel.0,
+                        target: Some(slot_pos),
+                        chase: true,
+                        neighbours: &neighbours,
+                        max_speed: speed,
+                    },
+                );
+                *vel = Velocity::new(ai_vel.x, ai_vel.y);
+                let body = world
+                    .rigid_body_mut(physics_component.body_handle)
+                    .unwrap();
+                let mut velocity = *body.velocity();
+                *velocity.as_vector_mut() =
+                    Vector3::new(vel.0.x, vel.0.y, spin.0);
+                body.set_velocity(velocity);
+            }
             let follow_area =
                 if let Some(multy_lazer) = multy_lazers.get(entity) {
                     multy_lazer.first_distance() * 0.95
@@ -96,63 +301,21 @@ impl<'a> System<'a> for AISystem {
                 };
             for ai_type in ai.kinds.iter() {
                 match ai_type {
+                    // followers in formation move only with their leader
+                    AIType::Follow
+                    | AIType::FollowRotate { .. }
+                    | AIType::Kamikadze
+                    | AIType::Charging(_)
+                        if in_formation => {}
                     AIType::Shoot => {
-                        // Copy paste from top
-                        let gun = cannons.get_mut(entity);
-                        if let Some(gun) = gun {
-                            if diff.norm() < SCREEN_AREA
-                                && gun.shoot()
-                                && character_noticed
-                            {
-                                let bullets = gun.spawn_bullets(
-                                    EntityType::Enemy,
-                                    isometry,
-                                    gun.bullet_speed,
-                                    gun.bullets_damage,
-                                    Vector2::new(vel.0.x, vel.0.y),
-                                    entity,
-                                );
-                                insert_channel.iter_write(bullets.into_iter());
-                                sounds_channel.single_write(Sound(
-                                    preloaded_sounds.enemy_blaster,
-                                    Point2::new(position.x, position.y),
-                                ))
-                            }
-                        }
-                        let shotgun = shotguns.get_mut(entity);
-                        if let Some(shotgun) = shotgun {
-                            if diff.norm() < SCREEN_AREA && shotgun.shoot() {
-                                let bullets = shotgun.spawn_bullets(
-                                    EntityType::Enemy,
-                                    isometry,
-                                    shotgun.bullet_speed,
-                                    shotgun.bullets_damage,
-                                    Vector2::new(vel.0.x, vel.0.y),
-                                    entity,
-                                );
-                                insert_channel.iter_write(bullets.into_iter());
-                                sounds_channel.single_write(Sound(
-                                    preloaded_sounds.enemy_shotgun,
-                                    Point2::new(position.x, position.y),
-                                ))
-                            }
-                        }
-                        if let Some(rocket_gun) = rocket_guns.get_mut(entity) {
-                            if diff.norm() < SCREEN_AREA && rocket_gun.shoot() {
-                                let bullets = rocket_gun.spawn_bullets(
-                                    EntityType::Enemy,
-                                    isometry,
-                                    rocket_gun.bullet_speed,
-                                    rocket_gun.bullets_damage,
-                                    Vector2::new(vel.0.x, vel.0.y),
-                                    entity,
-                                );
-                                insert_channel.iter_write(bullets.into_iter());
-                                sounds_channel.single_write(Sound(
-                                    preloaded_sounds.enemy_shotgun,
-                                    Point2::new(position.x, position.y),
-                                ))
-                            }
+                        // turrets track on their own, fixed mounts wait
+                        // until the ship turns to the target
+                        if let Some(ship_hardpoints) =
+                            hardpoints.get_mut(entity)
+                        {
+                            ship_hardpoints.firing =
+                                diff.norm() < SCREEN_AREA && character_noticed;
+                            ship_hardpoints.hold_fixed = !aligned;
                         }
                         if diff.norm() > follow_area {
                             if let Some(multy_lazer) =
@@ -169,8 +332,7 @@ impl<'a> System<'a> for AISystem {
                         }
                     }
                     AIType::Follow => {
-                        let speed =
-                            ship_stats.get(entity).unwrap().thrust_force;
+                        let speed = thrust_force;
                         let mut is_chain = false;
                         if let Some(chain) = chains.get(entity) {
                             if let Some(iso) = isometries.get(chain.follow) {
@@ -198,15 +360,25 @@ impl<'a> System<'a> for AISystem {
                             }
                         };
                         if !is_chain {
-                            if diff.norm() > follow_area {
-                                if character_noticed {
-                                    let ai_vel = speed * dir;
-                                    *vel = Velocity::new(ai_vel.x, ai_vel.y);
-                                }
+                            let chase = if character_noticed {
+                                diff.norm() > follow_area
                             } else {
-                                let vel_vec = DAMPING_FACTOR * vel.0;
-                                *vel = Velocity::new(vel_vec.x, vel_vec.y);
-                            }
+                                true
+                            };
+                            let ai_vel = steer(
+                                &steering,
+                                &world,
+                                &SteeringInput {
+                                    entity: entity,
+                                    pos: pos,
+                                    velocity: DAMPING_FACTOR * vel.0,
+                                    target: target,
+                                    chase: chase,
+                                    neighbours: &neighbours,
+                                    max_speed: speed,
+                                },
+                            );
+                            *vel = Velocity::new(ai_vel.x, ai_vel.y);
                             let body = world
                                 .rigid_body_mut(physics_component.body_handle)
                                 .unwrap();
@@ -217,8 +389,7 @@ impl<'a> System<'a> for AISystem {
                         }
                     }
                     AIType::FollowRotate { spin: rot_spin } => {
-                        let speed =
-                            ship_stats.get(entity).unwrap().thrust_force;
+                        let speed = thrust_force;
                         let mut is_chain = false;
                         if let Some(chain) = chains.get(entity) {
                             if let Some(iso) = isometries.get(chain.follow) {
@@ -275,10 +446,7 @@ impl<'a> System<'a> for AISystem {
                     AIType::Aim => {
                         let ship_torque = DT
                             * calculate_player_ship_spin_for_aim(
-                                Vector2::new(
-                                    character_position.x,
-                                    character_position.y,
-                                ) - Vector2::new(position.x, position.y),
+                                aim,
                                 iso.rotation(),
                                 spin.0,
                             );
@@ -289,8 +457,19 @@ impl<'a> System<'a> for AISystem {
                     }
                     AIType::Kamikadze => {
                         let speed = 0.1f32;
-                        let diff = character_position - position;
-                        let dir = speed * (diff).normalize();
+                        let dir = steer(
+                            &steering,
+                            &world,
+                            &SteeringInput {
+                                entity: entity,
+                                pos: pos,
+                                velocity: vel.0,
+                                target: Some(character_point),
+                                chase: true,
+                                neighbours: &neighbours,
+                                max_speed: speed,
+                            },
+                        );
                         *vel = Velocity::new(dir.x, dir.y);
                         let body = world
                             .rigid_body_mut(physics_component.body_handle)
@@ -306,8 +485,19 @@ impl<'a> System<'a> for AISystem {
                             "no charging component while have charging AI",
                         );
                         if charging.shoot() {
-                            let diff = character_position - position;
-                            let dir = speed * (diff).normalize();
+                            let dir = steer(
+                                &steering,
+                                &world,
+                                &SteeringInput {
+                                    entity: entity,
+                                    pos: pos,
+                                    velocity: vel.0,
+                                    target: Some(character_point),
+                                    chase: true,
+                                    neighbours: &neighbours,
+                                    max_speed: speed,
+                                },
+                            );
                             *vel = Velocity::new(dir.x, dir.y);
                             let body = world
                                 .rigid_body_mut(physics_component.body_handle)
@@ -324,3 +514,17 @@ impl<'a> System<'a> for AISystem {
         }
     }
 }
+
+fn nearest_target(
+    targets: &[(Entity, Point2, Vector2)],
+    pos: Point2,
+) -> (Entity, Point2, Vector2) {
+    *targets
+        .iter()
+        .min_by(|a, b| {
+            let a = (a.1 - pos).norm();
+            let b = (b.1 - pos).norm();
+            a.partial_cmp(&b).unwrap()
+        })
+        .unwrap()
+}
@@ -52,20 +52,31 @@ fn damage_ship(
     ship: specs::Entity,
     lifes: &mut WriteStorage<Lifes>,
     shields: &mut WriteStorage<Shield>,
+    resistances: &ReadStorage<Resistances>,
+    dashes: &ReadStorage<Dash>,
     entities: &Entities,
     app_state: &mut Write<AppState>,
-    progress: &mut Write<Progress>,
-    macro_game: &mut WriteExpect<MacroGame>,
+    coop: &mut Write<Coop>,
     insert_channel: &mut Write<EventChannel<InsertEvent>>,
     sounds_channel: &mut Write<EventChannel<Sound>>,
+    destroyed_channel: &mut Write<EventChannel<ShipDestroyed>>,
     preloaded_sounds: &ReadExpect<PreloadedSounds>,
     preloaded_images: &ReadExpect<PreloadedImages>,
     global_params: &mut WriteExpect<GlobalParams>,
     contact_pos: Point2,
     ship_pos: Point2,
     damage: usize,
+    damage_type: DamageType,
     bullet: bool,
 ) {
+    // downed character has nothing to lose
+    if coop.is_downed(ship) {
+        return;
+    }
+    // invulnerability frames of the dash
+    if dashes.get(ship).map_or(false, |dash| dash.invulnerable()) {
+        return;
+    }
     if is_character {
         if bullet {
             global_params.damaged(DAMAGED_RED);
@@ -85,6 +96,8 @@ fn damage_ship(
         lifes.get_mut(ship).unwrap(),
         shields.get_mut(ship),
         damage,
+        damage_type,
+        resistances.get(ship),
     ) {
         // ship is done... Explode it
         ship_explode(
@@ -93,10 +106,9 @@ fn damage_ship(
             sounds_channel,
             preloaded_sounds,
         );
-        if is_character {
-            to_menu(app_state, progress, &mut macro_game.score_table);
+        if !is_character || !knock_out(app_state, coop, ship) {
+            destroy_ship(ship, entities, destroyed_channel);
         }
-        entities.delete(ship).unwrap();
     }
 }
 
@@ -123,18 +135,28 @@ impl<'a> System<'a> for CollisionSystem {
         ReadStorage<'a, Damage>,
         WriteStorage<'a, Polygon>,
         ReadStorage<'a, Size>,
-        WriteStorage<'a, DamageFlash>,
+        (
+            WriteStorage<'a, DamageFlash>,
+            WriteStorage<'a, StatusEffects>,
+            ReadStorage<'a, Resistances>,
+            ReadStorage<'a, DamageType>,
+            ReadStorage<'a, Asteroid>,
+            Read<'a, AsteroidKinds>,
+            ReadStorage<'a, Dash>,
+            Write<'a, EventChannel<ShipDestroyed>>,
+            Write<'a, RunRngs>,
+        ),
         Write<'a, World<f32>>,
         Read<'a, BodiesMap>,
         Write<'a, EventChannel<InsertEvent>>,
         Write<'a, EventChannel<Sound>>,
         ReadExpect<'a, PreloadedSounds>,
         ReadExpect<'a, PreloadedImages>,
-        Write<'a, Progress>,
+        Write<'a, Coop>,
         Write<'a, AppState>,
-        WriteExpect<'a, MacroGame>,
         WriteExpect<'a, GlobalParams>,
         ReadExpect<'a, Arc<Mutex<EventChannel<InsertEvent>>>>,
+        ReadExpect<'a, Director>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -154,18 +176,28 @@ impl<'a> System<'a> for CollisionSystem {
             damages,
             polygons,
             sizes,
-            mut flashes,
+            (
+                mut flashes,
+                mut status_effects,
+                resistances,
+                damage_types,
+                asteroid_kinds,
+                asteroids_description,
+                dashes,
+                mut destroyed_channel,
+                mut run_rngs,
+            ),
             mut world,
             bodies_map,
             mut insert_channel,
             mut sounds_channel,
             preloaded_sounds,
             preloaded_images,
-            mut progress,
+            mut coop,
             mut app_state,
-            mut macro_game,
             mut global_params,
             asteroids_channel,
+            director,
         ) = data;
         self.colliding_pairs.clear();
         self.colliding_start_events.clear();
@@ -209,7 +241,7 @@ impl<'a> System<'a> for CollisionSystem {
             if asteroids.get(entity1).is_some() {
                 let asteroid = entity1;
                 let mut asteroid_explosion = false;
-                let mut bullet_position = None;
+                let mut impact = None;
                 // 
                 if asteroids.get(entity2).is_some() {
                     // let asteroid1 = entity1;
@@ -240,7 +272,6 @@ impl<'a> System<'a> for CollisionSystem {
                     let proj_pos =
                         isometries.get(entity2).unwrap().0.translation.vector;
                     let proj_pos2d = Point2::new(proj_pos.x, proj_pos.y);
-                    bullet_position = Some(proj_pos2d);
                     bullet_contact(
                         proj_pos2d,
                         &mut insert_channel,
@@ -250,6 +281,15 @@ impl<'a> System<'a> for CollisionSystem {
                     );
                     let projectile = entity2;
                     let projectile_damage = damages.get(projectile).unwrap().0;
+                    let projectile_velocity = body_velocity(
+                        physics_components.get(projectile),
+                        &world,
+                    );
+                    impact = Some(Impact::new(
+                        proj_pos2d,
+                        projectile_velocity.linear,
+                        projectile_damage,
+                    ));
                     if projectile_damage != 0 {
                         if reflections.get(projectile).is_some() {
                             reflect_bullet(
@@ -278,6 +318,17 @@ impl<'a> System<'a> for CollisionSystem {
                     let ship = entity2;
                     let isometry = isometries.get(ship).unwrap().0;
                     let position = isometry.translation.vector;
+                    let (_, material) = asteroid_kind(
+                        asteroid,
+                        &asteroid_kinds,
+                        &asteroids_description,
+                    );
+                    if let AsteroidMaterial::Ice { effect } = material {
+                        if let Some(effects) = status_effects.get_mut(ship) {
+                            let now = TRACKER.lock().unwrap().now();
+                            effects.apply(effect, now);
+                        }
+                    }
                     // asteroid_explosion = true;
                     let effect = InsertEvent::Explosion {
                         position: Point2::new(position.x, position.y),
@@ -299,12 +350,14 @@ impl<'a> System<'a> for CollisionSystem {
                             ship,
                             &mut lifes,
                             &mut shields,
+                            &resistances,
+                            &dashes,
                             &entities,
                             &mut app_state,
-                            &mut progress,
-                            &mut macro_game,
+                            &mut coop,
                             &mut insert_channel,
                             &mut sounds_channel,
+                            &mut destroyed_channel,
                             &preloaded_sounds,
                             &preloaded_images,
                             &mut global_params,
@@ -313,6 +366,7 @@ impl<'a> System<'a> for CollisionSystem {
                             (ASTEROID_DAMAGE as f32
                                 * sizes.get(asteroid).unwrap().0)
                                 as usize,
+                            DamageType::Collision,
                             false,
                         );
                         bullet_contact(
@@ -341,13 +395,27 @@ impl<'a> System<'a> for CollisionSystem {
                     );
                     let iso = isometries.get(asteroid).unwrap().0;
                     let poly = polygons.get(asteroid).unwrap().clone();
+                    let (kind, material) = asteroid_kind(
+                        asteroid,
+                        &asteroid_kinds,
+                        &asteroids_description,
+                    );
+                    let velocity =
+                        body_velocity(physics_components.get(asteroid), &world);
                     let channel_arc = (*asteroids_channel).clone();
+                    let drop_rate = director.drop_rate;
+                    let rng = StdRng::seed_from_u64(run_rngs.drops.gen());
                     thread::spawn(move || {
                         spawn_asteroids(
                             iso,
+                            velocity,
                             poly,
+                            kind,
+                            material,
                             channel_arc,
-                            bullet_position,
+                            impact,
+                            drop_rate,
+                            rng,
                         );
                     });
                     entities.delete(asteroid).unwrap();
@@ -376,18 +444,27 @@ impl<'a> System<'a> for CollisionSystem {
                     ship,
                     &mut lifes,
                     &mut shields,
+                    &resistances,
+                    &dashes,
                     &entities,
                     &mut app_state,
-                    &mut progress,
-                    &mut macro_game,
+                    &mut coop,
                     &mut insert_channel,
                     &mut sounds_channel,
+                    &mut destroyed_channel,
                     &preloaded_sounds,
                     &preloaded_images,
                     &mut global_params,
                     projectile_pos,
                     Point2::new(position.x, position.y),
-                    projectile_damage,
+                    affected_damage(
+                        status_effects.get(ship),
+                        projectile_damage,
+                    ),
+                    damage_types
+                        .get(projectile)
+                        .cloned()
+                        .unwrap_or(DamageType::Kinetic),
                     true,
                 );
                 // Kludge
@@ -414,7 +491,10 @@ impl<'a> System<'a> for CollisionSystem {
                 if character_markers.get(ship2).is_some() {
                     swap(&mut ship1, &mut ship2)
                 }
-                if character_markers.get(ship1).is_some() {
+                // downed players neither ram nor get rammed
+                if character_markers.get(ship1).is_some()
+                    && !coop.is_downed(ship1)
+                {
                     let character_ship = ship1;
                     let other_ship = ship2;
                     // entities.delete(other_ship).unwrap();
@@ -425,7 +505,12 @@ impl<'a> System<'a> for CollisionSystem {
                     if process_damage(
                         lifes.get_mut(other_ship).unwrap(),
                         shields.get_mut(other_ship),
-                        damages.get(character_ship).unwrap().0,
+                        affected_damage(
+                            status_effects.get(other_ship),
+                            damages.get(character_ship).unwrap().0,
+                        ),
+                        DamageType::Collision,
+                        resistances.get(other_ship),
                     ) {
                         ship_explode(
                             Point2::new(position.x, position.y),
@@ -433,21 +518,40 @@ impl<'a> System<'a> for CollisionSystem {
                             &mut sounds_channel,
                             &preloaded_sounds,
                         );
-                        entities.delete(other_ship).unwrap();
-                    }
-                    global_params.damaged(DAMAGED_RED);
-                    if process_damage(
-                        lifes.get_mut(character_ship).unwrap(),
-                        shields.get_mut(character_ship),
-                        damages.get(other_ship).unwrap().0,
-                    ) {
-                        to_menu(
-                            &mut app_state,
-                            &mut progress,
-                            &mut macro_game.score_table,
+                        destroy_ship(
+                            other_ship,
+                            &entities,
+                            &mut destroyed_channel,
                         );
-                        // delete character
-                        entities.delete(character_ship).unwrap();
+                    }
+                    let invulnerable = dashes
+                        .get(character_ship)
+                        .map_or(false, Dash::invulnerable);
+                    if !invulnerable {
+                        global_params.damaged(DAMAGED_RED);
+                        if process_damage(
+                            lifes.get_mut(character_ship).unwrap(),
+                            shields.get_mut(character_ship),
+                            affected_damage(
+                                status_effects.get(character_ship),
+                                damages.get(other_ship).unwrap().0,
+                            ),
+                            DamageType::Collision,
+                            resistances.get(character_ship),
+                        ) {
+                            if !knock_out(
+                                &mut app_state,
+                                &mut coop,
+                                character_ship,
+                            ) {
+                                // delete character
+                                destroy_ship(
+                                    character_ship,
+                                    &entities,
+                                    &mut destroyed_channel,
+                                );
+                            }
+                        }
                     }
                 }
             }
@@ -20,6 +20,12 @@ impl<'a> System<'a> for CommonRespawn {
         WriteExpect<'a, NebulaGrid>,
         WriteExpect<'a, PlanetGrid>,
         ReadStorage<'a, PhysicsComponent>,
+        Read<'a, AsteroidKinds>,
+        Read<'a, Waves>,
+        Read<'a, CurrentWave>,
+        Write<'a, RunRngs>,
+        Read<'a, RunMode>,
+        ReadExpect<'a, Director>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -40,6 +46,12 @@ impl<'a> System<'a> for CommonRespawn {
             mut nebula_grid,
             mut planet_grid,
             physics,
+            asteroid_kinds,
+            waves,
+            current_wave,
+            mut run_rngs,
+            run_mode,
+            director,
         ) = data;
         let character_position =
             if let Some((_char_entity, char_isometry, _char)) =
@@ -52,25 +64,65 @@ impl<'a> System<'a> for CommonRespawn {
             };
 
         let cnt = asteroid_markers.count();
-        let add_cnt = if ASTEROIDS_MIN_NUMBER > cnt {
-            ASTEROIDS_MIN_NUMBER - cnt
+        let asteroids_number = match run_mode.modifier() {
+            Some(DailyModifier::DenseAsteroids) => 2 * ASTEROIDS_MIN_NUMBER,
+            _ => ASTEROIDS_MIN_NUMBER,
+        };
+        let asteroids_number =
+            (asteroids_number as f32 * director.asteroid_density) as usize;
+        let add_cnt = if asteroids_number > cnt {
+            asteroids_number - cnt
         } else {
             0
         };
+        // wave can override default spawn weights
+        let kinds_weights: Vec<(usize, f32)> =
+            match waves.0.get(current_wave.id) {
+                Some(wave) if wave.asteroids.len() > 0 => {
+                    wave.asteroids.clone()
+                }
+                _ => asteroid_kinds
+                    .0
+                    .iter()
+                    .enumerate()
+                    .map(|(id, kind)| (id, kind.spawn_weight))
+                    .collect(),
+            };
+        let rng = &mut run_rngs.asteroids;
         for _ in 0..add_cnt {
-            let mut rng = thread_rng();
+            let kind = kinds_weights
+                .choose_weighted(rng, |item| item.1)
+                .map(|item| item.0)
+                .unwrap_or(0);
             let size = rng.gen_range(ASTEROID_MIN_RADIUS, ASTEROID_MAX_RADIUS);
             let r = size;
-            let poly = generate_convex_polygon(10, r);
+            let shape = asteroid_kinds
+                .0
+                .get(kind)
+                .map(|asteroid_kind| asteroid_kind.shape)
+                .unwrap_or_default();
+            let poly = generate_noise_polygon(
+                shape.samples,
+                r,
+                shape.roughness,
+                shape.elongation,
+                shape.craters,
+                rng,
+            );
             let spin = rng.gen_range(-1E-2, 1E-2);
             // let ball = ncollide2d::shape::Ball::new(r);
-            let spawn_pos =
-                spawn_position(character_position, PLAYER_AREA, ACTIVE_AREA);
+            let spawn_pos = spawn_position(
+                character_position,
+                PLAYER_AREA,
+                ACTIVE_AREA,
+                rng,
+            );
             insert_channel.single_write(InsertEvent::Asteroid {
                 iso: Point3::new(spawn_pos.x, spawn_pos.y, 0.0),
-                velocity: initial_asteroid_velocity(),
+                velocity: initial_asteroid_velocity(rng),
                 polygon: poly,
                 spin: spin,
+                kind: Asteroid { kind, stage: 0 },
             });
         }
 
@@ -1,24 +1,13 @@
-use sdl2::keyboard::Keycode;
-use std::collections::HashSet;
-
 pub use super::*;
 use log::info;
+use nphysics2d::algebra::{Force2, ForceType};
 use physics::*;
 
+/// Applies `Actions` of the first player
+#[derive(Default)]
 pub struct ControlSystem {
-    reader: ReaderId<Keycode>,
-    prev_keys: HashSet<Keycode>,
-    new_keys: HashSet<Keycode>,
-}
-
-impl ControlSystem {
-    pub fn new(reader: ReaderId<Keycode>) -> Self {
-        ControlSystem {
-            reader: reader,
-            prev_keys: HashSet::new(),
-            new_keys: HashSet::new(),
-        }
-    }
+    // turning with keys, spin is reset when they are released
+    turning: bool,
 }
 
 impl<'a> System<'a> for ControlSystem {
@@ -29,7 +18,6 @@ impl<'a> System<'a> for ControlSystem {
             WriteStorage<'a, Velocity>,
             WriteStorage<'a, PhysicsComponent>,
             WriteStorage<'a, Spin>,
-            WriteStorage<'a, ShotGun>,
             WriteStorage<'a, MultyLazer>,
             WriteStorage<'a, Lifes>,
             WriteStorage<'a, Shield>,
@@ -38,9 +26,13 @@ impl<'a> System<'a> for ControlSystem {
             ReadStorage<'a, AsteroidMarker>,
             WriteStorage<'a, ShipStats>,
             WriteStorage<'a, Rift>,
+            WriteStorage<'a, StatusEffects>,
+            ReadStorage<'a, Resistances>,
+            ReadStorage<'a, Asteroid>,
+            WriteStorage<'a, Hardpoints>,
+            WriteStorage<'a, Dash>,
         ),
-        Read<'a, EventChannel<Keycode>>,
-        Read<'a, Mouse>,
+        Read<'a, Actions>,
         Write<'a, EventChannel<Sound>>,
         ReadExpect<'a, PreloadedSounds>,
         ReadExpect<'a, PreloadedImages>,
@@ -49,10 +41,14 @@ impl<'a> System<'a> for ControlSystem {
         Write<'a, EventChannel<InsertEvent>>,
         Write<'a, AppState>,
         WriteExpect<'a, Canvas>,
-        Write<'a, Progress>,
-        WriteExpect<'a, MacroGame>,
+        Write<'a, Coop>,
+        ReadStorage<'a, Player>,
         WriteExpect<'a, DevInfo>,
         ReadExpect<'a, Arc<Mutex<EventChannel<InsertEvent>>>>,
+        Read<'a, AsteroidKinds>,
+        ReadExpect<'a, Director>,
+        Write<'a, EventChannel<ShipDestroyed>>,
+        Write<'a, RunRngs>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -63,7 +59,6 @@ impl<'a> System<'a> for ControlSystem {
                 mut velocities,
                 physics,
                 mut spins,
-                mut shotguns,
                 mut multiple_lazers,
                 mut lifes,
                 mut shields,
@@ -72,9 +67,13 @@ impl<'a> System<'a> for ControlSystem {
                 asteroid_markers,
                 mut ships_stats,
                 mut rifts,
+                mut status_effects,
+                resistances,
+                asteroid_kinds,
+                mut hardpoints,
+                mut dashes,
             ),
-            keys_channel,
-            mouse_state,
+            actions,
             mut sounds_channel,
             preloaded_sounds,
             preloaded_images,
@@ -83,60 +82,93 @@ impl<'a> System<'a> for ControlSystem {
             mut insert_channel,
             mut app_state,
             mut canvas,
-            mut progress,
-            mut macro_game,
+            mut coop,
+            players,
             mut dev_info,
             asteroids_channel,
+            asteroids_description,
+            director,
+            mut destroyed_channel,
+            mut run_rngs,
         ) = data;
         info!("asteroids: started control system");
-        let (ship_stats, _) = if let Some(value) =
-            (&mut ships_stats, &character_markers).join().next()
+        // the second player is handled by `SecondPlayerControl`
+        let (ship_stats, _, _) = if let Some(value) =
+            (&mut ships_stats, &character_markers, &players)
+                .join()
+                .find(|(_, _, player)| player.id == 0)
         {
             value
         } else {
             return;
         };
-        #[cfg(not(target_os = "android"))]
         {
             let mut character = None;
-            for (entity, iso, _vel, spin, _char_marker) in (
+            for (entity, iso, _vel, spin, _char_marker, player) in (
                 &entities,
                 &isometries,
                 &mut velocities,
                 &mut spins,
                 &character_markers,
+                &players,
             )
                 .join()
             {
+                if player.id != 0 {
+                    continue;
+                }
                 character = Some(entity);
-                if mouse_state.left {
+                let aim = actions.aim.map(|aim| match aim {
+                    Aim::At(point) => Vector2::new(
+                        point.x - iso.0.translation.vector.x,
+                        point.y - iso.0.translation.vector.y,
+                    ),
+                    Aim::Direction(direction) => direction,
+                });
+                if coop.is_downed(entity) {
+                    continue;
+                }
+                if let Some(aim) = aim {
                     let player_torque = DT
                         * calculate_player_ship_spin_for_aim(
-                            Vector2::new(mouse_state.x, mouse_state.y)
-                                - Vector2::new(
-                                    iso.0.translation.vector.x,
-                                    iso.0.translation.vector.y,
-                                ),
+                            aim,
                             iso.rotation(),
                             spin.0,
                         );
                     spin.0 += player_torque.max(-MAX_TORQUE).min(MAX_TORQUE);
+                } else if actions.turn != 0.0 {
+                    spin.0 = actions.turn * KEYS_TURN_SPIN;
+                    self.turning = true;
+                } else if self.turning {
+                    spin.0 = 0.0;
+                    self.turning = false;
                 }
             }
             let character = character.unwrap();
-            let (character_isometry, mut character_velocity) = {
+            let mut character_velocity = {
                 let character_body = world
                     .rigid_body(physics.get(character).unwrap().body_handle)
                     .unwrap();
-                (*character_body.position(), *character_body.velocity())
+                *character_body.velocity()
             };
+            let downed = coop.is_downed(character);
+            let stunned = downed
+                || status_effects
+                    .get(character)
+                    .map_or(false, |effects| effects.stunned());
+            let ship_stats =
+                effective_stats(ship_stats, status_effects.get(character));
+            let fire = actions.fire;
             if let Some(multy_lazer) = multiple_lazers.get_mut(character) {
-                if mouse_state.left {
+                if fire && !stunned {
                     multy_lazer.set_all(true);
                 } else {
                     multy_lazer.set_all(false);
                 }
             }
+            if let Some(ship_hardpoints) = hardpoints.get_mut(character) {
+                ship_hardpoints.firing = fire && !stunned;
+            }
             let mut process_lazer =
                 |isometry: &Isometry3,
                  lazer: &mut Lazer,
@@ -165,11 +197,31 @@ impl<'a> System<'a> for ControlSystem {
                         if let Some(target_entity) =
                             bodies_map.get(&closest_body.unwrap())
                         {
-                            if let Some(_) = lifes.get(*target_entity) {
+                            let invulnerable = dashes
+                                .get(*target_entity)
+                                .map_or(false, |dash| dash.invulnerable());
+                            if lifes.get(*target_entity).is_some()
+                                && !coop.is_downed(*target_entity)
+                                && !invulnerable
+                            {
+                                if let (Some(effect), Some(effects)) = (
+                                    lazer.effect,
+                                    status_effects.get_mut(*target_entity),
+                                ) {
+                                    effects.apply(
+                                        effect,
+                                        TRACKER.lock().unwrap().now(),
+                                    );
+                                }
                                 if process_damage(
                                     lifes.get_mut(*target_entity).unwrap(),
                                     shields.get_mut(*target_entity),
-                                    lazer.damage,
+                                    affected_damage(
+                                        status_effects.get(*target_entity),
+                                        lazer.damage,
+                                    ),
+                                    lazer.damage_type,
+                                    resistances.get(*target_entity),
                                 ) {
                                     let explosion_isometry = isometries
                                         .get(*target_entity)
@@ -202,14 +254,37 @@ impl<'a> System<'a> for ControlSystem {
                                             .get(asteroid)
                                             .unwrap()
                                             .clone();
+                                        let (kind, material) = asteroid_kind(
+                                            asteroid,
+                                            &asteroid_kinds,
+                                            &asteroids_description,
+                                        );
+                                        let velocity = body_velocity(
+                                            physics.get(asteroid),
+                                            &world,
+                                        );
+                                        let impact = Impact::new(
+                                            pos + min_d * dir,
+                                            dir,
+                                            lazer.damage,
+                                        );
                                         let channel_arc =
                                             (*asteroids_channel).clone();
+                                        let drop_rate = director.drop_rate;
+                                        let rng = StdRng::seed_from_u64(
+                                            run_rngs.drops.gen(),
+                                        );
                                         thread::spawn(move || {
                                             spawn_asteroids(
                                                 iso,
+                                                velocity,
                                                 poly,
+                                                kind,
+                                                material,
                                                 channel_arc,
-                                                None,
+                                                Some(impact),
+                                                drop_rate,
+                                                rng,
                                             );
                                         });
                                     } else {
@@ -233,16 +308,14 @@ impl<'a> System<'a> for ControlSystem {
                                     insert_channel.single_write(
                                         InsertEvent::Wobble(EXPLOSION_WOBBLE),
                                     );
-                                    if character_markers
+                                    let downed = character_markers
                                         .get(*target_entity)
                                         .is_some()
-                                    {
-                                        to_menu(
+                                        && knock_out(
                                             &mut app_state,
-                                            &mut progress,
-                                            &mut macro_game.score_table,
+                                            &mut coop,
+                                            *target_entity,
                                         );
-                                    }
                                     let effect_position =
                                         position + dir * min_d;
                                     let effect = InsertEvent::Explosion {
@@ -255,7 +328,20 @@ impl<'a> System<'a> for ControlSystem {
                                         with_animation: None,
                                     };
                                     insert_channel.single_write(effect);
-                                    entities.delete(*target_entity).unwrap();
+                                    let is_asteroid = asteroid_markers
+                                        .get(*target_entity)
+                                        .is_some();
+                                    if !downed && is_asteroid {
+                                        entities
+                                            .delete(*target_entity)
+                                            .unwrap();
+                                    } else if !downed {
+                                        destroy_ship(
+                                            *target_entity,
+                                            &entities,
+                                            &mut destroyed_channel,
+                                        );
+                                    }
                                 }
                             }
                         }
@@ -281,6 +367,8 @@ impl<'a> System<'a> for ControlSystem {
                         active: true,
                         distance: dir.norm(),
                         current_distance: dir.norm(),
+                        effect: None,
+                        damage_type: DamageType::Energy,
                     };
                     let dir = Vector2::new(dir.x, dir.y);
                     let rotation = Rotation2::rotation_between(&up, &dir);
@@ -331,119 +419,46 @@ impl<'a> System<'a> for ControlSystem {
                 }
             }
             info!("asteroids: ended process multy lazers");
-            info!("asteroids: started process crazyness");
-            let gun_position = Point2::new(
-                character_isometry.translation.vector.x,
-                character_isometry.translation.vector.y,
-            );
-            if mouse_state.left {
-                if let Some(shotgun) = shotguns.get_mut(character) {
-                    if shotgun.shoot() {
-                        let bullets = shotgun.spawn_bullets(
-                            EntityType::Player,
-                            isometries.get(character).unwrap().0,
-                            shotgun.bullet_speed,
-                            shotgun.bullets_damage,
-                            velocities.get(character).unwrap().0,
-                            character,
-                        );
-                        info!("asteroids: bullets {:?} processed", bullets);
-                        sounds_channel.single_write(Sound(
-                            preloaded_sounds.shot,
-                            gun_position,
-                        ));
-                        insert_channel.iter_write(bullets.into_iter());
-                    }
-                }
+            info!("asteroids: started applying actions");
+            if actions.held(Action::ZoomIn) {
+                canvas.z_far -= 0.5;
             }
-            info!("asteroids: started reading keys");
-            self.prev_keys = self.new_keys.clone();
-            self.new_keys.clear();
-            let mut angular_vel = None;
-            for key in keys_channel.read(&mut self.reader) {
-                self.new_keys.insert(*key);
-                let mut thrust = match key {
-                    Keycode::W => {
-                        ship_stats.thrust_force * Vector3::new(0.0, -1.0, 0.0)
-                    }
-                    Keycode::S => {
-                        ship_stats.thrust_force * Vector3::new(0.0, 1.0, 0.0)
-                    }
-                    Keycode::A => {
-                        ship_stats.thrust_force * Vector3::new(-1.0, 0.0, 0.0)
-                    }
-                    Keycode::D => {
-                        ship_stats.thrust_force * Vector3::new(1.0, 0.0, 0.0)
-                    }
-                    _ => Vector3::new(0f32, 0f32, 0f32),
-                };
-                match key {
-                    Keycode::LeftBracket => {
-                        canvas.z_far -= 0.5;
-                    }
-                    Keycode::RightBracket => {
-                        canvas.z_far += 0.5;
-                    }
-                    Keycode::Left => {
-                        let player_torque = -5. * DT;
-                        let character_body = world
-                            .rigid_body_mut(physics.get(character).unwrap().body_handle)
-                            .unwrap();
-                        angular_vel = Some(player_torque.max(-MAX_TORQUE).min(MAX_TORQUE));
-                        // character_body.set_angular_velocity(player_torque.max(-MAX_TORQUE).min(MAX_TORQUE));
-                    }
-                    Keycode::Right => {
-                        let player_torque = 5. * DT;
-                        let character_body = world
-                            .rigid_body_mut(physics.get(character).unwrap().body_handle)
-                            .unwrap();
-                        angular_vel = Some(player_torque.max(-MAX_TORQUE).min(MAX_TORQUE));
-                        // character_body.set_angular_velocity(player_torque.max(-MAX_TORQUE).min(MAX_TORQUE));
-                    }
-                    Keycode::Up => {
-                        // copy paste TODO
-                        if let Some(shotgun) = shotguns.get_mut(character) {
-                            if shotgun.shoot() {
-                                let bullets = shotgun.spawn_bullets(
-                                    EntityType::Player,
-                                    isometries.get(character).unwrap().0,
-                                    shotgun.bullet_speed,
-                                    shotgun.bullets_damage,
-                                    velocities.get(character).unwrap().0,
-                                    character,
-                                );
-                                info!("asteroids: bullets {:?} processed", bullets);
-                                sounds_channel.single_write(Sound(
-                                    preloaded_sounds.shot,
-                                    gun_position,
-                                ));
-                                insert_channel.iter_write(bullets.into_iter());
-                            }
-                        }
-                    }
-                    _ => (),
-                };
-                thrust = thrust_calculation(
+            if actions.held(Action::ZoomOut) {
+                canvas.z_far += 0.5;
+            }
+            if actions.thrust.norm() > 0.0 {
+                let thrust = thrust_calculation(
                     ship_stats.maneuverability.unwrap(),
-                    thrust,
+                    ship_stats.thrust_force
+                        * Vector3::new(actions.thrust.x, actions.thrust.y, 0.0),
                     *character_velocity.as_vector(),
                 );
                 *character_velocity.as_vector_mut() += thrust;
             }
-            let new_pressed = &self.new_keys - &self.prev_keys;
-            for key in new_pressed.iter() {
-                match key {
-                    Keycode::Space => {
-                        *app_state = AppState::Play(PlayState::Upgrade)
-                    }
-                    Keycode::T => {
-                        dev_info.draw_telemetry = !dev_info.draw_telemetry;
-                    }
-                    _ => (),
+            let mut dash_impulse = None;
+            if let Some(dash) = dashes.get_mut(character) {
+                if actions.just_pressed(Action::Dash) && !stunned && dash.dash()
+                {
+                    // along thrust, forward if ship isn't thrusting
+                    let direction = if actions.thrust.norm() > 0.0 {
+                        actions.thrust.normalize()
+                    } else {
+                        let rotation =
+                            isometries.get(character).unwrap().0.rotation;
+                        let forward = rotation * Vector3::new(0.0, -1.0, 0.0);
+                        Vector2::new(forward.x, forward.y)
+                    };
+                    dash_impulse = Some(DASH_IMPULSE * direction);
                 }
             }
-            info!("asteroids: ended reading keys");
-            if mouse_state.right {
+            if actions.just_pressed(Action::OpenUpgrades) {
+                *app_state = AppState::Play(PlayState::Upgrade)
+            }
+            if actions.just_pressed(Action::ToggleTelemetry) {
+                dev_info.draw_telemetry = !dev_info.draw_telemetry;
+            }
+            info!("asteroids: ended applying actions");
+            if actions.reverse {
                 let rotation = isometries.get(character).unwrap().0.rotation;
                 let _vel = velocities.get_mut(character).unwrap();
                 let thrust = ship_stats.thrust_force
@@ -453,9 +468,16 @@ impl<'a> System<'a> for ControlSystem {
             let character_body = world
                 .rigid_body_mut(physics.get(character).unwrap().body_handle)
                 .unwrap();
-            character_body.set_velocity(character_velocity);
-            if let Some(angular_vel) = angular_vel {
-                character_body.set_angular_velocity(angular_vel);
+            if !downed {
+                character_body.set_velocity(character_velocity);
+            }
+            if let Some(impulse) = dash_impulse {
+                character_body.apply_force(
+                    0,
+                    &Force2::new(impulse, 0.0),
+                    ForceType::VelocityChange,
+                    true,
+                );
             }
         }
         info!("asteroids: ended process crazyness");
@@ -0,0 +1,232 @@
+use sdl2::keyboard::Keycode;
+
+use super::*;
+use crate::setup::save_bindings;
+use gfx_h::{TextData, WorldTextData};
+
+/// Screen to rebind controls
+pub struct ControlsRendering {
+    reader: ReaderId<Primitive>,
+    // action which waits for a new binding
+    waiting: Option<Action>,
+    prev_keys: Vec<Keycode>,
+}
+
+impl ControlsRendering {
+    pub fn new(reader: ReaderId<Primitive>) -> Self {
+        ControlsRendering {
+            reader: reader,
+            waiting: None,
+            prev_keys: vec![],
+        }
+    }
+}
+
+impl<'a> System<'a> for ControlsRendering {
+    type SystemData = (
+        ReadExpect<'a, ThreadPin<red::GL>>,
+        WriteExpect<'a, Canvas>,
+        ReadExpect<'a, red::Viewport>,
+        Write<'a, EventChannel<Primitive>>,
+        Write<'a, UI>,
+        Read<'a, Mouse>,
+        Read<'a, Gamepad>,
+        Read<'a, Vec<Keycode>>,
+        WriteExpect<'a, ThreadPin<TextData<'static>>>,
+        WriteExpect<'a, ThreadPin<WorldTextData<'static>>>,
+        Write<'a, AppState>,
+        Write<'a, Bindings>,
+        ReadExpect<'a, PreloadedImages>,
+        Write<'a, EventChannel<Sound>>,
+        ReadExpect<'a, PreloadedSounds>,
+        Entities<'a>,
+        ReadStorage<'a, CharacterMarker>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            gl,
+            mut canvas,
+            viewport,
+            mut primitives_channel,
+            mut ui,
+            mouse,
+            gamepad,
+            keys,
+            mut text_data,
+            mut world_text_data,
+            mut app_state,
+            mut bindings,
+            preloaded_images,
+            mut sounds_channel,
+            preloaded_sounds,
+            entities,
+            character_markers,
+        ) = data;
+        let mut frame = red::Frame::new(&gl);
+        frame.set_clear_color(0.0, 0.0, 0.0, 1.0);
+        frame.clear_color();
+        let dims = viewport.dimensions();
+        let (w, h) = (dims.0 as f32, dims.1 as f32);
+        if let Some(action) = self.waiting {
+            let new_key = keys
+                .iter()
+                .find(|key| !self.prev_keys.contains(key))
+                .cloned();
+            // the click which started rebinding was released before
+            let binding = if let Some(key) = new_key {
+                Some(Binding::key(key))
+            } else if mouse.left_released {
+                Some(Binding::MouseLeft)
+            } else if mouse.right_released {
+                Some(Binding::MouseRight)
+            } else {
+                gamepad
+                    .just_pressed_buttons()
+                    .first()
+                    .map(|button| Binding::pad(*button))
+            };
+            if let Some(binding) = binding {
+                bindings.bind(action, binding);
+                self.waiting = None;
+            }
+        }
+        self.prev_keys = keys.clone();
+        let conflicts = bindings.conflicts();
+        // all actions and a few conflict lines fit the screen
+        let row_h = h / (Action::ALL.len() + 4) as f32;
+        let (bind_button_w, bind_button_h) = (w / 4.0, 0.8 * row_h);
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let current_h = h / 20.0 + i as f32 * row_h;
+            let conflict = conflicts
+                .iter()
+                .any(|(_, actions)| actions.contains(action));
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Text(Text {
+                    position: Point2::new(w / 3.0, current_h + row_h / 2.0),
+                    color: if conflict {
+                        (1.0, 0.4, 0.4, 1.0)
+                    } else {
+                        (1.0, 1.0, 1.0, 1.0)
+                    },
+                    text: action.name().to_string(),
+                    font_size: 1.0,
+                }),
+                with_projection: false,
+            });
+            let text = if self.waiting == Some(*action) {
+                "Press...".to_string()
+            } else {
+                bindings
+                    .get(*action)
+                    .iter()
+                    .map(|binding| binding.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let bind_button = Button::new(
+                Point2::new(w / 2.0, current_h),
+                bind_button_w,
+                bind_button_h,
+                None,
+                false,
+                Some(preloaded_images.upg_bar),
+                text,
+                Widgets::ShopItem as usize + i,
+                Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+                Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
+            );
+            if bind_button.place_and_check(&mut ui, &*mouse) {
+                self.waiting = Some(*action);
+            }
+        }
+        for (i, (binding, actions)) in conflicts.iter().enumerate() {
+            let names: Vec<&str> =
+                actions.iter().map(|action| action.name()).collect();
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Text(Text {
+                    position: Point2::new(
+                        w / 2.0,
+                        h / 20.0 + (Action::ALL.len() + i) as f32 * row_h,
+                    ),
+                    color: (1.0, 0.4, 0.4, 1.0),
+                    text: format!(
+                        "{} is used by {}",
+                        binding.name(),
+                        names.join(", ")
+                    ),
+                    font_size: 1.0,
+                }),
+                with_projection: false,
+            });
+        }
+
+        let (button_w, button_h) = (w / 6f32, h / 12f32);
+        let reset_button = Button::new(
+            Point2::new(
+                w / 2.0 - button_w - button_w / 4.0,
+                h - 1.5 * button_h,
+            ),
+            button_w,
+            button_h,
+            Some(Point3::new(0f32, 0f32, 0f32)),
+            false,
+            None,
+            "Reset".to_string(),
+            Widgets::ResetBindings as usize,
+            None,
+            None,
+        );
+        if reset_button.place_and_check(&mut ui, &*mouse) {
+            *bindings = Bindings::default();
+            self.waiting = None;
+        }
+        let back_to_menu = Button::new(
+            Point2::new(w / 2.0 + button_w / 4.0, h - 1.5 * button_h),
+            button_w,
+            button_h,
+            Some(Point3::new(0f32, 0f32, 0f32)),
+            false,
+            None,
+            "Back".to_string(),
+            Widgets::BackMenu as usize,
+            None,
+            None,
+        );
+        let back = ui.take_back();
+        if back_to_menu.place_and_check(&mut ui, &*mouse) || back {
+            // conflicting controls are not saved
+            if conflicts.is_empty() {
+                save_bindings(&bindings);
+                self.waiting = None;
+                // opened from the pause menu if the run is going on
+                let in_run =
+                    (&entities, &character_markers).join().next().is_some();
+                *app_state = if in_run {
+                    AppState::Paused
+                } else {
+                    AppState::Menu
+                };
+            } else {
+                sounds_channel.single_write(Sound(
+                    preloaded_sounds.deny,
+                    Point2::new(0f32, 0f32),
+                ));
+            }
+        }
+
+        primitives_channel.iter_write(ui.primitives.drain(..));
+        sounds_channel.iter_write(ui.sounds.drain(..));
+        render_primitives(
+            &mouse,
+            &mut self.reader,
+            &mut frame,
+            &gl,
+            &mut canvas,
+            &viewport,
+            &mut primitives_channel,
+            &mut text_data,
+            &mut world_text_data,
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use sdl2::keyboard::Keycode;
+use std::collections::HashSet;
+
+use super::*;
+use log::info;
+use nphysics2d::algebra::{Force2, ForceType};
+use physics::*;
+
+/// Keys used by the second player, first player ignores them in co-op
+pub const SECOND_PLAYER_KEYS: [Keycode; 6] = [
+    Keycode::Left,
+    Keycode::Right,
+    Keycode::Up,
+    Keycode::Down,
+    Keycode::RCtrl,
+    Keycode::RShift,
+];
+
+/// Controls of the second local player: left and right arrows to turn,
+/// up to thrust, down or right ctrl to shoot, right shift to dash
+pub struct SecondPlayerControl {
+    reader: ReaderId<Keycode>,
+    // dash is triggered on press, not while held
+    dash_held: bool,
+}
+
+impl SecondPlayerControl {
+    pub fn new(reader: ReaderId<Keycode>) -> Self {
+        SecondPlayerControl {
+            reader: reader,
+            dash_held: false,
+        }
+    }
+}
+
+impl<'a> System<'a> for SecondPlayerControl {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, PhysicsComponent>,
+        ReadStorage<'a, CharacterMarker>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, ShipStats>,
+        ReadStorage<'a, StatusEffects>,
+        WriteStorage<'a, Spin>,
+        WriteStorage<'a, MultyLazer>,
+        WriteStorage<'a, Hardpoints>,
+        WriteStorage<'a, Dash>,
+        Read<'a, EventChannel<Keycode>>,
+        Write<'a, World<f32>>,
+        Read<'a, Coop>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: second player control started");
+        let (
+            entities,
+            isometries,
+            physics,
+            character_markers,
+            players,
+            ships_stats,
+            status_effects,
+            mut spins,
+            mut multy_lazers,
+            mut hardpoints,
+            mut dashes,
+            keys_channel,
+            mut world,
+            coop,
+        ) = data;
+        // read anyway, so old keys don't pile up
+        let keys: HashSet<Keycode> =
+            keys_channel.read(&mut self.reader).cloned().collect();
+        let character = if let Some((entity, _, _)) =
+            (&entities, &character_markers, &players)
+                .join()
+                .find(|(_, _, player)| player.id == 1)
+        {
+            entity
+        } else {
+            return;
+        };
+        let downed = coop.is_downed(character);
+        let stunned = downed
+            || status_effects
+                .get(character)
+                .map_or(false, |effects| effects.stunned());
+        let shoot = !stunned
+            && (keys.contains(&Keycode::Down)
+                || keys.contains(&Keycode::RCtrl));
+        if let Some(multy_lazer) = multy_lazers.get_mut(character) {
+            multy_lazer.set_all(shoot);
+        }
+        if let Some(ship_hardpoints) = hardpoints.get_mut(character) {
+            ship_hardpoints.firing = shoot;
+        }
+        let isometry = isometries.get(character).unwrap().0;
+        if downed {
+            return;
+        }
+        let ship_stats = effective_stats(
+            ships_stats.get(character).unwrap(),
+            status_effects.get(character),
+        );
+        let character_body = world
+            .rigid_body_mut(physics.get(character).unwrap().body_handle)
+            .unwrap();
+        let mut character_velocity = *character_body.velocity();
+        if keys.contains(&Keycode::Up) {
+            let thrust = ship_stats.thrust_force
+                * (isometry.rotation * Vector3::new(0.0, -1.0, 0.0));
+            *character_velocity.as_vector_mut() += thrust;
+        }
+        character_body.set_velocity(character_velocity);
+        let dash_pressed = keys.contains(&Keycode::RShift) && !self.dash_held;
+        self.dash_held = keys.contains(&Keycode::RShift);
+        if let Some(dash) = dashes.get_mut(character) {
+            if dash_pressed && !stunned && dash.dash() {
+                let forward = isometry.rotation * Vector3::new(0.0, -1.0, 0.0);
+                character_body.apply_force(
+                    0,
+                    &Force2::new(
+                        DASH_IMPULSE * Vector2::new(forward.x, forward.y),
+                        0.0,
+                    ),
+                    ForceType::VelocityChange,
+                    true,
+                );
+            }
+        }
+        // kinematic system turns spin into angular velocity
+        if let Some(spin) = spins.get_mut(character) {
+            spin.0 = if keys.contains(&Keycode::Left) {
+                -KEYS_TURN_SPIN
+            } else if keys.contains(&Keycode::Right) {
+                KEYS_TURN_SPIN
+            } else {
+                0.0
+            };
+        }
+        info!("asteroids: second player control ended");
+    }
+}
+
+/// Revives downed players when a teammate stays near them long enough
+#[derive(Default)]
+pub struct CoopSystem;
+
+impl<'a> System<'a> for CoopSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, ShipStats>,
+        WriteStorage<'a, Lifes>,
+        Write<'a, Coop>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            isometries,
+            ships_stats,
+            mut lifes,
+            mut coop,
+            lazy_update,
+        ) = data;
+        let gone: Vec<Entity> = coop
+            .players
+            .iter()
+            .cloned()
+            .filter(|player| !entities.is_alive(*player))
+            .collect();
+        for player in gone.into_iter() {
+            coop.remove(player);
+        }
+        let standing: Vec<Point2> = coop
+            .players
+            .iter()
+            .filter(|player| !coop.is_downed(**player))
+            .filter_map(|player| isometries.get(*player))
+            .map(|iso| {
+                let position = iso.0.translation.vector;
+                Point2::new(position.x, position.y)
+            })
+            .collect();
+        let time_scaler =
+            normalize_60frame(TRACKER.lock().unwrap().last_delta());
+        let mut revived = vec![];
+        for (entity, revive) in coop.downed.iter_mut() {
+            let position = if let Some(iso) = isometries.get(*entity) {
+                let position = iso.0.translation.vector;
+                Point2::new(position.x, position.y)
+            } else {
+                continue;
+            };
+            if standing
+                .iter()
+                .any(|teammate| (teammate - position).norm() < REVIVE_RADIUS)
+            {
+                *revive += REVIVE_SPEED * time_scaler;
+            }
+            if *revive >= 1.0 {
+                revived.push((*entity, position));
+            }
+        }
+        for (entity, position) in revived.into_iter() {
+            coop.downed.retain(|(downed, _)| *downed != entity);
+            if let (Some(life), Some(ship_stats)) =
+                (lifes.get_mut(entity), ships_stats.get(entity))
+            {
+                life.0 =
+                    (ship_stats.max_health as f32 * REVIVE_LIFES_PART) as usize;
+            }
+            add_text(
+                &entities,
+                TextComponent {
+                    text: "Revived".to_string(),
+                    color: (0.6, 1.0, 0.6, 1.0),
+                },
+                &lazy_update,
+                position,
+                Some(Lifetime::new(Duration::from_secs(1))),
+            );
+        }
+    }
+}
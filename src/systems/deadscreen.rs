@@ -1,4 +1,5 @@
 pub use super::*;
+use crate::setup::save_director_history;
 
 #[derive(Default)]
 pub struct DeadScreen;
@@ -17,6 +18,12 @@ impl<'a> System<'a> for DeadScreen {
         WriteExpect<'a, PreloadedImages>,
         ReadStorage<'a, ShipMarker>,
         ReadStorage<'a, AsteroidMarker>,
+        Read<'a, AvaliableUpgrades>,
+        Write<'a, RunUpgrades>,
+        Read<'a, RunMode>,
+        Read<'a, GameMode>,
+        Read<'a, RunTimer>,
+        ReadExpect<'a, Director>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -33,6 +40,12 @@ impl<'a> System<'a> for DeadScreen {
             preloaded_images,
             ship_markers,
             asteroid_markers,
+            avaliable_upgrades,
+            mut run_upgrades,
+            run_mode,
+            game_mode,
+            run_timer,
+            director,
         ) = data;
         let dims = viewport.dimensions();
         let (w, h) = (dims.0 as f32, dims.1 as f32);
@@ -46,6 +59,20 @@ impl<'a> System<'a> for DeadScreen {
             }),
             with_projection: false,
         });
+        let title = if run_timer.time_up {
+            "Time is up"
+        } else {
+            game_mode.name()
+        };
+        ui.primitives.push(Primitive {
+            kind: PrimitiveKind::Text(Text {
+                position: Point2::new(w / 2.0, h / 2.0 - h / 10.0),
+                text: title.to_string(),
+                color: (1.0, 1.0, 0.7, 1.0),
+                font_size: 1.0,
+            }),
+            with_projection: false,
+        });
         ui.primitives.push(Primitive {
             kind: PrimitiveKind::Text(Text {
                 position: Point2::new(w / 2.0, h / 2.0),
@@ -69,7 +96,8 @@ impl<'a> System<'a> for DeadScreen {
             None,
             None,
         );
-        if to_menu.place_and_check(&mut ui, &*mouse) {
+        let back = ui.take_back();
+        if to_menu.place_and_check(&mut ui, &*mouse) || back {
             for (entity, _ship_marker) in (&entities, &ship_markers).join() {
                 entities.delete(entity).unwrap();
             }
@@ -80,8 +108,29 @@ impl<'a> System<'a> for DeadScreen {
             }
             *spawned_upgrades = vec![];
             *app_state = AppState::Menu;
-            macro_game.score_table.push(progress.score);
-            macro_game.score_table.sort_by(|a, b| b.cmp(a));
+            save_director_history(&director);
+            match *run_mode {
+                RunMode::Normal => {
+                    macro_game.add_score(*game_mode, progress.score);
+                }
+                RunMode::Daily { day, .. } => {
+                    let best = macro_game.daily_best.entry(day).or_insert(0);
+                    *best = (*best).max(progress.score);
+                }
+            }
+            macro_game.runs.push(RunRecord {
+                score: progress.score,
+                level: progress.level,
+                upgrades: run_upgrades
+                    .picked
+                    .iter()
+                    .map(|&id| avaliable_upgrades[id].name.clone())
+                    .collect(),
+            });
+            if macro_game.runs.len() > RUN_RECORDS_MAX {
+                macro_game.runs.remove(0);
+            }
+            *run_upgrades = RunUpgrades::default();
             *progress = Progress::default();
             *current_wave = CurrentWave::default();
         }
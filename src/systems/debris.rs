@@ -0,0 +1,84 @@
+use super::*;
+use log::info;
+
+/// Breaks destroyed ships into debris along their sprite outlines
+pub struct DebrisSystem {
+    reader: ReaderId<ShipDestroyed>,
+}
+
+impl DebrisSystem {
+    pub fn new(reader: ReaderId<ShipDestroyed>) -> Self {
+        DebrisSystem { reader }
+    }
+}
+
+impl<'a> System<'a> for DebrisSystem {
+    type SystemData = (
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, ShipOutline>,
+        ReadStorage<'a, AtlasImage>,
+        ReadStorage<'a, Size>,
+        ReadStorage<'a, PhysicsComponent>,
+        Read<'a, World<f32>>,
+        Write<'a, EventChannel<InsertEvent>>,
+        Read<'a, EventChannel<ShipDestroyed>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: debris started");
+        let (
+            isometries,
+            outlines,
+            images,
+            sizes,
+            physics,
+            world,
+            mut insert_channel,
+            destroyed_channel,
+        ) = data;
+        let mut rng = thread_rng();
+        for &ShipDestroyed(entity) in destroyed_channel.read(&mut self.reader) {
+            let (iso, outline, image) = match (
+                isometries.get(entity),
+                outlines.get(entity),
+                images.get(entity),
+            ) {
+                (Some(iso), Some(outline), Some(image)) => {
+                    (iso, outline, image)
+                }
+                _ => continue,
+            };
+            let velocity = body_velocity(physics.get(entity), &world);
+            let size = sizes.get(entity).map_or(1f32, |size| size.0);
+            let position = iso.0.translation.vector;
+            let angle = iso.0.rotation.euler_angles().2;
+            let rotation = Rotation2::new(angle);
+            let impact = outline.0.center()
+                + size
+                    * Vector2::new(
+                        rng.gen_range(-0.3, 0.3),
+                        rng.gen_range(-0.3, 0.3),
+                    );
+            for shard in outline.0.fracture(impact, DEBRIS_SITES, &mut rng) {
+                // pieces fly away from the ship center
+                let offset = rotation * shard.center().coords;
+                let kick = if offset.norm() > EPS {
+                    DEBRIS_KICK * offset.normalize()
+                } else {
+                    Vector2::new(0f32, 0f32)
+                };
+                insert_channel.single_write(InsertEvent::Debris {
+                    iso: Point3::new(position.x, position.y, angle),
+                    velocity: Velocity2::new(
+                        velocity.linear + kick,
+                        velocity.angular + rng.gen_range(-0.1, 0.1),
+                    ),
+                    polygon: shard,
+                    image: *image,
+                    size: size,
+                });
+            }
+        }
+        info!("asteroids: debris ended");
+    }
+}
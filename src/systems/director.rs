@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use super::*;
+use log::info;
+use telemetry::TeleGraph;
+
+/// Collects performance of the players for the difficulty director
+/// and lets it adjust the run
+pub struct DirectorSystem {
+    reader: ReaderId<ShipDestroyed>,
+    // lifes and shield of characters on the previous frame
+    durability: HashMap<Entity, (usize, usize)>,
+    // characters which are close to death right now
+    near_death: Vec<Entity>,
+    // when enemies were seen first
+    spawned: HashMap<Entity, Instant>,
+}
+
+impl DirectorSystem {
+    pub fn new(reader: ReaderId<ShipDestroyed>) -> Self {
+        DirectorSystem {
+            reader,
+            durability: HashMap::new(),
+            near_death: vec![],
+            spawned: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for DirectorSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CharacterMarker>,
+        ReadStorage<'a, ShipMarker>,
+        ReadStorage<'a, Lifes>,
+        ReadStorage<'a, Shield>,
+        ReadStorage<'a, ShipStats>,
+        Read<'a, Coop>,
+        Read<'a, EventChannel<ShipDestroyed>>,
+        WriteExpect<'a, Director>,
+        WriteExpect<'a, TeleGraph>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            character_markers,
+            ships,
+            lifes,
+            shields,
+            ships_stats,
+            coop,
+            destroyed_channel,
+            mut director,
+            mut telegraph,
+        ) = data;
+        let now = TRACKER.lock().unwrap().now();
+        for (entity, life, shield, ship_stats, _) in (
+            &entities,
+            &lifes,
+            &shields,
+            &ships_stats,
+            &character_markers,
+        )
+            .join()
+        {
+            if coop.is_downed(entity) {
+                continue;
+            }
+            // regeneration is not a performance, only losses are counted
+            if let Some((prev_life, prev_shield)) =
+                self.durability.insert(entity, (life.0, shield.0))
+            {
+                let prev = prev_life + prev_shield;
+                let current = life.0 + shield.0;
+                if prev > current {
+                    director.stats.damage_taken += prev - current;
+                }
+            }
+            if ship_stats.max_shield > 0 {
+                director.stats.shield_samples += 1;
+                if shield.0 > 0 {
+                    director.stats.shield_up_samples += 1;
+                }
+            }
+            let low = (life.0 as f32)
+                < ship_stats.max_health as f32 * NEAR_DEATH_LIFES_PART;
+            let was_low = self.near_death.contains(&entity);
+            if low && !was_low {
+                director.stats.near_deaths += 1;
+                self.near_death.push(entity);
+            } else if !low && was_low {
+                self.near_death.retain(|character| *character != entity);
+            }
+        }
+        self.durability
+            .retain(|entity, _| entities.is_alive(*entity));
+        self.near_death.retain(|entity| entities.is_alive(*entity));
+        for (entity, _, _) in (&entities, &ships, !&character_markers).join() {
+            self.spawned.entry(entity).or_insert(now);
+        }
+        // characters are not in spawned, despawned enemies are not kills
+        for &ShipDestroyed(entity) in destroyed_channel.read(&mut self.reader) {
+            if let Some(spawned) = self.spawned.remove(&entity) {
+                director.stats.kills += 1;
+                director.stats.time_to_kill += now - spawned;
+            }
+        }
+        self.spawned.retain(|entity, _| entities.is_alive(*entity));
+        if let Some(record) = director.update(now) {
+            info!("asteroids: director {:?}", record);
+        }
+        // plots are in 0..1
+        telegraph.insert(
+            "director intensity".to_string(),
+            (director.intensity + 1.0) / 2.0,
+        );
+    }
+}
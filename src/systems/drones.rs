@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+use log::info;
+
+/// Keeps drones near their owners, aims and shoots them,
+/// takes hits and respawns destroyed drones
+///
+/// Drones have no physics bodies, so hits by projectiles, enemy lazers
+/// and ramming ships are checked by distance here. Blasts are handled
+/// in gameplay together with other ships.
+#[derive(Default)]
+pub struct DroneSystem {
+    /// (drone, enemy ship) pairs touching on the previous frame,
+    /// ramming hurts only once per contact
+    touching: HashSet<(Entity, Entity)>,
+}
+
+impl<'a> System<'a> for DroneSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Isometry>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, Size>,
+        ReadStorage<'a, CharacterMarker>,
+        ReadStorage<'a, EnemyMarker>,
+        ReadStorage<'a, ShipMarker>,
+        ReadStorage<'a, Projectile>,
+        ReadStorage<'a, Damage>,
+        ReadStorage<'a, DamageType>,
+        ReadStorage<'a, MultyLazer>,
+        WriteStorage<'a, Lifes>,
+        WriteStorage<'a, DamageFlash>,
+        WriteStorage<'a, Drone>,
+        WriteStorage<'a, DroneBay>,
+        WriteStorage<'a, AttachAim>,
+        WriteStorage<'a, AttachOffset>,
+        Read<'a, Coop>,
+        Write<'a, EventChannel<InsertEvent>>,
+        Write<'a, EventChannel<Sound>>,
+        ReadExpect<'a, PreloadedSounds>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: drone system started");
+        let (
+            entities,
+            mut isometries,
+            velocities,
+            sizes,
+            character_markers,
+            enemies,
+            ship_markers,
+            projectiles,
+            damages,
+            damage_types,
+            multy_lazers,
+            mut lifes,
+            mut flashes,
+            mut drones,
+            mut drone_bays,
+            mut attach_aims,
+            mut attach_offsets,
+            coop,
+            mut insert_channel,
+            mut sounds_channel,
+            preloaded_sounds,
+        ) = data;
+        let now = TRACKER.lock().unwrap().now();
+        let position = |iso: &Isometry| {
+            let position = iso.0.translation.vector;
+            Point2::new(position.x, position.y)
+        };
+        // owner rotation, size and velocity
+        let mut owners = HashMap::new();
+        for (owner, bay, iso, size) in
+            (&entities, &mut drone_bays, &isometries, &sizes).join()
+        {
+            let velocity = velocities
+                .get(owner)
+                .map_or(Vector2::new(0.0, 0.0), |v| v.0);
+            owners.insert(owner, (iso.rotation(), size.0, velocity));
+            if coop.is_downed(owner) {
+                continue;
+            }
+            for (slot_id, slot) in bay.slots.iter_mut().enumerate() {
+                if slot.entity.is_none() && now >= slot.respawn_at {
+                    let drone = entities.create();
+                    slot.entity = Some(drone);
+                    insert_channel.single_write(InsertEvent::Drone {
+                        entity: drone,
+                        owner: owner,
+                        slot: slot_id,
+                        kind: slot.kind.clone(),
+                        position: position(iso),
+                    });
+                }
+            }
+        }
+        let targets: Vec<(Entity, Point2)> = (&entities, &isometries, &enemies)
+            .join()
+            .map(|(entity, iso, _)| (entity, position(iso)))
+            .collect();
+        // everything not fired by players or drones can hit a drone
+        let hostile: Vec<(Entity, Point2, f32, usize, DamageType)> =
+            (&entities, &isometries, &sizes, &projectiles, &damages)
+                .join()
+                .filter(|(_, _, _, projectile, _)| {
+                    character_markers.get(projectile.owner).is_none()
+                        && drones.get(projectile.owner).is_none()
+                })
+                .map(|(entity, iso, size, _, damage)| {
+                    let damage_type = damage_types
+                        .get(entity)
+                        .cloned()
+                        .unwrap_or(DamageType::Kinetic);
+                    (entity, position(iso), size.0, damage.0, damage_type)
+                })
+                .collect();
+        // start, direction and length of active enemy beams
+        let mut beams: Vec<(Point2, Vector2, f32, usize, DamageType)> = vec![];
+        for (iso, multy_lazer, _) in
+            (&isometries, &multy_lazers, &enemies).join()
+        {
+            let isometry = iso3_iso2(&iso.0);
+            let start = Point2::from(isometry.translation.vector);
+            for (angle, lazer) in multy_lazer.iter() {
+                if !lazer.active {
+                    continue;
+                }
+                let dir = isometry
+                    * (Rotation2::new(angle) * Vector2::new(0f32, -1f32));
+                beams.push((
+                    start,
+                    dir,
+                    lazer.current_distance,
+                    lazer.damage,
+                    lazer.damage_type,
+                ));
+            }
+        }
+        let rammers: Vec<(Entity, Point2, f32, usize)> = (
+            &entities,
+            &isometries,
+            &sizes,
+            &damages,
+            &enemies,
+            &ship_markers,
+        )
+            .join()
+            .map(|(entity, iso, size, damage, _, _)| {
+                (entity, position(iso), size.0, damage.0)
+            })
+            .collect();
+        let mut touching = HashSet::new();
+        let mut hit = HashSet::new();
+        let mut destroyed = vec![];
+        let time_scaler =
+            normalize_60frame(TRACKER.lock().unwrap().last_delta());
+        for (entity, drone, iso, size, life) in
+            (&entities, &mut drones, &mut isometries, &sizes, &mut lifes).join()
+        {
+            let (owner_rotation, owner_size, owner_velocity) =
+                match owners.get(&drone.owner) {
+                    Some(owner) => *owner,
+                    None => continue,
+                };
+            let drone_position = position(iso);
+            let mut damaged = false;
+            for (
+                projectile,
+                projectile_position,
+                radius,
+                damage,
+                damage_type,
+            ) in hostile.iter()
+            {
+                let distance = (*projectile_position - drone_position).norm();
+                if distance < size.0 + radius && hit.insert(*projectile) {
+                    entities.delete(*projectile).unwrap();
+                    process_damage(life, None, *damage, *damage_type, None);
+                    damaged = true;
+                }
+            }
+            for (start, dir, length, damage, damage_type) in beams.iter() {
+                let along = (drone_position - start).dot(dir).max(0.0);
+                let closest = start + dir * along.min(*length);
+                if (drone_position - closest).norm() < size.0 {
+                    process_damage(life, None, *damage, *damage_type, None);
+                    damaged = true;
+                }
+            }
+            for (rammer, rammer_position, radius, damage) in rammers.iter() {
+                let distance = (*rammer_position - drone_position).norm();
+                if distance < size.0 + radius {
+                    touching.insert((entity, *rammer));
+                    if !self.touching.contains(&(entity, *rammer)) {
+                        process_damage(
+                            life,
+                            None,
+                            *damage,
+                            DamageType::Collision,
+                            None,
+                        );
+                        damaged = true;
+                    }
+                }
+            }
+            if damaged {
+                if let Some(flash) = flashes.get_mut(entity) {
+                    flash.0 = (flash.0 + 0.5).min(1f32);
+                }
+            }
+            if life.0 == 0 {
+                destroyed.push((entity, drone.owner, drone.slot));
+                continue;
+            }
+            if let DroneFormation::Orbit { speed, .. } = drone.formation {
+                drone.phase += speed * time_scaler;
+            }
+            let offset = drone.offset(owner_rotation, owner_size);
+            attach_offsets.insert(entity, AttachOffset(offset)).unwrap();
+            let target = targets
+                .iter()
+                .map(|(target, target_position)| {
+                    (*target, *target_position - drone_position)
+                })
+                .filter(|(_, diff)| diff.norm() < drone.range)
+                .min_by(|a, b| a.1.norm().partial_cmp(&b.1.norm()).unwrap());
+            let angle = match target {
+                Some((target, diff)) => {
+                    attach_aims.insert(entity, AttachAim(target)).unwrap();
+                    -(-diff.x).atan2(-diff.y)
+                }
+                None => {
+                    attach_aims.remove(entity);
+                    owner_rotation
+                }
+            };
+            iso.0 = Isometry3::new(
+                iso.0.translation.vector,
+                Vector3::new(0f32, 0f32, angle),
+            );
+            if target.is_some() && !coop.is_downed(drone.owner) {
+                let bullets = drone.gun.fire(
+                    EntityType::Player,
+                    iso.0,
+                    owner_velocity,
+                    entity,
+                );
+                if !bullets.is_empty() {
+                    sounds_channel.single_write(Sound(
+                        preloaded_sounds.shot,
+                        drone_position,
+                    ));
+                }
+                insert_channel.iter_write(bullets.into_iter());
+            }
+        }
+        for (entity, owner, slot) in destroyed.into_iter() {
+            let drone_position = position(isometries.get(entity).unwrap());
+            insert_channel.single_write(InsertEvent::Explosion {
+                position: drone_position,
+                num: 3usize,
+                lifetime: Duration::from_secs(EXPLOSION_LIFETIME_SECS),
+                with_animation: None,
+            });
+            sounds_channel.single_write(Sound(
+                preloaded_sounds.ship_explosion,
+                drone_position,
+            ));
+            if let Some(bay) = drone_bays.get_mut(owner) {
+                bay.destroyed(slot);
+            }
+            entities.delete(entity).unwrap();
+        }
+        self.touching = touching;
+        info!("asteroids: drone system ended");
+    }
+}
@@ -12,7 +12,7 @@ impl<'a> System<'a> for GamePlaySystem {
             WriteStorage<'a, Isometry>,
             WriteStorage<'a, Blast>,
             WriteStorage<'a, MultyLazer>,
-            WriteStorage<'a, ShotGun>,
+            WriteStorage<'a, Hardpoints>,
             WriteStorage<'a, Lifetime>,
             WriteStorage<'a, AsteroidMarker>,
             ReadStorage<'a, CharacterMarker>,
@@ -55,6 +55,26 @@ impl<'a> System<'a> for GamePlaySystem {
         ReadExpect<'a, Arc<Mutex<EventChannel<InsertEvent>>>>,
         Read<'a, LazyUpdate>,
         Write<'a, UpgradesStats>,
+        WriteStorage<'a, StatusEffects>,
+        ReadStorage<'a, Resistances>,
+        (
+            ReadStorage<'a, Asteroid>,
+            Read<'a, AsteroidKinds>,
+            ReadStorage<'a, PhysicsComponent>,
+            Read<'a, World<f32>>,
+            Write<'a, RunUpgrades>,
+            Write<'a, RunRngs>,
+            Read<'a, GameMode>,
+            Write<'a, RunTimer>,
+            WriteStorage<'a, Player>,
+            Write<'a, Coop>,
+            ReadExpect<'a, Director>,
+            ReadStorage<'a, DroneCollectable>,
+            WriteStorage<'a, DroneBay>,
+            WriteStorage<'a, Dash>,
+            Write<'a, EventChannel<ShipDestroyed>>,
+            ReadStorage<'a, Drone>,
+        ),
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -64,7 +84,7 @@ impl<'a> System<'a> for GamePlaySystem {
                 mut isometries,
                 blasts,
                 mut multiple_lazers,
-                mut shotguns,
+                mut hardpoints,
                 mut lifetimes,
                 asteroid_markers,
                 character_markers,
@@ -107,6 +127,26 @@ impl<'a> System<'a> for GamePlaySystem {
             asteroids_channel,
             lazy_update,
             mut upgrade_stats,
+            mut status_effects,
+            resistances,
+            (
+                asteroid_kinds,
+                asteroids_description,
+                physics,
+                world,
+                mut run_upgrades,
+                mut run_rngs,
+                game_mode,
+                mut run_timer,
+                mut players,
+                mut coop,
+                director,
+                drone_collectables,
+                mut drone_bays,
+                mut dashes,
+                mut destroyed_channel,
+                drones,
+            ),
         ) = data;
         let dims = viewport.dimensions();
         let (w, h) = (dims.0 as f32, dims.1 as f32);
@@ -114,48 +154,103 @@ impl<'a> System<'a> for GamePlaySystem {
         for flash in (&mut flashes).join() {
             flash.0 /= 1.2f32;
         }
-        if let Some((shield, life, ship_stats, _character)) =
-            (&mut shields, &mut lifes, &ships_stats, &character_markers)
-                .join()
-                .next()
+        for (entity, shield, life, ship_stats, _character) in (
+            &entities,
+            &mut shields,
+            &mut lifes,
+            &ships_stats,
+            &character_markers,
+        )
+            .join()
         {
-            shield.0 =
-                (shield.0 + ship_stats.shield_regen).min(ship_stats.max_shield);
+            // downed players wait for revive
+            if coop.is_downed(entity) {
+                continue;
+            }
+            let ship_stats =
+                effective_stats(ship_stats, status_effects.get(entity));
+            // overcharged shield isn't cut down by regeneration
+            if shield.0 < ship_stats.max_shield {
+                shield.0 = (shield.0 + ship_stats.shield_regen)
+                    .min(ship_stats.max_shield);
+            }
             life.0 =
                 (life.0 + ship_stats.health_regen).min(ship_stats.max_health);
-        } else {
+        }
+        // all characters get team abilities, only standing ones collect
+        let all_characters: Vec<Entity> = (&entities, &character_markers)
+            .join()
+            .map(|(entity, _)| entity)
+            .collect();
+        let characters: Vec<(Entity, Isometry)> =
+            (&entities, &isometries, &character_markers)
+                .join()
+                .filter(|(entity, _, _)| !coop.is_downed(*entity))
+                .map(|(entity, iso, _)| (entity, iso.clone()))
+                .collect();
+        if characters.len() == 0 {
             return;
-        };
-        if progress.experience >= progress.current_max_experience() {
-            progress.level_up();
-            let mut rng = thread_rng();
-            let up_id = rng.gen_range(0, avaliable_upgrades.len());
-            let mut second_id = rng.gen_range(0, avaliable_upgrades.len());
-            while second_id == up_id {
-                second_id = rng.gen_range(0, avaliable_upgrades.len());
+        }
+        let elapsed = run_timer.elapsed();
+        let score_multiplier = game_mode.score_multiplier(elapsed);
+        for player in (&mut players).join() {
+            if player.progress.experience
+                < player.progress.current_max_experience()
+            {
+                continue;
+            }
+            player.progress.level_up();
+            // upgrades and rerolls are shared by the team
+            progress.level += 1;
+            if progress.level % UPGRADE_REROLL_LEVELS == 0 {
+                run_upgrades.rerolls += 1;
+            }
+            let offer = offer_upgrades(
+                &avaliable_upgrades,
+                &run_upgrades.picked,
+                UPGRADE_CARDS_NUM,
+                &mut run_rngs.upgrades,
+            );
+            // nothing to offer when the whole tree is taken
+            if offer.len() > 0 {
+                spawned_upgrades.push(offer);
             }
-            spawned_upgrades.push([up_id, second_id]);
             // *app_state = AppState::Play(PlayState::Upgrade);
         }
-        let (char_entity, char_isometry, _char) =
-            (&entities, &isometries, &character_markers)
-                .join()
-                .next()
-                .unwrap();
-        let char_isometry = char_isometry.clone(); // to avoid borrow
-        let pos3d = char_isometry.0.translation.vector;
-        let character_position = Point2::new(pos3d.x, pos3d.y);
+        let character_position = players_center(
+            characters.iter().map(|(entity, iso)| (*entity, iso)),
+            &coop,
+        )
+        .unwrap();
+        for dash in (&mut dashes).join() {
+            dash.update();
+        }
         {
-            // player trace
+            // players trace
             let mut transparent_basic = preloaded_images.glow;
             transparent_basic.transparency = 0.1;
-            let trace = entities.create();
-            lazy_update
-                .insert(trace, Size(sizes.get(char_entity).unwrap().0 * 1.3)); // hack
-            lazy_update.insert(trace, transparent_basic);
-            lazy_update.insert(trace, char_isometry);
-            lazy_update
-                .insert(trace, Lifetime::new(Duration::from_millis(300)));
+            for (char_entity, char_isometry) in characters.iter() {
+                let trace = entities.create();
+                let size = sizes.get(*char_entity).unwrap().0 * 1.3; // hack
+                let dashing = dashes
+                    .get(*char_entity)
+                    .map_or(false, |dash| dash.is_active());
+                // dash leaves bright long living afterimages
+                let (trace_image, trace_lifetime) = if dashing {
+                    let mut dash_trace = transparent_basic;
+                    dash_trace.transparency = DASH_TRACE_TRANSPARENCY;
+                    (dash_trace, DASH_TRACE_LIFETIME_MS)
+                } else {
+                    (transparent_basic, 300)
+                };
+                lazy_update.insert(trace, Size(size));
+                lazy_update.insert(trace, trace_image);
+                lazy_update.insert(trace, char_isometry.clone());
+                lazy_update.insert(
+                    trace,
+                    Lifetime::new(Duration::from_millis(trace_lifetime)),
+                );
+            }
             for (entity, iso, projectile, bullet_image, size) in
                 (&entities, &isometries, &projectiles, &atlas_images, &sizes)
                     .join()
@@ -181,26 +276,36 @@ impl<'a> System<'a> for GamePlaySystem {
                     entities.delete(entity).unwrap();
                 }
                 if side_bullet_ability.get(entity).is_some() {
-                    if let Some(gun) = shotguns.get_mut(char_entity) {
-                        // it's hack to avoid overflow
-                        // posibble if we forgot to delete upgrade from previous game
-                        if gun.side_projectiles_number > 0 {
-                            gun.side_projectiles_number -= 1;
+                    for char_entity in all_characters.iter() {
+                        if let Some(mounts) = hardpoints.get_mut(*char_entity) {
+                            for gun in mounts.shotguns_mut() {
+                                // it's hack to avoid overflow
+                                // posibble if we forgot to delete upgrade from previous game
+                                if gun.side_projectiles_number > 0 {
+                                    gun.side_projectiles_number -= 1;
+                                }
+                            }
+                        }
+                        if let Some(multy_lazer) =
+                            multiple_lazers.get_mut(*char_entity)
+                        {
+                            multy_lazer.minus_side_lazers();
                         }
-                    }
-                    if let Some(multy_lazer) =
-                        multiple_lazers.get_mut(char_entity)
-                    {
-                        multy_lazer.minus_side_lazers();
                     }
                 }
                 if let Some(reflect_ability) =
                     reflect_bullet_ability.get(entity)
                 {
                     if reflect_bullet_ability.count() == 1 {
-                        if let Some(gun) = shotguns.get_mut(char_entity) {
-                            if gun.reflection.is_some() {
-                                gun.reflection = None
+                        for char_entity in all_characters.iter() {
+                            if let Some(mounts) =
+                                hardpoints.get_mut(*char_entity)
+                            {
+                                for gun in mounts.shotguns_mut() {
+                                    if gun.reflection.is_some() {
+                                        gun.reflection = None
+                                    }
+                                }
                             }
                         }
                         // if let Some(ref mut reflection) = gun.reflection {
@@ -209,12 +314,11 @@ impl<'a> System<'a> for GamePlaySystem {
                     }
                 }
                 if let Some(blast) = blasts.get(entity) {
-                    let owner =
-                        if let Some(projectile) = projectiles.get(entity) {
-                            projectile.owner
-                        } else {
-                            entity
-                        };
+                    // blasts without owner are world hazards,
+                    // e.g. volatile asteroids
+                    let owner = projectiles
+                        .get(entity)
+                        .map(|projectile| projectile.owner);
                     let position =
                         isometries.get(entity).unwrap().0.translation.vector;
                     blast_explode(
@@ -233,12 +337,32 @@ impl<'a> System<'a> for GamePlaySystem {
                         (&entities, &mut lifes, &isometries).join()
                     {
                         let position = isometry.0.translation.vector;
-                        let is_character = entity == char_entity;
+                        let is_character =
+                            character_markers.get(entity).is_some();
+                        let is_drone = drones.get(entity).is_some();
+                        // drones fight on the players side
+                        let by_player = owner.map_or(false, |owner| {
+                            character_markers.get(owner).is_some()
+                                || drones.get(owner).is_some()
+                        });
                         let is_asteroid =
                             asteroid_markers.get(entity).is_some();
-                        let affected = is_character && owner != char_entity
-                            || entity != char_entity
-                                && (owner == char_entity || is_asteroid);
+                        let invulnerable = dashes
+                            .get(entity)
+                            .map_or(false, |dash| dash.invulnerable());
+                        let protected = coop.is_downed(entity) || invulnerable;
+                        let affected = if owner.is_none() {
+                            // hazards hit players, enemies and asteroids
+                            !protected
+                        } else {
+                            // no friendly fire between players
+                            (is_character || is_drone)
+                                && !by_player
+                                && !protected
+                                || !is_character
+                                    && !is_drone
+                                    && (by_player || is_asteroid)
+                        };
                         if affected
                             && (blast_position - position).norm()
                                 < blast.blast_radius
@@ -246,10 +370,24 @@ impl<'a> System<'a> for GamePlaySystem {
                             if is_character {
                                 global_params.damaged(DAMAGED_RED);
                             }
+                            let damage = affected_damage(
+                                status_effects.get(entity),
+                                blast.blast_damage,
+                            );
+                            if let (Some(effect), Some(effects)) =
+                                (blast.effect, status_effects.get_mut(entity))
+                            {
+                                effects.apply(
+                                    effect,
+                                    TRACKER.lock().unwrap().now(),
+                                );
+                            }
                             if process_damage(
                                 life,
                                 shields.get_mut(entity),
-                                blast.blast_damage,
+                                damage,
+                                blast.damage_type,
+                                resistances.get(entity),
                             ) {
                                 if is_asteroid {
                                     let asteroid = entity;
@@ -266,26 +404,66 @@ impl<'a> System<'a> for GamePlaySystem {
                                         isometries.get(asteroid).unwrap().0;
                                     let poly =
                                         polygons.get(asteroid).unwrap().clone();
+                                    let (kind, material) = asteroid_kind(
+                                        asteroid,
+                                        &asteroid_kinds,
+                                        &asteroids_description,
+                                    );
+                                    let velocity = body_velocity(
+                                        physics.get(asteroid),
+                                        &world,
+                                    );
+                                    let impact = Impact::new(
+                                        Point2::new(
+                                            blast_position.x,
+                                            blast_position.y,
+                                        ),
+                                        Vector2::new(
+                                            position.x - blast_position.x,
+                                            position.y - blast_position.y,
+                                        ),
+                                        damage,
+                                    );
                                     let channel_arc =
                                         (*asteroids_channel).clone();
+                                    let drop_rate = director.drop_rate;
+                                    let rng = StdRng::seed_from_u64(
+                                        run_rngs.drops.gen(),
+                                    );
                                     thread::spawn(move || {
                                         spawn_asteroids(
                                             iso,
+                                            velocity,
                                             poly,
+                                            kind,
+                                            material,
                                             channel_arc,
-                                            None,
+                                            Some(impact),
+                                            drop_rate,
+                                            rng,
                                         );
                                     });
                                 }
-                                if is_character {
-                                    to_menu(
+                                if is_drone {
+                                    // drone system explodes it and starts
+                                    // the respawn timer
+                                } else if !is_character
+                                    || !knock_out(
                                         &mut app_state,
-                                        &mut progress,
-                                        &mut macro_game.score_table,
-                                    );
+                                        &mut coop,
+                                        entity,
+                                    )
+                                {
+                                    if ships.get(entity).is_some() {
+                                        destroy_ship(
+                                            entity,
+                                            &entities,
+                                            &mut destroyed_channel,
+                                        );
+                                    } else {
+                                        entities.delete(entity).unwrap();
+                                    }
                                 }
-                                // delete character
-                                entities.delete(entity).unwrap();
                                 // dbg!("dead");
                             }
                         }
@@ -294,11 +472,22 @@ impl<'a> System<'a> for GamePlaySystem {
                 entities.delete(entity).unwrap()
             }
         }
+        let magnet_radius = MAGNETO_RADIUS + upgrade_stats.magnet_bonus;
         for (entity, iso, _collectable) in
             (&entities, &mut isometries, &collectables).join()
         {
             let collectable_position = iso.0.translation.vector;
-            if (pos3d - collectable_position).norm() < MAGNETO_RADIUS {
+            // the nearest player attracts it
+            let (char_entity, pos3d) = characters
+                .iter()
+                .map(|(entity, iso)| (*entity, iso.0.translation.vector))
+                .min_by(|a, b| {
+                    let a = (a.1 - collectable_position).norm();
+                    let b = (b.1 - collectable_position).norm();
+                    a.partial_cmp(&b).unwrap()
+                })
+                .unwrap();
+            if (pos3d - collectable_position).norm() < magnet_radius {
                 let vel = 0.3 * (pos3d - collectable_position).normalize();
                 iso.0.translation.vector += vel;
             }
@@ -333,7 +522,9 @@ impl<'a> System<'a> for GamePlaySystem {
                         ),
                     ));
                     progress.add_coins(coins_add);
-                    progress.add_score(coins_add);
+                    progress.add_score(
+                        (coins_add as f32 * score_multiplier) as usize,
+                    );
                     macro_game.coins += coins_add;
                 }
                 if let Some(exp) = exps.get(entity) {
@@ -345,7 +536,9 @@ impl<'a> System<'a> for GamePlaySystem {
                         ),
                     ));
                     let add_exp = upgrade_stats.exp_mult * exp.0;
-                    progress.add_score(3 * add_exp);
+                    progress.add_score(
+                        (3.0 * add_exp as f32 * score_multiplier) as usize,
+                    );
                     add_text(
                         &entities,
                         TextComponent {
@@ -359,7 +552,9 @@ impl<'a> System<'a> for GamePlaySystem {
                         ),
                         Some(Lifetime::new(Duration::from_secs(1))),
                     );
-                    progress.add_exp(add_exp);
+                    if let Some(player) = players.get_mut(char_entity) {
+                        player.progress.add_exp(add_exp);
+                    }
                 }
                 if let Some(health) = healths.get(entity) {
                     lifes.get_mut(char_entity).unwrap().0 += health.0;
@@ -379,15 +574,40 @@ impl<'a> System<'a> for GamePlaySystem {
                         ),
                         Some(Lifetime::new(Duration::from_secs(1))),
                     );
-                    if let Some(gun) = shotguns.get_mut(char_entity) {
-                        gun.side_projectiles_number += 1;
-                    }
-                    if let Some(multy_lazer) =
-                        multiple_lazers.get_mut(char_entity)
-                    {
-                        multy_lazer.plus_side_lazers();
+                    for char_entity in all_characters.iter() {
+                        if let Some(mounts) = hardpoints.get_mut(*char_entity) {
+                            for gun in mounts.shotguns_mut() {
+                                gun.side_projectiles_number += 1;
+                            }
+                        }
+                        if let Some(multy_lazer) =
+                            multiple_lazers.get_mut(*char_entity)
+                        {
+                            multy_lazer.plus_side_lazers();
+                        }
                     }
                 }
+                if drone_collectables.get(entity).is_some()
+                    && !description.drones.is_empty()
+                {
+                    let kind = description.drones
+                        [run_rngs.drops.gen_range(0, description.drones.len())]
+                    .clone();
+                    add_text(
+                        &entities,
+                        TextComponent {
+                            text: kind.name.clone(),
+                            color: (1.0, 1.0, 1.0, 1.0),
+                        },
+                        &lazy_update,
+                        Point2::new(
+                            collectable_position.x,
+                            collectable_position.y,
+                        ),
+                        Some(Lifetime::new(Duration::from_secs(1))),
+                    );
+                    add_drone(&mut drone_bays, char_entity, kind);
+                }
                 if double_coins_collectable.get(entity).is_some() {
                     add_text(
                         &entities,
@@ -440,15 +660,37 @@ impl<'a> System<'a> for GamePlaySystem {
                 entities.delete(entity).unwrap();
             }
         }
-        let cnt = ships.count();
+        if let Some(time_limit) = game_mode.time_limit() {
+            if elapsed >= time_limit {
+                run_timer.time_up = true;
+                *app_state = AppState::DeadScreen;
+                return;
+            }
+        }
+        if !game_mode.spawns_enemies() {
+            return;
+        }
+        // every player ship has the marker too, so only enemies are counted
+        let cnt = (&ships, !&character_markers).join().count();
         let wave = &waves.0[current_wave.id];
-        let (add_cnt, const_spawn) = if cnt == 1 {
+        let (add_cnt, const_spawn) = if cnt == 0 {
             current_wave.iteration += 1;
-            (wave.ships_number - cnt + 1, true)
+            let add_cnt = wave.ships_number;
+            let difficulty = game_mode.difficulty(elapsed);
+            let add_cnt = add_cnt as f32 * difficulty * director.spawn_count;
+            (add_cnt as usize, true)
         } else {
             (0, false)
         };
-        if current_wave.iteration > wave.iterations {
+        let wave_finished = match *game_mode {
+            // waves follow the clock instead of kills
+            GameMode::Survival => {
+                let id = (elapsed.as_secs() / SURVIVAL_WAVE_SECS) as usize;
+                current_wave.id < id.min(waves.0.len() - 1)
+            }
+            _ => current_wave.iteration > wave.iterations,
+        };
+        if wave_finished {
             current_wave.iteration = 0;
             current_wave.id = (waves.0.len() - 1).min(current_wave.id + 1);
             add_screen_text(
@@ -462,19 +704,33 @@ impl<'a> System<'a> for GamePlaySystem {
                 Some(Lifetime::new(Duration::from_secs(1))),
             );
         }
-        let mut rng = thread_rng();
-        fn ships2insert(spawn_pos: Point2, enemy: EnemyKind) -> InsertEvent {
+        let rng = &mut run_rngs.waves;
+        fn ships2insert(
+            spawn_pos: Point2,
+            enemy: EnemyKind,
+            entity: Option<Entity>,
+            squad: Option<SquadMember>,
+            stats_multiplier: f32,
+        ) -> InsertEvent {
             InsertEvent::Ship {
                 iso: Point3::new(spawn_pos.x, spawn_pos.y, 0f32),
                 light_shape: Geometry::Circle { radius: 1f32 },
                 spin: 0f32,
                 kind: enemy.ai_kind,
                 gun_kind: enemy.gun_kind,
-                ship_stats: enemy.ship_stats,
+                ship_stats: enemy.ship_stats.scaled(stats_multiplier),
                 size: enemy.size,
                 image: enemy.image,
                 snake: enemy.snake,
                 rift: enemy.rift,
+                steering: enemy.steering,
+                skill: enemy.skill,
+                perception: enemy.perception,
+                resistances: enemy.resistances,
+                outline: enemy.outline,
+                hardpoints: enemy.hardpoints,
+                entity: entity,
+                squad: squad,
             }
         };
         for _ in 0..add_cnt {
@@ -483,37 +739,89 @@ impl<'a> System<'a> for GamePlaySystem {
                     character_position,
                     PLAYER_AREA,
                     ACTIVE_AREA,
+                    rng,
                 );
                 // TODO move from loop
                 let ships = &description.enemies;
                 let ship_id = wave
                     .distribution
-                    .choose_weighted(&mut rng, |item| item.1)
+                    .choose_weighted(rng, |item| item.1)
                     .unwrap()
                     .0;
                 insert_channel.single_write(ships2insert(
                     spawn_pos,
                     ships[ship_id].clone(),
+                    None,
+                    None,
+                    director.enemy_stats,
                 ));
             }
         }
         if const_spawn {
             for kind in wave.const_distribution.iter() {
                 // dbg!(kind);
-                for _ in 0..kind.1 {
+                let num = (kind.1 as f32 * director.spawn_count).round();
+                for _ in 0..num as usize {
                     let spawn_pos = spawn_position(
                         character_position,
                         PLAYER_AREA,
                         ACTIVE_AREA,
+                        rng,
                     );
                     let ships = &description.enemies;
                     let ship_id = kind.0;
                     insert_channel.single_write(ships2insert(
                         spawn_pos,
                         ships[ship_id].clone(),
+                        None,
+                        None,
+                        director.enemy_stats,
                     ));
                 }
             }
+            for squad in wave.squads.iter() {
+                let spawn_pos = spawn_position(
+                    character_position,
+                    PLAYER_AREA,
+                    ACTIVE_AREA,
+                    rng,
+                );
+                let ships = &description.enemies;
+                // followers need leader entity, so create it right here
+                let leader = entities.create();
+                insert_channel.single_write(ships2insert(
+                    spawn_pos,
+                    ships[squad.leader].clone(),
+                    Some(leader),
+                    None,
+                    director.enemy_stats,
+                ));
+                let followers_num: usize =
+                    squad.followers.iter().map(|follower| follower.1).sum();
+                let mut slot_id = 0;
+                for (ship_id, num) in squad.followers.iter() {
+                    for _ in 0..*num {
+                        let slot = squad.formation.slot(
+                            slot_id,
+                            followers_num,
+                            squad.spacing,
+                        );
+                        let member = SquadMember {
+                            leader: leader,
+                            slot: slot,
+                            engaged: false,
+                        };
+                        insert_channel.single_write(ships2insert(
+                            spawn_pos + slot,
+                            ships[*ship_id].clone(),
+                            None,
+                            Some(member),
+                            director.enemy_stats,
+                        ));
+                        slot_id += 1;
+                    }
+                }
+            }
         }
         info!("asteroids: gameplay ended");
     }
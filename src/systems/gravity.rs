@@ -0,0 +1,156 @@
+use super::*;
+use log::info;
+
+/// Spawns gravity wells around the character and pulls physics bodies
+/// towards them. Whatever crosses an event horizon is destroyed.
+#[derive(Default)]
+pub struct GravitySystem;
+
+impl<'a> System<'a> for GravitySystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, GravityWell>,
+        ReadStorage<'a, PhysicsComponent>,
+        ReadStorage<'a, CharacterMarker>,
+        ReadStorage<'a, ShipMarker>,
+        Write<'a, World<f32>>,
+        Read<'a, GravityWellKinds>,
+        Write<'a, EventChannel<InsertEvent>>,
+        Write<'a, EventChannel<Sound>>,
+        ReadExpect<'a, PreloadedSounds>,
+        Write<'a, AppState>,
+        Write<'a, Coop>,
+        Write<'a, EventChannel<ShipDestroyed>>,
+        Write<'a, RunRngs>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: gravity started");
+        let (
+            entities,
+            isometries,
+            gravity_wells,
+            physics,
+            character_markers,
+            ships,
+            mut world,
+            gravity_well_kinds,
+            mut insert_channel,
+            mut sounds_channel,
+            preloaded_sounds,
+            mut app_state,
+            mut coop,
+            mut destroyed_channel,
+            mut run_rngs,
+        ) = data;
+        let character_position = if let Some(center) = players_center(
+            (&entities, &isometries, &character_markers)
+                .join()
+                .map(|(entity, iso, _)| (entity, iso)),
+            &coop,
+        ) {
+            center
+        } else {
+            return;
+        };
+        let rng = &mut run_rngs.gravity_wells;
+        let wells_cnt = gravity_wells.count();
+        if wells_cnt < GRAVITY_WELLS_MAX_NUMBER
+            && gravity_well_kinds.0.len() > 0
+            && rng.gen_range(0.0, 1.0) < GRAVITY_WELL_SPAWN_CHANCE
+        {
+            let kinds_weights: Vec<(usize, f32)> = gravity_well_kinds
+                .0
+                .iter()
+                .enumerate()
+                .map(|(id, kind)| (id, kind.spawn_weight))
+                .collect();
+            let kind =
+                kinds_weights.choose_weighted(rng, |item| item.1).unwrap().0;
+            insert_channel.single_write(InsertEvent::GravityWell {
+                position: spawn_position(
+                    character_position,
+                    PLAYER_AREA,
+                    ACTIVE_AREA,
+                    rng,
+                ),
+                kind: kind,
+            });
+        }
+        let time_scaler =
+            normalize_60frame(TRACKER.lock().unwrap().last_delta());
+        let mut swallowed = vec![];
+        for (well_entity, well_iso, well) in
+            (&entities, &isometries, &gravity_wells).join()
+        {
+            let well_vec = well_iso.0.translation.vector;
+            let well_position = Point2::new(well_vec.x, well_vec.y);
+            if !is_active(character_position, well_position, ACTIVE_AREA) {
+                entities.delete(well_entity).unwrap();
+                continue;
+            }
+            for (entity, iso, physics_component) in
+                (&entities, &isometries, &physics).join()
+            {
+                let pos = iso.0.translation.vector;
+                let to_well = well_position.coords - Vector2::new(pos.x, pos.y);
+                let distance = to_well.norm();
+                if distance > well.radius {
+                    continue;
+                }
+                if let Some(event_horizon) = well.event_horizon {
+                    if distance < event_horizon {
+                        swallowed.push(entity);
+                        continue;
+                    }
+                }
+                let direction = to_well / distance.max(EPS);
+                let distance = distance.max(GRAVITY_MIN_DISTANCE);
+                let mut acceleration =
+                    well.strength / (distance * distance) * direction;
+                let body = world
+                    .rigid_body_mut(physics_component.body_handle)
+                    .unwrap();
+                let mut velocity = *body.velocity();
+                if character_markers.get(entity).is_some()
+                    && velocity.linear.norm() > EPS
+                {
+                    // boost along the orbit, the faster we pass the well
+                    // the stronger it throws us
+                    let heading = velocity.linear.normalize();
+                    let tangential =
+                        (heading - heading.dot(&direction) * direction).norm();
+                    acceleration +=
+                        well.slingshot * tangential * heading / distance;
+                }
+                velocity.linear += time_scaler * acceleration;
+                body.set_velocity(velocity);
+            }
+        }
+        // overlapping wells can swallow the same body
+        swallowed.sort();
+        swallowed.dedup();
+        for entity in swallowed.into_iter() {
+            if ships.get(entity).is_some() {
+                let pos = isometries.get(entity).unwrap().0.translation.vector;
+                ship_explode(
+                    Point2::new(pos.x, pos.y),
+                    &mut insert_channel,
+                    &mut sounds_channel,
+                    &preloaded_sounds,
+                );
+                // nothing left to revive
+                if character_markers.get(entity).is_some() {
+                    coop.remove(entity);
+                    if coop.standing() == 0 {
+                        *app_state = AppState::DeadScreen;
+                    }
+                }
+                destroy_ship(entity, &entities, &mut destroyed_channel);
+            } else {
+                entities.delete(entity).unwrap();
+            }
+        }
+    }
+}
@@ -1,6 +1,5 @@
 use super::*;
 use crate::gui::*;
-use physics::*;
 
 #[derive(Default)]
 pub struct GUISystem;
@@ -12,16 +11,11 @@ impl<'a> System<'a> for GUISystem {
             ReadStorage<'a, CharacterMarker>,
             ReadStorage<'a, Lifes>,
             ReadStorage<'a, Shield>,
-            ReadStorage<'a, SideBulletAbility>,
-            ReadStorage<'a, DoubleCoinsAbility>,
-            ReadStorage<'a, DoubleExpAbility>,
+            ReadStorage<'a, AbilityBar>,
             WriteStorage<'a, ShipStats>,
-            WriteStorage<'a, ShotGun>,
-            WriteStorage<'a, Isometry>,
-            WriteStorage<'a, Velocity>,
-            WriteStorage<'a, Spin>,
-            ReadStorage<'a, PhysicsComponent>,
             ReadExpect<'a, red::Viewport>,
+            ReadStorage<'a, Dash>,
+            Read<'a, Bindings>,
         ),
         ReadExpect<'a, DevInfo>,
         Write<'a, UI>,
@@ -31,12 +25,10 @@ impl<'a> System<'a> for GUISystem {
         Read<'a, CurrentWave>,
         ReadExpect<'a, Pallete>,
         ReadExpect<'a, MacroGame>,
-        ReadExpect<'a, PreloadedSounds>,
-        Write<'a, EventChannel<Sound>>,
-        WriteExpect<'a, Touches>,
-        Write<'a, World<f32>>,
-        Write<'a, EventChannel<InsertEvent>>,
-        Write<'a, AppState>,
+        Read<'a, GameMode>,
+        Read<'a, RunTimer>,
+        ReadStorage<'a, Player>,
+        Read<'a, Coop>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -46,16 +38,11 @@ impl<'a> System<'a> for GUISystem {
                 character_markers,
                 lifes,
                 shields,
-                side_bullet_abilities,
-                double_coins_abilities,
-                double_exp_abilities,
-                mut ships_stats,
-                mut shotguns,
-                isometries,
-                mut velocities,
-                mut spins,
-                physics,
+                ability_bars,
+                ships_stats,
                 viewport,
+                dashes,
+                bindings,
             ),
             // preloaded_particles,
             dev_info,
@@ -66,124 +53,16 @@ impl<'a> System<'a> for GUISystem {
             current_wave,
             pallete,
             macro_game,
-            preloaded_sounds,
-            mut sounds_channel,
-            touches,
-            mut world,
-            mut insert_channel,
-            mut app_state,
+            game_mode,
+            run_timer,
+            players,
+            coop,
         ) = data;
         let dims = viewport.dimensions();
         let (w, h) = (dims.0 as f32, dims.1 as f32);
         let d = (w * w + h * h).sqrt();
-        //contorls
-        #[cfg(any(target_os = "android"))]
-        let stick_size = w / 80.0;
-        #[cfg(any(target_os = "android"))]
-        let ctrl_size = stick_size * 10.0;
-        #[cfg(any(target_os = "android"))]
-        let move_controller = VecController::new(
-            Point2::new(ctrl_size, h - ctrl_size),
-            ctrl_size,
-            stick_size,
-            preloaded_images.circle,
-        );
-        #[cfg(any(target_os = "android"))]
-        let attack_controller = VecController::new(
-            Point2::new(w - ctrl_size, h - ctrl_size),
-            ctrl_size,
-            stick_size,
-            preloaded_images.circle,
-        );
-        let (character, ship_stats, _) = if let Some(value) =
-            (&entities, &mut ships_stats, &character_markers)
-                .join()
-                .next()
-        {
-            value
-        } else {
+        if (&entities, &character_markers).join().next().is_none() {
             return;
-        };
-        // move controller
-        #[cfg(target_os = "android")]
-        {
-            let mut controlling = touches.iter().any(|x| x.is_some());
-            if let Some(dir) = move_controller.set(0, &mut ui, &touches) {
-                let (character, _) =
-                    (&entities, &character_markers).join().next().unwrap();
-                let (_character_isometry, mut character_velocity) = {
-                    let character_body = world
-                        .rigid_body(physics.get(character).unwrap().body_handle)
-                        .unwrap();
-                    (*character_body.position(), *character_body.velocity())
-                };
-                let time_scaler =
-                    normalize_60frame(TRACKER.lock().unwrap().last_delta());
-                let mut thrust =
-                    ship_stats.thrust_force * Vector3::new(dir.x, dir.y, 0.0);
-                thrust = thrust_calculation(
-                    ship_stats.maneuverability.unwrap(),
-                    thrust,
-                    *character_velocity.as_vector(),
-                );
-                *character_velocity.as_vector_mut() += thrust;
-                let character_body = world
-                    .rigid_body_mut(physics.get(character).unwrap().body_handle)
-                    .unwrap();
-                character_body.set_velocity(character_velocity);
-            }
-
-            if let Some(dir) = attack_controller.set(1, &mut ui, &touches) {
-                for (iso, _vel, spin, _char_marker) in (
-                    &isometries,
-                    &mut velocities,
-                    &mut spins,
-                    &character_markers,
-                )
-                    .join()
-                {
-                    let player_torque = DT
-                        * calculate_player_ship_spin_for_aim(
-                            dir,
-                            iso.rotation(),
-                            spin.0,
-                        );
-                    spin.0 += player_torque.max(-MAX_TORQUE).min(MAX_TORQUE);
-                }
-
-                let dir = dir.normalize();
-                let shotgun = shotguns.get_mut(character);
-                if let Some(shotgun) = shotgun {
-                    if shotgun.shoot() {
-                        let isometry = *isometries.get(character).unwrap();
-                        let position = isometry.0.translation.vector;
-                        // let direction = isometry.0 * Vector3::new(0f32, -1f32, 0f32);
-                        let velocity_rel = shotgun.bullet_speed * dir;
-                        let char_velocity = velocities.get(character).unwrap();
-                        let projectile_velocity = Velocity::new(
-                            char_velocity.0.x + velocity_rel.x,
-                            char_velocity.0.y + velocity_rel.y,
-                        );
-                        sounds_channel.single_write(Sound(
-                            preloaded_sounds.shot,
-                            Point2::new(position.x, position.y),
-                        ));
-                        let rotation = Rotation2::rotation_between(
-                            &Vector2::new(0.0, 1.0),
-                            &dir,
-                        );
-                        let bullets = shotgun.spawn_bullets(
-                            EntityType::Player,
-                            isometries.get(character).unwrap().0,
-                            shotgun.bullet_speed,
-                            shotgun.bullets_damage,
-                            velocities.get(character).unwrap().0,
-                            character,
-                        );
-                        insert_channel.iter_write(bullets.into_iter());
-                    }
-                }
-            }
         }
         // FPS
         ui.primitives.push(Primitive {
@@ -217,118 +96,192 @@ impl<'a> System<'a> for GUISystem {
             with_projection: false,
         });
 
+        if game_mode.spawns_enemies() {
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Text(Text {
+                    position: Point2::new(w - w / 7.0, h / 7.0 + h / 20.0),
+                    text: format!("Wave: {}", current_wave.id).to_string(),
+                    color: (1.0, 1.0, 1.0, 1.0),
+                    font_size: 1.0,
+                }),
+                with_projection: false,
+            });
+        }
+
+        // mode timer, time attack counts down
+        let elapsed = run_timer.elapsed();
+        let timer = match game_mode.time_limit() {
+            Some(time_limit) => {
+                time_limit.checked_sub(elapsed).unwrap_or_default()
+            }
+            None => elapsed,
+        };
         ui.primitives.push(Primitive {
             kind: PrimitiveKind::Text(Text {
-                position: Point2::new(w - w / 7.0, h / 7.0 + h / 20.0),
-                text: format!("Wave: {}", current_wave.id).to_string(),
+                position: Point2::new(w / 7.0, h / 7.0 + h / 20.0),
+                text: format!(
+                    "{}: {}:{:02}",
+                    game_mode.name(),
+                    timer.as_secs() / 60,
+                    timer.as_secs() % 60
+                ),
                 color: (1.0, 1.0, 1.0, 1.0),
                 font_size: 1.0,
             }),
             with_projection: false,
         });
+        let score_multiplier = game_mode.score_multiplier(elapsed);
+        if score_multiplier > 1.0 {
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Text(Text {
+                    position: Point2::new(w / 7.0, 2.0 * h / 7.0 + h / 20.0),
+                    text: format!("Score x{:.1}", score_multiplier),
+                    color: (1.0, 1.0, 0.7, 1.0),
+                    font_size: 1.0,
+                }),
+                with_projection: false,
+            });
+        }
 
-        let side_bullets_cnt = side_bullet_abilities.count();
-        let double_coins_cnt = double_coins_abilities.count();
-        let double_exp_cnt = double_exp_abilities.count();
+        // active abilities: icon, key and charges,
+        // bar under the icon shows the next charge
         let icon_size = w / 20.0;
-        struct Ability {
-            pub icon: AtlasImage,
-            pub text: String,
-        };
-        let mut abilities = vec![];
-
-        if double_coins_cnt > 0 {
-            let ability = Ability {
-                icon: preloaded_images.double_coin,
-                text: format!("x{}", double_coins_cnt).to_string(),
-            };
-            abilities.push(ability);
-        }
-        if double_exp_cnt > 0 {
-            let ability = Ability {
-                icon: preloaded_images.double_exp,
-                text: format!("x{}", double_exp_cnt).to_string(),
-            };
-            abilities.push(ability);
+        // ability charges run on unscaled time
+        let now = TRACKER.lock().unwrap().unscaled_now();
+        for (bar, player) in (&ability_bars, &players).join() {
+            let x_pos = w - w / 7.0 - player.id as f32 * 3.0 * icon_size;
+            for (i, ability) in bar.slots.iter().enumerate() {
+                let y_pos = (i as f32 + 1.0) * h / 7.0 + h / 20.0;
+                let icon = match ability.kind {
+                    ActiveAbilityKind::Bomb => preloaded_images.bomb_ability,
+                    ActiveAbilityKind::ShieldBurst => {
+                        preloaded_images.shield_burst_ability
+                    }
+                    ActiveAbilityKind::TimeSlow => {
+                        preloaded_images.time_slow_ability
+                    }
+                };
+                let key = if player.id == 0 {
+                    bindings
+                        .get(ABILITY_ACTIONS[i])
+                        .first()
+                        .map_or(String::new(), |binding| binding.name())
+                } else {
+                    SECOND_PLAYER_ABILITY_KEYS[i].name()
+                };
+                let color = if ability.charges > 0 {
+                    (1.0, 1.0, 1.0, 1.0)
+                } else {
+                    (0.5, 0.5, 0.5, 1.0)
+                };
+                ui.primitives.push(Primitive {
+                    kind: PrimitiveKind::Picture(Picture {
+                        position: Point2::new(x_pos, y_pos),
+                        width: icon_size,
+                        height: icon_size,
+                        image: icon,
+                    }),
+                    with_projection: false,
+                });
+                ui.primitives.push(Primitive {
+                    kind: PrimitiveKind::Rectangle(Rectangle {
+                        position: Point2::new(x_pos, y_pos + icon_size),
+                        width: ability.recharge(now) * icon_size,
+                        height: icon_size / 10.0,
+                        color: pallete.white_color,
+                    }),
+                    with_projection: false,
+                });
+                ui.primitives.push(Primitive {
+                    kind: PrimitiveKind::Text(Text {
+                        position: Point2::new(
+                            x_pos + 2.0 * icon_size,
+                            y_pos + icon_size / 2.0,
+                        ),
+                        text: format!("{} x{}", key, ability.charges),
+                        color: color,
+                        font_size: 1.0,
+                    }),
+                    with_projection: false,
+                });
+            }
         }
-        if side_bullets_cnt > 0 {
-            let ability = Ability {
-                icon: preloaded_images.side_bullet_ability,
-                text: format!("+{}", side_bullets_cnt).to_string(),
+
+        // in co-op every player gets own half of the screen for bars
+        let bars_x = |player: &Player| {
+            if coop.local_players > 1 {
+                w / 4.0 + player.id as f32 * w / 2.0
+            } else {
+                w / 2.0
+            }
+        };
+        // "UI" things
+        // experience and level bars
+        let experiencebar_w = w / 5.0;
+        let experiencebar_h = h / 100.0;
+        for player in (&players).join() {
+            let player_progress = &player.progress;
+            let experience_position = Point2::new(
+                bars_x(player) - experiencebar_w / 2.0,
+                h - h / 20.0,
+            );
+            let experience_bar = Rectangle {
+                position: experience_position,
+                width: (player_progress.experience as f32
+                    / player_progress.current_max_experience() as f32)
+                    * experiencebar_w,
+                height: experiencebar_h,
+                color: pallete.experience_color.clone(),
             };
-            abilities.push(ability);
-        }
 
-        for (i, ability) in abilities.iter().enumerate() {
-            let x_pos = w - w / 7.0;
-            let y_pos = (i as f32 + 1.0) * h / 7.0 + h / 20.0;
+            let border = d / 200f32;
             ui.primitives.push(Primitive {
                 kind: PrimitiveKind::Picture(Picture {
-                    position: Point2::new(x_pos, y_pos),
-                    width: icon_size,
-                    height: icon_size,
-                    image: ability.icon,
+                    position: experience_position
+                        + Vector2::new(-border / 2.0, -border / 2.0),
+                    width: experiencebar_w + border,
+                    height: experiencebar_h + border,
+                    image: preloaded_images.bar,
                 }),
                 with_projection: false,
             });
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Picture(Picture {
+                    position: experience_position
+                        + Vector2::new(-border / 2.0, -border / 2.0),
+                    width: (player_progress.experience as f32
+                        / player_progress.current_max_experience() as f32)
+                        * experiencebar_w,
+                    height: experiencebar_h,
+                    image: preloaded_images.bar,
+                }),
+                with_projection: false,
+            });
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Rectangle(experience_bar),
+                with_projection: false,
+            });
+        }
+        // downed players and their revive progress
+        for (i, (downed, revive)) in coop.downed.iter().enumerate() {
+            let id = players.get(*downed).map_or(0, |player| player.id);
             ui.primitives.push(Primitive {
                 kind: PrimitiveKind::Text(Text {
                     position: Point2::new(
-                        x_pos + 2.0 * icon_size,
-                        y_pos + icon_size / 2.0,
+                        w / 2.0,
+                        h / 5.0 + i as f32 * h / 20.0,
                     ),
-                    text: ability.text.clone(),
-                    color: (1.0, 1.0, 1.0, 1.0),
+                    text: format!(
+                        "P{} downed {}%",
+                        id + 1,
+                        (revive * 100.0) as usize
+                    ),
+                    color: (1.0, 0.6, 0.6, 1.0),
                     font_size: 1.0,
                 }),
                 with_projection: false,
             });
         }
-
-        let (_character, _) =
-            (&entities, &character_markers).join().next().unwrap();
-        // "UI" things
-        // experience and level bars
-        let experiencebar_w = w / 5.0;
-        let experiencebar_h = h / 100.0;
-        let experience_position =
-            Point2::new(w / 2.0 - experiencebar_w / 2.0, h - h / 20.0);
-        let experience_bar = Rectangle {
-            position: experience_position,
-            width: (progress.experience as f32
-                / progress.current_max_experience() as f32)
-                * experiencebar_w,
-            height: experiencebar_h,
-            color: pallete.experience_color.clone(),
-        };
-
-        let border = d / 200f32;
-        ui.primitives.push(Primitive {
-            kind: PrimitiveKind::Picture(Picture {
-                position: experience_position
-                    + Vector2::new(-border / 2.0, -border / 2.0),
-                width: experiencebar_w + border,
-                height: experiencebar_h + border,
-                image: preloaded_images.bar,
-            }),
-            with_projection: false,
-        });
-        ui.primitives.push(Primitive {
-            kind: PrimitiveKind::Picture(Picture {
-                position: experience_position
-                    + Vector2::new(-border / 2.0, -border / 2.0),
-                width: (progress.experience as f32
-                    / progress.current_max_experience() as f32)
-                    * experiencebar_w,
-                height: experiencebar_h,
-                image: preloaded_images.bar,
-            }),
-            with_projection: false,
-        });
-        ui.primitives.push(Primitive {
-            kind: PrimitiveKind::Rectangle(experience_bar),
-            with_projection: false,
-        });
         if spawned_upgrades.len() > 0 {
             // {
             let (upgrade_bar_w, upgrade_bar_h) = (w / 3f32, h / 10.0);
@@ -348,9 +301,10 @@ impl<'a> System<'a> for GUISystem {
         let (lifebar_w, lifebar_h) = (w / 4f32, h / 50.0);
         let health_y = h / 40.0;
         let shields_y = health_y + h / 13.0;
-        for (life, shield, _character) in
-            (&lifes, &shields, &character_markers).join()
+        for (life, shield, ship_stats, player) in
+            (&lifes, &shields, &ships_stats, &players).join()
         {
+            let center_x = bars_x(player);
             {
                 // upgrade bar
                 let border = d / 200f32;
@@ -359,7 +313,7 @@ impl<'a> System<'a> for GUISystem {
                 ui.primitives.push(Primitive {
                     kind: PrimitiveKind::Picture(Picture {
                         position: Point2::new(
-                            w / 2.0 - health_back_w / 2.0,
+                            center_x - health_back_w / 2.0,
                             health_y - border / 2.0,
                         ),
                         width: health_back_w,
@@ -374,7 +328,7 @@ impl<'a> System<'a> for GUISystem {
                 ui.primitives.push(Primitive {
                     kind: PrimitiveKind::Picture(Picture {
                         position: Point2::new(
-                            w / 2.0 - health_back_w / 2.0,
+                            center_x - health_back_w / 2.0,
                             shields_y - border / 2.0,
                         ),
                         width: health_back_w,
@@ -386,14 +340,14 @@ impl<'a> System<'a> for GUISystem {
             }
 
             let lifes_bar = Rectangle {
-                position: Point2::new(w / 2.0 - lifebar_w / 2.0, health_y),
+                position: Point2::new(center_x - lifebar_w / 2.0, health_y),
                 width: (life.0 as f32 / ship_stats.max_health as f32)
                     * lifebar_w,
                 height: lifebar_h,
                 color: pallete.life_color.clone(),
             };
             let shields_bar = Rectangle {
-                position: Point2::new(w / 2.0 - lifebar_w / 2.0, shields_y),
+                position: Point2::new(center_x - lifebar_w / 2.0, shields_y),
                 width: (shield.0 as f32 / ship_stats.max_shield as f32)
                     * lifebar_w,
                 height: lifebar_h,
@@ -408,5 +362,29 @@ impl<'a> System<'a> for GUISystem {
                 with_projection: false,
             });
         }
+        // dash charges, bar shows the next charge
+        let dash_y = shields_y + h / 13.0;
+        for (dash, player) in (&dashes, &players).join() {
+            let center_x = bars_x(player);
+            let dash_bar = Rectangle {
+                position: Point2::new(center_x - lifebar_w / 2.0, dash_y),
+                width: dash.recharge() * lifebar_w / 2.0,
+                height: lifebar_h / 2.0,
+                color: pallete.dash_color,
+            };
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Rectangle(dash_bar),
+                with_projection: false,
+            });
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Text(Text {
+                    position: Point2::new(center_x + lifebar_w / 4.0, dash_y),
+                    text: format!("Dash {}/{}", dash.charges, dash.max_charges),
+                    color: (1.0, 1.0, 1.0, 1.0),
+                    font_size: 1.0,
+                }),
+                with_projection: false,
+            });
+        }
     }
 }
@@ -0,0 +1,133 @@
+use super::*;
+use log::info;
+use physics::*;
+
+/// Turns turrets to targets and fires guns of hardpoints
+#[derive(Default)]
+pub struct HardpointsSystem;
+
+impl<'a> System<'a> for HardpointsSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, Velocity>,
+        ReadStorage<'a, Size>,
+        ReadStorage<'a, CharacterMarker>,
+        ReadStorage<'a, EnemyMarker>,
+        ReadStorage<'a, StatusEffects>,
+        WriteStorage<'a, Hardpoints>,
+        Read<'a, Coop>,
+        Write<'a, EventChannel<InsertEvent>>,
+        Write<'a, EventChannel<Sound>>,
+        ReadExpect<'a, PreloadedSounds>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: hardpoints started");
+        let (
+            entities,
+            isometries,
+            velocities,
+            sizes,
+            character_markers,
+            enemies,
+            status_effects,
+            mut hardpoints,
+            coop,
+            mut insert_channel,
+            mut sounds_channel,
+            preloaded_sounds,
+        ) = data;
+        let time_scaler =
+            normalize_60frame(TRACKER.lock().unwrap().last_delta());
+        let position = |iso: &Isometry| {
+            let position = iso.0.translation.vector;
+            Point2::new(position.x, position.y)
+        };
+        let players: Vec<Point2> = (&entities, &isometries, &character_markers)
+            .join()
+            .filter(|(entity, _, _)| !coop.is_downed(*entity))
+            .map(|(_, iso, _)| position(iso))
+            .collect();
+        let enemy_positions: Vec<Point2> = (&isometries, &enemies)
+            .join()
+            .map(|(iso, _)| position(iso))
+            .collect();
+        for (entity, iso, velocity, size, ship_hardpoints) in
+            (&entities, &isometries, &velocities, &sizes, &mut hardpoints)
+                .join()
+        {
+            let is_character = character_markers.get(entity).is_some();
+            let (targets, entity_type) = if is_character {
+                (&enemy_positions, EntityType::Player)
+            } else {
+                (&players, EntityType::Enemy)
+            };
+            let disabled = coop.is_downed(entity)
+                || status_effects
+                    .get(entity)
+                    .map_or(false, |effects| effects.stunned());
+            let firing = ship_hardpoints.firing && !disabled;
+            let hold_fixed = ship_hardpoints.hold_fixed;
+            let mut fired = None;
+            for mount in ship_hardpoints.mounts.iter_mut() {
+                let mut aimed = !hold_fixed;
+                if let Some(turret) = mount.turret {
+                    let mount_isometry = mount.isometry(&iso.0, size.0);
+                    let mount_position = mount_isometry.translation.vector;
+                    let mount_position =
+                        Point2::new(mount_position.x, mount_position.y);
+                    let base = iso.rotation() + mount.angle;
+                    let arc = turret.arc.to_radians();
+                    // nearest target within range and arc
+                    let target = targets
+                        .iter()
+                        .filter_map(|target| {
+                            let diff = *target - mount_position;
+                            let target_rot = -(-diff.x).atan2(-diff.y);
+                            let turn = angle_shortest_dist(base, target_rot);
+                            if diff.norm() > turret.range || turn.abs() > arc {
+                                None
+                            } else {
+                                Some((diff.norm(), turn))
+                            }
+                        })
+                        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+                        .map(|(_, turn)| turn);
+                    // turret returns to the base angle when idle
+                    let goal = target.unwrap_or(0.0);
+                    let turn_rate = turret.turn_rate * time_scaler;
+                    mount.turret_angle += (goal - mount.turret_angle)
+                        .max(-turn_rate)
+                        .min(turn_rate);
+                    aimed = target.map_or(false, |turn| {
+                        (turn - mount.turret_angle).abs() < TURRET_FIRE_ANGLE
+                    });
+                }
+                if firing && aimed {
+                    let bullets = mount.fire(
+                        entity_type,
+                        &iso.0,
+                        size.0,
+                        velocity.0,
+                        entity,
+                    );
+                    if !bullets.is_empty() {
+                        fired = Some(match (is_character, &mount.gun) {
+                            (true, _) => preloaded_sounds.shot,
+                            (false, GunKind::Cannon(_)) => {
+                                preloaded_sounds.enemy_blaster
+                            }
+                            (false, _) => preloaded_sounds.enemy_shotgun,
+                        });
+                    }
+                    insert_channel.iter_write(bullets.into_iter());
+                }
+            }
+            if let Some(sound) = fired {
+                sounds_channel.single_write(Sound(sound, position(iso)));
+            }
+        }
+        info!("asteroids: hardpoints ended");
+    }
+}
@@ -119,7 +119,7 @@ impl<'a> System<'a> for InsertSystem {
     type SystemData = (
         Entities<'a>,
         WriteStorage<'a, PhysicsComponent>,
-        WriteStorage<'a, ShotGun>,
+        WriteStorage<'a, Hardpoints>,
         ReadStorage<'a, CharacterMarker>,
         ReadExpect<'a, ThreadPin<red::GL>>,
         WriteExpect<'a, PreloadedImages>,
@@ -130,13 +130,24 @@ impl<'a> System<'a> for InsertSystem {
         WriteExpect<'a, Canvas>,
         Read<'a, LazyUpdate>,
         Write<'a, UpgradesStats>,
+        Read<'a, AsteroidKinds>,
+        Read<'a, GravityWellKinds>,
+        ReadExpect<'a, MacroGame>,
+        Read<'a, Shop>,
+        Write<'a, RunUpgrades>,
+        Write<'a, SpawnedUpgrades>,
+        Read<'a, AvaliableUpgrades>,
+        Read<'a, RunMode>,
+        Write<'a, RunRngs>,
+        ReadStorage<'a, AsteroidMarker>,
+        Write<'a, Coop>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             entities,
             mut physics,
-            mut shotguns,
+            mut ships_hardpoints,
             character_markers,
             gl,
             preloaded_images,
@@ -147,8 +158,18 @@ impl<'a> System<'a> for InsertSystem {
             mut canvas,
             lazy_update,
             mut upgrades_stats,
+            asteroid_kinds,
+            gravity_well_kinds,
+            macro_game,
+            shop,
+            mut run_upgrades,
+            mut spawned_upgrades,
+            avaliable_upgrades,
+            run_mode,
+            mut run_rngs,
+            asteroid_markers,
+            mut coop,
         ) = data;
-        let mut rng = thread_rng();
         info!("asteroids: started insert system");
         for insert in insert_channel.read(&mut self.reader) {
             match insert {
@@ -156,8 +177,60 @@ impl<'a> System<'a> for InsertSystem {
                     gun_kind,
                     ship_stats,
                     image,
+                    resistances,
+                    hardpoints,
+                    player,
                 } => {
-                    *progress = Progress::default();
+                    // permanent bonuses from the shop, daily runs are the
+                    // same for everyone so bought perks are off there
+                    let bonus = |perk| match *run_mode {
+                        RunMode::Normal => shop.bonus(perk, &macro_game),
+                        RunMode::Daily { .. } => 0.0,
+                    };
+                    let mut ship_stats = *ship_stats;
+                    ship_stats.max_health +=
+                        bonus(Perk::StartingHealth) as usize;
+                    ship_stats.shield_regen +=
+                        bonus(Perk::ShieldRegen) as usize;
+                    if run_mode.modifier() == Some(DailyModifier::NoShields) {
+                        ship_stats.max_shield = 0;
+                        ship_stats.shield_regen = 0;
+                    }
+                    // run state is shared, so the first player sets it up
+                    if *player == 0 {
+                        *progress = Progress::default();
+                        *coop = Coop {
+                            local_players: coop.local_players,
+                            ..Coop::default()
+                        };
+                        if let RunMode::Daily { .. } = *run_mode {
+                            // asteroids left from the menu are not seeded
+                            for (entity, _) in
+                                (&entities, &asteroid_markers).join()
+                            {
+                                entities.delete(entity).unwrap();
+                            }
+                        }
+                        upgrades_stats.magnet_bonus = bonus(Perk::MagnetRadius);
+                        upgrades_stats.ability_duration =
+                            1.0 + bonus(Perk::AbilityDuration);
+                        run_upgrades.rerolls +=
+                            bonus(Perk::RerollTokens) as usize;
+                        let start_levels = bonus(Perk::StartingLevel) as usize;
+                        // every bought level comes with its upgrade offer
+                        for _ in 0..start_levels {
+                            progress.level += 1;
+                            let offer = offer_upgrades(
+                                &avaliable_upgrades,
+                                &run_upgrades.picked,
+                                UPGRADE_CARDS_NUM,
+                                &mut run_rngs.upgrades,
+                            );
+                            if offer.len() > 0 {
+                                spawned_upgrades.push(offer);
+                            }
+                        }
+                    }
                     let char_size = 0.5f32;
                     let character_shape =
                         Geometry::Circle { radius: char_size };
@@ -166,28 +239,43 @@ impl<'a> System<'a> for InsertSystem {
                     let life = Lifes(ship_stats.max_health);
                     let shield = Shield(ship_stats.max_shield);
                     let character = entities.create();
+                    // projectile guns are mounted, lazers are beams of the ship
+                    let mut mounts = hardpoints.clone();
                     match gun_kind {
                         GunKind::MultyLazer(multy_lazer) => {
                             lazy_update.insert(character, multy_lazer.clone())
                         }
-                        GunKind::ShotGun(shotgun) => {
-                            lazy_update.insert(character, *shotgun);
-                        }
-                        _ => unimplemented!(),
+                        gun => mounts.insert(0, Hardpoint::main(gun.clone())),
                     };
+                    if !mounts.is_empty() {
+                        lazy_update.insert(character, Hardpoints::new(mounts));
+                    }
                     lazy_update.insert(character, life);
                     lazy_update.insert(character, shield);
+                    let spawn_x = *player as f32 * COOP_SPAWN_SHIFT;
                     lazy_update
-                        .insert(character, Isometry::new(0f32, 0f32, 0f32));
+                        .insert(character, Isometry::new(spawn_x, 0f32, 0f32));
                     lazy_update.insert(character, Velocity::new(0f32, 0f32));
                     lazy_update.insert(character, CharacterMarker::default());
+                    lazy_update.insert(
+                        character,
+                        Player {
+                            id: *player,
+                            progress: Progress::default(),
+                        },
+                    );
+                    coop.players.push(character);
                     lazy_update.insert(character, Damage(ship_stats.damage));
                     lazy_update.insert(character, ShipMarker::default());
+                    lazy_update.insert(character, StatusEffects::default());
+                    lazy_update.insert(character, *resistances);
                     lazy_update.insert(character, *image);
                     lazy_update.insert(character, Spin::default());
                     lazy_update.insert(character, character_shape);
                     lazy_update.insert(character, Size(char_size));
-                    lazy_update.insert(character, *ship_stats);
+                    lazy_update.insert(character, ship_stats);
+                    lazy_update.insert(character, Dash::default());
+                    lazy_update.insert(character, AbilityBar::default());
                     let character_physics_shape =
                         ncollide2d::shape::Ball::new(char_size);
 
@@ -198,6 +286,7 @@ impl<'a> System<'a> for InsertSystem {
                         CollisionId::Asteroid as usize,
                         CollisionId::EnemyBullet as usize,
                         CollisionId::EnemyShip as usize,
+                        CollisionId::Debris as usize,
                     ]);
                     character_collision_groups
                         .set_blacklist(&[CollisionId::PlayerBullet as usize]);
@@ -206,7 +295,7 @@ impl<'a> System<'a> for InsertSystem {
                         &mut physics,
                         character,
                         ShapeHandle::new(character_physics_shape),
-                        Isometry2::new(Vector2::new(0f32, 0f32), 0f32),
+                        Isometry2::new(Vector2::new(spawn_x, 0f32), 0f32),
                         Velocity2::new(Vector2::new(0f32, 0f32), 0f32),
                         BodyStatus::Dynamic,
                         &mut world,
@@ -241,26 +330,41 @@ impl<'a> System<'a> for InsertSystem {
                     velocity,
                     polygon,
                     spin,
+                    kind,
                 } => {
                     let mut polygon = polygon.clone();
-                    let center = polygon.center();
-                    polygon.centralize(Rotation2::new(iso.z));
+                    let rotation = Rotation2::new(iso.z);
+                    let center = rotation * polygon.center();
+                    polygon.centralize(rotation);
                     let light_shape = Geometry::Polygon(polygon.clone());
                     let iso =
                         Point3::new(iso.x + center.x, iso.y + center.y, 0.0);
-                    let physics_polygon = if let Some(physics_polygon) =
-                        ncollide2d::shape::ConvexPolygon::try_from_points(
-                            &polygon.points(),
-                        ) {
-                        physics_polygon
+                    // concave outlines are built from convex parts
+                    let parts: Vec<_> = polygon
+                        .convex_parts()
+                        .iter()
+                        .filter_map(|part| {
+                            ncollide2d::shape::ConvexPolygon::try_from_points(
+                                part,
+                            )
+                        })
+                        .map(|part| {
+                            (Isometry2::identity(), ShapeHandle::new(part))
+                        })
+                        .collect();
+                    let physics_shape = match parts.len() {
+                        // degenerate outline, skip only this asteroid
+                        0 => continue,
+                        1 => parts[0].1.clone(),
+                        _ => ShapeHandle::new(
+                            ncollide2d::shape::Compound::new(parts),
+                        ),
+                    };
+                    let triangulation = if polygon.is_convex() {
+                        polygon.clone().into_rounded(5).triangulate()
                     } else {
-                        // TODO: looks like BUG!
-                        dbg!(&polygon.points);
-                        break;
-                        // panic!();
+                        polygon.triangulate_concave()
                     };
-                    let triangulation =
-                        polygon.clone().into_rounded(5).triangulate();
                     let geom_data = GeometryData::new(
                         &gl,
                         &triangulation.points,
@@ -275,16 +379,20 @@ impl<'a> System<'a> for InsertSystem {
                         asteroid,
                         Velocity::new(velocity.linear.x, velocity.linear.y),
                     );
-                    lazy_update.insert(
-                        asteroid,
-                        Lifes(
-                            (ASTEROID_MAX_LIFES as f32 * polygon.min_r
-                                / ASTEROID_MAX_RADIUS)
-                                as usize,
-                        ),
-                    );
+                    let lifes = match asteroid_kinds.0.get(kind.kind) {
+                        Some(asteroid_kind) => {
+                            asteroid_kind.health_per_area * polygon.area()
+                        }
+                        None => {
+                            ASTEROID_MAX_LIFES as f32 * polygon.min_r
+                                / ASTEROID_MAX_RADIUS
+                        }
+                    };
+                    lazy_update
+                        .insert(asteroid, Lifes((lifes as usize).max(1)));
                     lazy_update.insert(asteroid, polygon);
                     lazy_update.insert(asteroid, AsteroidMarker::default());
+                    lazy_update.insert(asteroid, *kind);
                     lazy_update.insert(asteroid, Spin(*spin));
                     lazy_update.insert(asteroid, Size(1f32));
                     lazy_update.insert(asteroid, ThreadPin::new(geom_data));
@@ -311,11 +419,12 @@ impl<'a> System<'a> for InsertSystem {
                         CollisionId::PlayerShip as usize,
                         CollisionId::PlayerBullet as usize,
                         CollisionId::EnemyBullet as usize,
+                        CollisionId::Debris as usize,
                     ]);
                     PhysicsComponent::safe_insert(
                         &mut physics,
                         asteroid,
-                        ShapeHandle::new(physics_polygon),
+                        physics_shape,
                         Isometry2::new(Vector2::new(iso.x, iso.y), iso.z),
                         *velocity,
                         BodyStatus::Dynamic,
@@ -336,8 +445,23 @@ impl<'a> System<'a> for InsertSystem {
                     image,
                     snake,
                     rift,
+                    steering,
+                    skill,
+                    perception,
+                    resistances,
+                    outline,
+                    hardpoints,
+                    entity,
+                    squad,
                 } => {
                     let mut kind = kind.clone();
+                    let mut ship_stats = *ship_stats;
+                    if run_mode.modifier()
+                        == Some(DailyModifier::DoubleEnemySpeed)
+                    {
+                        ship_stats.thrust_force *= 2.0;
+                        ship_stats.torque *= 2.0;
+                    }
                     let num =
                         if let Some(chains) = snake { *chains } else { 1 };
                     let mut last_entity = None;
@@ -354,29 +478,28 @@ impl<'a> System<'a> for InsertSystem {
                             CollisionId::EnemyShip as usize,
                             CollisionId::PlayerShip as usize,
                             CollisionId::PlayerBullet as usize,
+                            CollisionId::Debris as usize,
                         ]);
                         enemy_collision_groups.set_blacklist(&[
                             CollisionId::EnemyBullet as usize,
                         ]);
-                        let enemy = entities.create();
+                        let enemy = match entity {
+                            Some(entity) if i == 0 => *entity,
+                            _ => entities.create(),
+                        };
 
+                        let mut mounts = hardpoints.clone();
                         match gun_kind {
-                            GunKind::ShotGun(shotgun) => {
-                                let side_num = 3usize;
-                                let _shift = std::f32::consts::PI
-                                    / (side_num as f32 + 1.0);
-                                lazy_update.insert(enemy, *shotgun);
-                            }
                             GunKind::MultyLazer(multy_lazer) => {
                                 lazy_update.insert(enemy, multy_lazer.clone());
                             }
-                            GunKind::Cannon(cannon) => {
-                                lazy_update.insert(enemy, cannon.clone());
-                            }
-                            GunKind::RocketGun(rocket_gun) => {
-                                lazy_update.insert(enemy, *rocket_gun);
+                            gun => {
+                                mounts.insert(0, Hardpoint::main(gun.clone()))
                             }
                         }
+                        if !mounts.is_empty() {
+                            lazy_update.insert(enemy, Hardpoints::new(mounts));
+                        }
                         for kind in kind.kinds.iter_mut() {
                             match kind {
                                 AIType::Charging(time) => {
@@ -387,7 +510,9 @@ impl<'a> System<'a> for InsertSystem {
                                 }
                                 AIType::FollowRotate { spin: None } => {
                                     *kind = AIType::FollowRotate {
-                                        spin: Some(rng.gen_range(-8.0, 8.0)),
+                                        spin: Some(
+                                            run_rngs.waves.gen_range(-8.0, 8.0),
+                                        ),
                                     }
                                 }
                                 _ => (),
@@ -402,16 +527,31 @@ impl<'a> System<'a> for InsertSystem {
                         lazy_update.insert(enemy, *image);
                         lazy_update.insert(enemy, Damage(ship_stats.damage));
                         lazy_update.insert(enemy, Lifes(ship_stats.max_health));
-                        lazy_update.insert(enemy, *ship_stats);
+                        lazy_update.insert(enemy, ship_stats);
                         // if let AIType::FollowRotate{spin: None} = kind.clone() {
                         //     lazy_update.insert(enemy,AIType::FollowRotate{spin: Some(rng.gen_range(1.0, 5.0))})
                         // } else {
                         lazy_update.insert(enemy, kind.clone());
                         // }
                         lazy_update.insert(enemy, Spin::default());
+                        lazy_update.insert(enemy, *steering);
+                        lazy_update.insert(enemy, *skill);
+                        lazy_update.insert(enemy, *perception);
+                        lazy_update.insert(enemy, *resistances);
+                        lazy_update.insert(
+                            enemy,
+                            ShipOutline(Polygon::new(
+                                outline
+                                    .points
+                                    .iter()
+                                    .map(|p| Point2::from(size * p.coords))
+                                    .collect(),
+                            )),
+                        );
                         lazy_update.insert(enemy, enemy_shape);
                         lazy_update.insert(enemy, Size(size));
                         lazy_update.insert(enemy, DamageFlash(0f32));
+                        lazy_update.insert(enemy, StatusEffects::default());
                         PhysicsComponent::safe_insert(
                             &mut physics,
                             enemy,
@@ -435,6 +575,9 @@ impl<'a> System<'a> for InsertSystem {
                                 )
                             }
                         }
+                        if let Some(squad) = squad {
+                            lazy_update.insert(enemy, *squad);
+                        }
                         if let Some(rift) = rift {
                             lazy_update.insert(enemy, rift.clone());
                             // lazy_update.insert(enemy, Aim(last_entity))
@@ -476,6 +619,7 @@ impl<'a> System<'a> for InsertSystem {
                     lazy_update.insert(bullet, *bullet_image);
                     lazy_update.insert(bullet, Spin::default());
                     lazy_update.insert(bullet, Projectile { owner: *owner });
+                    lazy_update.insert(bullet, DamageType::Kinetic);
                     lazy_update.insert(bullet, Lifetime::new(*lifetime));
                     lazy_update.insert(bullet, Size(*size));
                     if let Some(reflection) = reflection {
@@ -526,19 +670,43 @@ impl<'a> System<'a> for InsertSystem {
                     kind,
                     iso,
                     damage,
+                    speed,
+                    lifetime,
                     owner,
                     rocket_image,
                 } => {
                     let r = 0.3;
                     let entity = entities.create();
+                    let direction = Rotation2::new(iso.z)
+                        * Vector2::new(0f32, -1f32);
+                    let velocity = *speed * direction;
                     lazy_update.insert(entity, Damage(*damage));
                     lazy_update
                         .insert(entity, Isometry::new(iso.x, iso.y, iso.z));
-                    lazy_update.insert(entity, Velocity::new(0f32, 0f32));
+                    lazy_update
+                        .insert(entity, Velocity::new(velocity.x, velocity.y));
                     lazy_update.insert(entity, *rocket_image);
                     lazy_update.insert(entity, Spin::default());
-                    lazy_update.insert(entity, Rocket(Instant::now()));
+                    lazy_update.insert(
+                        entity,
+                        Rocket {
+                            kind: *kind,
+                            speed: *speed,
+                            target: None,
+                        },
+                    );
                     lazy_update.insert(entity, Projectile { owner: *owner });
+                    lazy_update.insert(entity, DamageType::Explosive);
+                    lazy_update.insert(entity, Lifetime::new(*lifetime));
+                    lazy_update.insert(
+                        entity,
+                        Blast {
+                            blast_damage: *damage,
+                            blast_radius: ROCKET_BLAST_RADIUS,
+                            effect: None,
+                            damage_type: DamageType::Explosive,
+                        },
+                    );
                     lazy_update.insert(entity, Size(r));
                     let bullet_collision_groups = get_collision_groups(*kind);
                     let ball = ncollide2d::shape::Ball::new(r);
@@ -548,7 +716,7 @@ impl<'a> System<'a> for InsertSystem {
                             entity,
                             ShapeHandle::new(ball),
                             Isometry2::new(Vector2::new(iso.x, iso.y), iso.z),
-                            Velocity2::new(Vector2::new(0f32, 0f32), 0f32),
+                            Velocity2::new(velocity, 0f32),
                             BodyStatus::Dynamic,
                             &mut world,
                             &mut bodies_map,
@@ -572,6 +740,108 @@ impl<'a> System<'a> for InsertSystem {
                         Lifetime::new(Duration::from_secs(COIN_LIFETIME_SECS)),
                     );
                 }
+                InsertEvent::Blast {
+                    position,
+                    blast,
+                    owner,
+                } => {
+                    let entity = entities.create();
+                    lazy_update.insert(
+                        entity,
+                        Isometry::new(position.x, position.y, 0f32),
+                    );
+                    lazy_update.insert(entity, *blast);
+                    if let Some(owner) = owner {
+                        lazy_update
+                            .insert(entity, Projectile { owner: *owner });
+                    }
+                    // processed by gameplay system on the next frame
+                    lazy_update
+                        .insert(entity, Lifetime::new(Duration::from_secs(0)));
+                }
+                InsertEvent::Debris {
+                    iso,
+                    velocity,
+                    polygon,
+                    image,
+                    size,
+                } => {
+                    let mut polygon = polygon.clone();
+                    let center = polygon.center();
+                    let offset = Rotation2::new(iso.z) * center.coords;
+                    // sprite quad coordinates, debris is drawn with size scale
+                    let (w, h) = image.dim_scales();
+                    let to_quad = |p: Point2| {
+                        Point2::new(p.x / (w * size), p.y / (h * size))
+                    };
+                    let triangulation = polygon.triangulate();
+                    let tex_coords: Vec<Point2> = triangulation
+                        .points
+                        .iter()
+                        .map(|p| to_quad(*p))
+                        .collect();
+                    let positions: Vec<Point2> = triangulation
+                        .points
+                        .iter()
+                        .map(|p| to_quad(p - center.coords))
+                        .collect();
+                    polygon.centralize(Rotation2::identity());
+                    let physics_polygon = if let Some(physics_polygon) =
+                        ncollide2d::shape::ConvexPolygon::try_from_points(
+                            &polygon.points,
+                        ) {
+                        physics_polygon
+                    } else {
+                        continue;
+                    };
+                    let polygon_data = AtlasPolygonData::new(
+                        &gl,
+                        &positions,
+                        &tex_coords,
+                        &triangulation.indicies,
+                    )
+                    .unwrap();
+                    let iso =
+                        Point3::new(iso.x + offset.x, iso.y + offset.y, iso.z);
+                    let debris = entities.create();
+                    lazy_update
+                        .insert(debris, Isometry::new(iso.x, iso.y, iso.z));
+                    lazy_update.insert(
+                        debris,
+                        Debris {
+                            image: *image,
+                            size: *size,
+                        },
+                    );
+                    lazy_update.insert(debris, ThreadPin::new(polygon_data));
+                    lazy_update.insert(
+                        debris,
+                        Lifetime::new(Duration::from_secs(
+                            DEBRIS_LIFETIME_SECS,
+                        )),
+                    );
+                    let mut debris_collision_groups = CollisionGroups::new();
+                    debris_collision_groups
+                        .set_membership(&[CollisionId::Debris as usize]);
+                    debris_collision_groups.set_whitelist(&[
+                        CollisionId::Asteroid as usize,
+                        CollisionId::EnemyShip as usize,
+                        CollisionId::PlayerShip as usize,
+                        CollisionId::Debris as usize,
+                    ]);
+                    PhysicsComponent::safe_insert(
+                        &mut physics,
+                        debris,
+                        ShapeHandle::new(physics_polygon),
+                        Isometry2::new(Vector2::new(iso.x, iso.y), iso.z),
+                        *velocity,
+                        BodyStatus::Dynamic,
+                        &mut world,
+                        &mut bodies_map,
+                        debris_collision_groups,
+                        DEBRIS_INERTIA,
+                    );
+                }
                 InsertEvent::SideBulletCollectable { position } => {
                     let iso = Isometry::new(position.x, position.y, 0f32);
                     let entity = entities.create();
@@ -588,14 +858,49 @@ impl<'a> System<'a> for InsertSystem {
                     lazy_update
                         .insert(entity, preloaded_images.side_bullet_ability);
                 }
+                InsertEvent::DroneCollectable { position } => {
+                    let iso = Isometry::new(position.x, position.y, 0f32);
+                    let entity = entities.create();
+                    lazy_update.insert(entity, CollectableMarker);
+                    lazy_update.insert(entity, DroneCollectable);
+                    lazy_update.insert(
+                        entity,
+                        Lifetime::new(Duration::from_secs(
+                            COLLECTABLE_DRONE_SEC,
+                        )),
+                    );
+                    lazy_update.insert(entity, iso);
+                    lazy_update.insert(entity, Size(0.5));
+                    lazy_update.insert(entity, preloaded_images.basic_ship);
+                }
+                InsertEvent::Drone {
+                    entity,
+                    owner,
+                    slot,
+                    kind,
+                    position,
+                } => {
+                    let drone = *entity;
+                    let iso = Isometry::new(position.x, position.y, 0f32);
+                    lazy_update.insert(drone, iso);
+                    lazy_update.insert(drone, AttachPosition(*owner));
+                    lazy_update
+                        .insert(drone, AttachOffset(Vector2::new(0f32, 0f32)));
+                    lazy_update.insert(drone, kind.image);
+                    lazy_update.insert(drone, Size(kind.size));
+                    lazy_update.insert(drone, DamageFlash(0f32));
+                    lazy_update.insert(drone, Drone::new(*owner, *slot, kind));
+                    lazy_update.insert(drone, Lifes(kind.health));
+                }
                 InsertEvent::SideBulletAbility => {
                     let entity = entities.create();
                     lazy_update.insert(entity, SideBulletAbility);
                     lazy_update.insert(
                         entity,
-                        Lifetime::new(Duration::from_secs(
+                        ability_lifetime(
                             SIDE_BULLET_LIFETIME_SEC,
-                        )),
+                            &upgrades_stats,
+                        ),
                     );
                 }
                 InsertEvent::DoubleCoinsCollectable { position } => {
@@ -619,9 +924,10 @@ impl<'a> System<'a> for InsertSystem {
                     lazy_update.insert(entity, DoubleCoinsAbility);
                     lazy_update.insert(
                         entity,
-                        Lifetime::new(Duration::from_secs(
+                        ability_lifetime(
                             DOUBLE_COINS_LIFETIME_SEC,
-                        )),
+                            &upgrades_stats,
+                        ),
                     );
                 }
                 InsertEvent::DoubleExpCollectable { position } => {
@@ -646,9 +952,10 @@ impl<'a> System<'a> for InsertSystem {
                     lazy_update.insert(entity, DoubleExpAbility);
                     lazy_update.insert(
                         entity,
-                        Lifetime::new(Duration::from_secs(
+                        ability_lifetime(
                             DOUBLE_COINS_LIFETIME_SEC,
-                        )),
+                            &upgrades_stats,
+                        ),
                     );
                 }
                 InsertEvent::ReflectBulletCollectable { position } => {
@@ -674,26 +981,30 @@ impl<'a> System<'a> for InsertSystem {
                     );
                 }
                 InsertEvent::ReflectBulletAbility => {
-                    let (character, _) =
-                        (&entities, &character_markers).join().next().unwrap();
-                    if let Some(gun) = shotguns.get_mut(character) {
-                        if let Some(ref mut reflection) = gun.reflection {
-                            reflection.lifetime += Duration::from_millis(200);
-                        } else {
-                            gun.reflection = Some(Reflection {
-                                speed: 0.4,
-                                lifetime: Duration::from_millis(1500),
-                                times: None,
-                            })
+                    for (mounts, _) in
+                        (&mut ships_hardpoints, &character_markers).join()
+                    {
+                        for gun in mounts.shotguns_mut() {
+                            if let Some(ref mut reflection) = gun.reflection {
+                                reflection.lifetime +=
+                                    Duration::from_millis(200);
+                            } else {
+                                gun.reflection = Some(Reflection {
+                                    speed: 0.4,
+                                    lifetime: Duration::from_millis(1500),
+                                    times: None,
+                                })
+                            }
                         }
                     }
                     let entity = entities.create();
                     lazy_update.insert(entity, ReflectBulletAbility);
                     lazy_update.insert(
                         entity,
-                        Lifetime::new(Duration::from_secs(
+                        ability_lifetime(
                             REFLECT_BULLET_LIFETIME_SEC,
-                        )),
+                            &upgrades_stats,
+                        ),
                     );
                 }
                 InsertEvent::Health { value, position } => {
@@ -823,6 +1134,19 @@ impl<'a> System<'a> for InsertSystem {
                     lazy_update.insert(nebula, PlanetMarker::default());
                     lazy_update.insert(nebula, Size(25f32));
                 }
+                InsertEvent::GravityWell { position, kind } => {
+                    let kind = &gravity_well_kinds.0[*kind];
+                    let gravity_well = entities.create();
+                    lazy_update.insert(
+                        gravity_well,
+                        Isometry::new(position.x, position.y, 0f32),
+                    );
+                    lazy_update.insert(gravity_well, kind.well);
+                    lazy_update.insert(gravity_well, Size(kind.size));
+                    if let Some(image) = kind.image {
+                        lazy_update.insert(gravity_well, image);
+                    }
+                }
                 InsertEvent::Wobble(wobble) => canvas.add_wobble(*wobble),
             }
         }
@@ -13,11 +13,14 @@ impl<'a> System<'a> for KinematicSystem {
         ReadStorage<'a, PhysicsComponent>,
         ReadStorage<'a, Spin>,
         ReadStorage<'a, AttachPosition>,
+        ReadStorage<'a, AttachOffset>,
         ReadStorage<'a, CharacterMarker>,
         ReadStorage<'a, AsteroidMarker>,
         ReadStorage<'a, ShipMarker>,
         ReadStorage<'a, Projectile>,
         ReadStorage<'a, ShipStats>,
+        ReadStorage<'a, StatusEffects>,
+        ReadStorage<'a, Dash>,
         Write<'a, World<f32>>,
     );
 
@@ -29,16 +32,19 @@ impl<'a> System<'a> for KinematicSystem {
             physics,
             spins,
             attach_positions,
+            attach_offsets,
             character_markers,
             asteroid_markers,
             ship_markers,
             projectiles,
             ships_stats,
+            status_effects,
+            dashes,
             mut world,
         ) = data;
         info!("asteroids: kinematic system started");
-        for (physics_component, _, _) in
-            (&physics, !&asteroid_markers, !&projectiles).join()
+        for (entity, physics_component, _, _) in
+            (&entities, &physics, !&asteroid_markers, !&projectiles).join()
         {
             let body =
                 world.rigid_body_mut(physics_component.body_handle).unwrap();
@@ -46,11 +52,15 @@ impl<'a> System<'a> for KinematicSystem {
             let max_velocity = 0.245f32;
             *velocity.as_vector_mut() *= DAMPING_FACTOR;
             let vel = *velocity.as_vector_mut();
-            let velocity = if velocity.as_vector_mut().norm() > max_velocity {
-                max_velocity * vel.normalize()
-            } else {
-                vel
-            };
+            // dash is faster than any thrust
+            let dashing =
+                dashes.get(entity).map_or(false, |dash| dash.is_active());
+            let velocity =
+                if velocity.as_vector_mut().norm() > max_velocity && !dashing {
+                    max_velocity * vel.normalize()
+                } else {
+                    vel
+                };
             // let v = velocity.as_vector();
             let velocity = Velocity2::new(Vector2::new(velocity.x, velocity.y), velocity.z);
             body.set_velocity(velocity);
@@ -86,7 +96,10 @@ impl<'a> System<'a> for KinematicSystem {
             let body =
                 world.rigid_body_mut(physics_component.body_handle).unwrap();
             if let Some(_) = character_markers.get(entity) {
-                body.set_angular_velocity(ship_stats.torque * spin.0);
+                let torque =
+                    effective_stats(ship_stats, status_effects.get(entity))
+                        .torque;
+                body.set_angular_velocity(torque * spin.0);
             } else {
                 body.set_angular_velocity(spin.0);
             }
@@ -98,13 +111,17 @@ impl<'a> System<'a> for KinematicSystem {
             attach_pairs.push((entity, attach.0));
         }
         for (entity, attach) in attach_pairs.iter() {
+            let offset = attach_offsets
+                .get(*entity)
+                .map_or(Vector2::new(0f32, 0f32), |offset| offset.0);
             // let physics_component = physics.get(*attach).unwrap();
             // let iso2 = world.rigid_body(physics_component.body_handle).position();
             match isometries.get(*attach) {
                 Some(isometry) => {
                     let iso = isometry;
                     isometries.get_mut(*entity).unwrap().0.translation.vector =
-                        iso.0.translation.vector;
+                        iso.0.translation.vector
+                            + Vector3::new(offset.x, offset.y, 0f32);
                 }
                 None => {
                     entities.delete(*entity).unwrap();
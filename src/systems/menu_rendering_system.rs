@@ -26,6 +26,13 @@ impl<'a> System<'a> for MenuRenderingSystem {
         WriteExpect<'a, MacroGame>,
         Write<'a, EventChannel<Sound>>,
         ReadExpect<'a, PreloadedSounds>,
+        Write<'a, RunUpgrades>,
+        Write<'a, RunMode>,
+        Write<'a, RunRngs>,
+        Write<'a, GameMode>,
+        Write<'a, RunTimer>,
+        Write<'a, Coop>,
+        WriteExpect<'a, Director>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -49,6 +56,13 @@ impl<'a> System<'a> for MenuRenderingSystem {
             mut macro_game,
             mut sounds_channel,
             preloaded_sounds,
+            mut run_upgrades,
+            mut run_mode,
+            mut run_rngs,
+            mut game_mode,
+            mut run_timer,
+            mut coop,
+            mut director,
         ) = data;
         let mut frame = red::Frame::new(&gl);
         // frame.set_clear_color(0.0, 0.0, 0.0, 1.0);
@@ -80,15 +94,19 @@ impl<'a> System<'a> for MenuRenderingSystem {
         let button_w = w / 12f32;
         let button_h = button_w;
         let mut buttons = vec![];
-        let buttons_names = vec!["", ""];
-        let guns = vec![Widgets::BlasterGun, Widgets::LazerGun];
-        let locked_guns_ids =
-            vec![Widgets::LockedBlasterGun, Widgets::LockedLazerGun];
+        let buttons_names = vec!["", "", ""];
+        let guns =
+            vec![Widgets::BlasterGun, Widgets::LazerGun, Widgets::RocketGun];
+        let locked_guns_ids = vec![
+            Widgets::LockedBlasterGun,
+            Widgets::LockedLazerGun,
+            Widgets::LockedRocketGun,
+        ];
         let buttons_num = buttons_names.len();
         let button_images = vec![
             preloaded_images.blaster,
             preloaded_images.lazer,
-            preloaded_images.blaster,
+            name_to_atlas["rocket"],
         ];
         let shift_between = w / 20f32;
         let shift_init = w / 2.0 - shift_between - button_w - button_w / 2.0;
@@ -157,12 +175,11 @@ impl<'a> System<'a> for MenuRenderingSystem {
                     // );
                 }
                 Widgets::LazerGun => {
-                    ui_state.chosed_gun =
-                        Some(description.player_guns[1].clone());
+                    ui_state.chosed_gun = description.player_lazer().cloned();
                 }
-                Widgets::ShotGun => {
+                Widgets::RocketGun => {
                     ui_state.chosed_gun =
-                        Some(description.player_guns[2].clone());
+                        Some(description.player_guns[3].clone());
                 }
                 Widgets::LockedLazerGun => {
                     if macro_game.coins >= description.gun_costs[1] {
@@ -173,7 +190,19 @@ impl<'a> System<'a> for MenuRenderingSystem {
                         ));
                         macro_game.coins -= description.gun_costs[1];
                         ui_state.chosed_gun =
-                            Some(description.player_guns[1].clone());
+                            description.player_lazer().cloned();
+                    }
+                }
+                Widgets::LockedRocketGun => {
+                    if macro_game.coins >= description.gun_costs[2] {
+                        macro_game.guns_unlocked[2] = true;
+                        sounds_channel.single_write(Sound(
+                            preloaded_sounds.buy,
+                            Point2::new(0f32, 0f32),
+                        ));
+                        macro_game.coins -= description.gun_costs[2];
+                        ui_state.chosed_gun =
+                            Some(description.player_guns[3].clone());
                     }
                 }
                 _ => (),
@@ -299,10 +328,46 @@ impl<'a> System<'a> for MenuRenderingSystem {
         if score_table_button.place_and_check(&mut ui, &*mouse) {
             *app_state = AppState::ScoreTable;
         }
+        let shop_button = Button::new(
+            Point2::new(
+                w / 2.0,
+                1.5 * button_h + shift_between + button_h / 4.0,
+            ),
+            button_w,
+            button_h / 5.0,
+            None,
+            false,
+            Some(preloaded_images.upg_bar),
+            "Shop".to_string(),
+            Widgets::Shop as usize,
+            Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+            Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
+        );
+        if shop_button.place_and_check(&mut ui, &*mouse) {
+            *app_state = AppState::Shop;
+        }
+        let controls_button = Button::new(
+            Point2::new(
+                w / 2.0,
+                1.5 * button_h + shift_between + button_h / 2.0,
+            ),
+            button_w,
+            button_h / 5.0,
+            None,
+            false,
+            Some(preloaded_images.upg_bar),
+            "Controls".to_string(),
+            Widgets::Controls as usize,
+            Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+            Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
+        );
+        if controls_button.place_and_check(&mut ui, &*mouse) {
+            *app_state = AppState::Controls;
+        }
         let button_w = button_w / 2.0;
         let button_h = button_w;
         let button = Button::new(
-            Point2::new(w / 2.0 - button_w / 2.0, h - button_h),
+            Point2::new(w / 2.0 - button_w - shift_between / 2.0, h - button_h),
             // Point2::new(0f32, 0f32),
             button_w,
             button_h / 4.0,
@@ -314,21 +379,168 @@ impl<'a> System<'a> for MenuRenderingSystem {
             Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
             Some(Sound(preloaded_sounds.play, Point2::new(0f32, 0f32))),
         );
+        let modes_ids = vec![
+            Widgets::ClassicMode,
+            Widgets::TimeAttackMode,
+            Widgets::SurvivalMode,
+            Widgets::ZenMode,
+        ];
+        let modes_num = GameMode::ALL.len() as f32;
+        let modes_shift = w / 2.0
+            - (modes_num * button_w + (modes_num - 1.0) * shift_between) / 2.0;
+        let mut buttons = vec![];
+        for (i, mode) in GameMode::ALL.iter().enumerate() {
+            let button = Button::new(
+                Point2::new(
+                    modes_shift + i as f32 * (button_w + shift_between),
+                    h - 2.5 * button_h,
+                ),
+                button_w,
+                button_h / 4.0,
+                None,
+                false,
+                Some(preloaded_images.upg_bar),
+                mode.name().to_string(),
+                modes_ids[i] as usize,
+                Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+                Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
+            );
+            buttons.push(button);
+        }
+        let modes_selector = Selector {
+            buttons: buttons,
+            id: Widgets::ModeSelector as usize,
+            mask: None,
+        };
+        if let Some(selected_id) =
+            modes_selector.place_and_check(&mut ui, &*mouse)
+        {
+            if let Some(i) =
+                modes_ids.iter().position(|&id| id as usize == selected_id)
+            {
+                *game_mode = GameMode::ALL[i];
+            }
+        }
+        let day = current_day();
+        let daily_mode = RunMode::daily(day);
+        let daily_button = Button::new(
+            Point2::new(w / 2.0 + shift_between / 2.0, h - button_h),
+            button_w,
+            button_h / 4.0,
+            None,
+            false,
+            Some(preloaded_images.upg_bar),
+            "Daily".to_string(),
+            Widgets::Daily as usize,
+            Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+            Some(Sound(preloaded_sounds.play, Point2::new(0f32, 0f32))),
+        );
+        if let Some(modifier) = daily_mode.modifier() {
+            let best = match macro_game.daily_best.get(&day) {
+                Some(score) => format!(", best {}", score),
+                None => "".to_string(),
+            };
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Text(Text {
+                    position: Point2::new(w / 2.0, h - 1.5 * button_h),
+                    color: (1.0, 1.0, 1.0, 1.0),
+                    text: format!("Daily: {}{}", modifier.description(), best),
+                    font_size: 1.0,
+                }),
+                with_projection: false,
+            });
+        }
+        let coop_button = Button::new(
+            Point2::new(w / 2.0 + button_w + 1.5 * shift_between, h - button_h),
+            button_w,
+            button_h / 4.0,
+            None,
+            false,
+            Some(preloaded_images.upg_bar),
+            if coop.local_players > 1 {
+                "Co-op: On".to_string()
+            } else {
+                "Co-op: Off".to_string()
+            },
+            Widgets::Coop as usize,
+            Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+            Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
+        );
+        if coop_button.place_and_check(&mut ui, &*mouse) {
+            coop.local_players = if coop.local_players > 1 { 1 } else { 2 };
+        }
+        let dead_zone_button = Button::new(
+            Point2::new(
+                w / 2.0 - 2.0 * button_w - 1.5 * shift_between,
+                h - button_h,
+            ),
+            button_w,
+            button_h / 4.0,
+            None,
+            false,
+            Some(preloaded_images.upg_bar),
+            format!("Dead zone: {:.1}", macro_game.dead_zone),
+            Widgets::DeadZone as usize,
+            Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+            Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
+        );
+        if dead_zone_button.place_and_check(&mut ui, &*mouse) {
+            // cycle through the presets
+            let current = GAMEPAD_DEAD_ZONES
+                .iter()
+                .position(|&zone| zone >= macro_game.dead_zone - 1e-3)
+                .unwrap_or(0);
+            macro_game.dead_zone =
+                GAMEPAD_DEAD_ZONES[(current + 1) % GAMEPAD_DEAD_ZONES.len()];
+        }
         if let (Some(ship), Some(gun)) =
             (ui_state.chosed_ship.clone(), ui_state.chosed_gun.clone())
         {
-            if button.place_and_check(&mut ui, &*mouse) {
+            let mode = if button.place_and_check(&mut ui, &*mouse) {
+                Some(RunMode::Normal)
+            } else if daily_button.place_and_check(&mut ui, &*mouse) {
+                Some(daily_mode)
+            } else {
+                None
+            };
+            if let Some(mode) = mode {
+                let gun = if mode.modifier() == Some(DailyModifier::OnlyLazers)
+                {
+                    description.player_lazer().cloned().unwrap_or(gun)
+                } else {
+                    gun
+                };
+                if let RunMode::Daily { .. } = mode {
+                    // the daily has one leaderboard, so the same rules
+                    *game_mode = GameMode::Classic;
+                }
+                *run_mode = mode;
+                *run_rngs = RunRngs::new(mode);
+                *run_timer = RunTimer::default();
+                director.restart(mode == RunMode::Normal);
+                TRACKER.lock().unwrap().reset_scale();
                 *app_state = AppState::Play(PlayState::Action);
-                insert_channel.single_write(InsertEvent::Character {
-                    gun_kind: gun.clone(),
-                    ship_stats: description.player_ships[ship].ship_stats,
-                    image: ship_images[ship],
-                });
+                for player in 0..coop.local_players {
+                    insert_channel.single_write(InsertEvent::Character {
+                        gun_kind: gun.clone(),
+                        ship_stats: description.player_ships[ship].ship_stats,
+                        image: ship_images[ship],
+                        resistances: description.player_ships[ship].resistances,
+                        hardpoints: description.player_ships[ship]
+                            .hardpoints
+                            .clone(),
+                        player: player,
+                    });
+                }
                 *avaliable_upgrades = get_avaliable_cards(
                     &upgrade_cards_raw,
                     &gun.clone(),
                     &name_to_atlas,
                 );
+                *run_upgrades = RunUpgrades {
+                    picked: vec![],
+                    rerolls: UPGRADE_REROLLS_INIT,
+                };
             }
         }
         primitives_channel.iter_write(ui.primitives.drain(..));
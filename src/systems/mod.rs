@@ -19,45 +19,75 @@ use specs::Join;
 
 use crate::gui::{Primitive, PrimitiveKind, Text, UI};
 use components::*;
-use geometry::{generate_convex_polygon, Polygon, TriangulateFromCenter, EPS};
-use gfx_h::{iso3_iso2, Explosion, GeometryData, ParticlesData};
+use geometry::{generate_noise_polygon, Polygon, TriangulateFromCenter, EPS};
+use gfx_h::{
+    iso3_iso2, AtlasPolygonData, Explosion, GeometryData, ParticlesData,
+};
 use physics::CollisionId;
 use sound::{MusicData, PreloadedSounds, SoundData, EFFECT_MAX_VOLUME};
 
+mod abilities;
+mod actions;
 mod ai;
 mod collision;
 mod common_respawn;
 mod control;
+mod controls;
+mod coop;
 mod deadscreen;
+mod debris;
 mod destroy_sync;
+mod director;
+mod drones;
 mod gameplay;
+mod gravity;
 mod gui_system;
+mod hardpoints;
 mod insert;
 mod kinematic;
 mod menu_rendering_system;
+mod pause;
 mod rendering;
+mod rocket;
 mod score_table;
+mod shop;
 mod sound_system;
+mod status;
+mod steering;
 mod ui_controlling;
 mod upgrade_ui;
 mod upgrade_control;
 mod upgrader;
 
+pub use abilities::*;
+pub use actions::*;
 pub use ai::*;
 pub use collision::*;
 pub use common_respawn::*;
 pub use control::*;
+pub use controls::*;
+pub use coop::*;
 pub use deadscreen::*;
+pub use debris::*;
 pub use destroy_sync::*;
+pub use director::*;
+pub use drones::*;
 pub use gameplay::*;
+pub use gravity::*;
 pub use gui_system::*;
+pub use hardpoints::*;
 pub use insert::*;
 pub use kinematic::*;
 pub use menu_rendering_system::*;
+pub use pause::*;
 pub use physics_system::*;
 pub use rendering::*;
+pub use rocket::*;
 pub use score_table::*;
+pub use shop::*;
 pub use sound_system::*;
+pub use status::*;
+pub use steering::*;
 pub use ui_controlling::*;
 pub use upgrade_ui::*;
 pub use upgrade_control::*;
@@ -80,9 +110,27 @@ const ASTEROIDS_MIN_NUMBER: usize = 20;
 const ASTEROID_MAX_RADIUS: f32 = 4.2f32;
 const ASTEROID_MIN_RADIUS: f32 = 0.5;
 const ASTEROID_INERTIA: f32 = 2f32;
+const DEBRIS_INERTIA: f32 = 1f32;
+const DEBRIS_SITES: usize = 5;
+const DEBRIS_KICK: f32 = 0.1;
+const UPGRADE_CARDS_NUM: usize = 3;
+const UPGRADE_REROLLS_INIT: usize = 1;
+// one more reroll every that many levels
+const UPGRADE_REROLL_LEVELS: usize = 5;
+const RUN_RECORDS_MAX: usize = 20;
+const GRAVITY_WELLS_MAX_NUMBER: usize = 2;
+const GRAVITY_WELL_SPAWN_CHANCE: f32 = 0.002;
+// closer than that acceleration stops growing
+const GRAVITY_MIN_DISTANCE: f32 = 1f32;
+const GRAVITY_RINGS: usize = 3;
+const GRAVITY_RING_SEGMENTS: usize = 32;
+const GRAVITY_RING_SPEED: f32 = 0.01;
+const GRAVITY_RING_INTENSITY: f32 = 0.4;
+const GRAVITY_RING_WIDTH: f32 = 0.04;
 
 const EXPLOSION_WOBBLE: f32 = 0.4;
 const DAMAGED_RED: f32 = 0.2;
+const STATUS_TINT: f32 = 0.35;
 
 const MAGNETO_RADIUS: f32 = 4f32;
 const COLLECT_RADIUS: f32 = 0.2;
@@ -90,13 +138,38 @@ const COIN_LIFETIME_SECS: u64 = 5;
 const EXPLOSION_LIFETIME_SECS: u64 = 1;
 const BLAST_LIFETIME_SECS: u64 = 1;
 const BULLET_CONTACT_LIFETIME_SECS: u64 = 1;
+const DEBRIS_LIFETIME_SECS: u64 = 4;
 const COLLECTABLE_SIDE_BULLET: u64 = 5;
+const COLLECTABLE_DRONE_SEC: u64 = 7;
+const DASH_TRACE_TRANSPARENCY: f32 = 0.5;
+const DASH_TRACE_LIFETIME_MS: u64 = 600;
 const SIDE_BULLET_LIFETIME_SEC: u64 = 6;
 const DOUBLE_COINS_LIFETIME_SEC: u64 = 5;
 const REFLECT_BULLET_LIFETIME_SEC: u64 = 5;
 const COLLECTABLE_DOUBLE_COINS_SEC: u64 = 5;
 const COLLECTABLE_REFLECT_BULLET_SEC: u64 = 5;
-const DESTUCTION_SITES: usize = 20;
+const DESTRUCTION_MIN_SITES: usize = 6;
+const DESTRUCTION_MAX_SITES: usize = 30;
+const DESTRUCTION_SITES_PER_RADIUS: f32 = 4.0;
+const DESTRUCTION_DAMAGE_PER_SITE: usize = 25;
+const SHARD_IMPULSE: f32 = 2E-2; // per damage point
+const SHARD_MAX_SPIN: f32 = 0.3;
+const PERCEPTION_RAYS_PER_FRAME: usize = 8;
+const FLANK_DISTANCE: f32 = 5f32;
+const SQUAD_ENGAGE_AREA: f32 = 8f32;
+const FORMATION_SPEED_MULTIPLIER: f32 = 1.5;
+const ROCKET_SEEK_RANGE: f32 = 15f32;
+const ROCKET_SEEK_CONE: f32 = 60.0; // degrees
+const ROCKET_TURN_RATE: f32 = 0.06;
+const ROCKET_PROXIMITY_RADIUS: f32 = 0.7;
+const ROCKET_BLAST_RADIUS: f32 = 1.5;
+const TURRET_FIRE_ANGLE: f32 = 0.1; // radians
+const REVIVE_RADIUS: f32 = 2f32;
+const REVIVE_SPEED: f32 = 1.0 / 180.0; // per 60 fps frame, 3 seconds nearby
+const REVIVE_LIFES_PART: f32 = 0.5;
+const COOP_SPAWN_SHIFT: f32 = 2f32;
+const KEYS_TURN_SPIN: f32 = 0.3;
+const NEAR_DEATH_LIFES_PART: f32 = 0.2;
 
 pub fn thrust_calculation(
     maneuverability: f32,
@@ -112,8 +185,7 @@ pub fn thrust_calculation(
     thrust
 }
 
-pub fn initial_asteroid_velocity() -> Velocity2 {
-    let mut rng = thread_rng();
+pub fn initial_asteroid_velocity<R: Rng>(rng: &mut R) -> Velocity2 {
     let rotation = rng.gen_range(-1E-1, 1E-1);
     let mut sign = || 1i32 - 2 * rng.gen_range(0, 2);
     let signx = sign() as f32;
@@ -125,18 +197,13 @@ pub fn initial_asteroid_velocity() -> Velocity2 {
     Velocity2::new(linear_velocity, rotation)
 }
 
-pub fn initial_shard_velocity() -> Velocity2 {
-    let mut rng = thread_rng();
-    let rotation = rng.gen_range(-2E-1, 2E-1);
-    let v = 5E-2;
-    let linear_velocity =
-        Vector2::new(rng.gen_range(-v, v), rng.gen_range(-v, v));
-    Velocity2::new(linear_velocity, rotation)
-}
-
-pub fn spawn_position(char_pos: Point2, forbidden: f32, active: f32) -> Point2 {
+pub fn spawn_position<R: Rng>(
+    char_pos: Point2,
+    forbidden: f32,
+    active: f32,
+    rng: &mut R,
+) -> Point2 {
     assert!(forbidden < active);
-    let mut rng = thread_rng();
     loop {
         let x = rng.gen_range(-active, active);
         let y = rng.gen_range(-active, active);
@@ -213,42 +280,168 @@ fn get_collision_groups(kind: EntityType) -> CollisionGroups {
     }
 }
 
-// side effect -- spawn all kind of dropables from asteroid
+/// velocity of entity's rigid body, zero if there is no body
+fn body_velocity(
+    physics_component: Option<&PhysicsComponent>,
+    world: &World<f32>,
+) -> Velocity2 {
+    physics_component
+        .and_then(|physics_component| {
+            world.rigid_body(physics_component.body_handle)
+        })
+        .map_or(Velocity2::zero(), |body| *body.velocity())
+}
+
+/// kind of the asteroid entity and its material
+fn asteroid_kind(
+    asteroid: Entity,
+    asteroid_kinds: &ReadStorage<Asteroid>,
+    asteroids_description: &AsteroidKinds,
+) -> (Asteroid, AsteroidMaterial) {
+    let kind = asteroid_kinds.get(asteroid).cloned().unwrap_or_default();
+    let material = asteroids_description
+        .0
+        .get(kind.kind)
+        .map_or(AsteroidMaterial::Rock, |asteroid_kind| asteroid_kind.material);
+    (kind, material)
+}
+
+/// What broke an asteroid
+#[derive(Debug, Clone, Copy)]
+pub struct Impact {
+    pub position: Point2,
+    /// direction of the hit, zero for omnidirectional damage
+    pub direction: Vector2,
+    pub damage: usize,
+}
+
+impl Impact {
+    pub fn new(position: Point2, direction: Vector2, damage: usize) -> Self {
+        let direction = if direction.norm() > EPS {
+            direction.normalize()
+        } else {
+            Vector2::new(0.0, 0.0)
+        };
+        Impact {
+            position,
+            direction,
+            damage,
+        }
+    }
+}
+
+/// number of shards grows with asteroid size and damage of the hit
+fn destruction_sites(polygon: &Polygon, impact: Option<Impact>) -> usize {
+    let damage = impact.map_or(0, |impact| impact.damage);
+    let sites = DESTRUCTION_MIN_SITES
+        + (DESTRUCTION_SITES_PER_RADIUS * polygon.max_r) as usize
+        + damage / DESTRUCTION_DAMAGE_PER_SITE;
+    sites.min(DESTRUCTION_MAX_SITES)
+}
+
+// side effect -- spawn all kind of dropables from asteroid,
+// chances of drops are scaled by `drop_rate`.
+// `rng` is seeded from the run rngs, so daily runs break the same way
 pub fn spawn_asteroids(
     isometry: Isometry3,
+    velocity: Velocity2,
     polygon: Polygon,
+    kind: Asteroid,
+    material: AsteroidMaterial,
     insert_channel: Arc<Mutex<EventChannel<InsertEvent>>>,
-    bullet_position: Option<Point2>,
+    impact: Option<Impact>,
+    drop_rate: f32,
+    mut rng: StdRng,
 ) {
     flame::start("asteroids");
     let position = isometry.translation.vector;
-    let new_polygons = if let Some(bullet_position) = bullet_position {
-        polygon.deconstruct(
-            bullet_position - Vector2::new(position.x, position.y),
-            DESTUCTION_SITES,
-        )
-    } else {
-        polygon.deconstruct(polygon.center(), DESTUCTION_SITES)
+    let position = Point2::new(position.x, position.y);
+    let angle = isometry.rotation.euler_angles().2;
+    let rotation = Rotation2::new(angle);
+    let shard_kind = match material {
+        // armor is used up, last shards are plain rock (the default kind)
+        AsteroidMaterial::Armored { stages } if kind.stage + 1 >= stages => {
+            Asteroid {
+                stage: kind.stage + 1,
+                ..Asteroid::default()
+            }
+        }
+        _ => Asteroid {
+            kind: kind.kind,
+            stage: kind.stage + 1,
+        },
     };
-    let mut rng = thread_rng();
+    match material {
+        AsteroidMaterial::Volatile { blast } if kind.stage == 0 => {
+            insert_channel
+                .lock()
+                .unwrap()
+                .single_write(InsertEvent::Blast {
+                    position: position,
+                    blast: blast,
+                    owner: None,
+                });
+        }
+        _ => (),
+    }
+    // impact in polygon coordinates and total impulse of the hit
+    let (local_impact, impulse) = match impact {
+        Some(impact) => (
+            Point2::from(rotation.inverse() * (impact.position - position)),
+            SHARD_IMPULSE * impact.damage as f32 * impact.direction,
+        ),
+        None => (polygon.center(), Vector2::new(0.0, 0.0)),
+    };
+    let new_polygons = polygon.deconstruct(
+        local_impact,
+        destruction_sites(&polygon, impact),
+        &mut rng,
+    );
     if new_polygons.len() > 1 {
-        for poly in new_polygons.iter() {
+        // shards near the impact take bigger part of the impulse,
+        // total momentum is parent momentum plus the impulse
+        let weights: Vec<f32> = new_polygons
+            .iter()
+            .map(|poly| {
+                poly.area() / (1.0 + (poly.center() - local_impact).norm())
+            })
+            .collect();
+        let weights_sum: f32 = weights.iter().sum::<f32>().max(EPS);
+        for (poly, weight) in new_polygons.iter().zip(weights.iter()) {
+            let area = poly.area().max(EPS);
+            let shard_impulse = impulse * *weight / weights_sum;
+            let offset = rotation * poly.center().coords;
+            let linear = velocity.linear
+                + velocity.angular * Vector2::new(-offset.y, offset.x)
+                + shard_impulse / area;
+            // impulse is applied at the impact point, not at shard center
+            let arm = rotation * (local_impact - poly.center());
+            let inertia = (area * poly.max_r * poly.max_r / 2.0).max(EPS);
+            let spin = (velocity.angular + arm.perp(&shard_impulse) / inertia)
+                .max(-SHARD_MAX_SPIN)
+                .min(SHARD_MAX_SPIN);
             let insert_event = InsertEvent::Asteroid {
-                iso: Point3::new(
-                    position.x,
-                    position.y,
-                    isometry.rotation.euler_angles().2,
-                ),
-                velocity: initial_shard_velocity(),
+                iso: Point3::new(position.x, position.y, angle),
+                velocity: Velocity2::new(linear, spin),
                 polygon: poly.clone(),
-                spin: rng.gen_range(-1E-2, 1E-2),
+                spin: spin,
+                kind: shard_kind,
             };
             insert_channel.lock().unwrap().single_write(insert_event);
         }
     } else {
         // spawn coins and stuff
-        let spawn_position = Point2::new(position.x, position.y);
-        if rng.gen_range(0.0, 1.0) < 0.1 {
+        let spawn_position = position;
+        if let AsteroidMaterial::Ore { coins } = material {
+            insert_channel
+                .lock()
+                .unwrap()
+                .single_write(InsertEvent::Coin {
+                    value: coins,
+                    position: spawn_position,
+                });
+        }
+        if rng.gen_range(0.0, 1.0) < 0.1 * drop_rate {
             insert_channel
                 .lock()
                 .unwrap()
@@ -258,7 +451,7 @@ pub fn spawn_asteroids(
                 })
         }
 
-        if rng.gen_range(0.0, 1.0) < 0.1 {
+        if rng.gen_range(0.0, 1.0) < 0.1 * drop_rate {
             insert_channel
                 .lock()
                 .unwrap()
@@ -267,52 +460,77 @@ pub fn spawn_asteroids(
                     position: spawn_position,
                 });
         }
-        if rng.gen_range(0.0, 1.0) < 0.01 {
+        if rng.gen_range(0.0, 1.0) < 0.01 * drop_rate {
             insert_channel.lock().unwrap().single_write(
                 InsertEvent::SideBulletCollectable {
                     position: spawn_position,
                 },
             );
         }
-        if rng.gen_range(0.0, 1.0) < 0.02 {
+        if rng.gen_range(0.0, 1.0) < 0.02 * drop_rate {
             insert_channel.lock().unwrap().single_write(
                 InsertEvent::DoubleCoinsCollectable {
                     position: spawn_position,
                 },
             );
         }
-        if rng.gen_range(0.0, 1.0) < 0.02 {
+        if rng.gen_range(0.0, 1.0) < 0.02 * drop_rate {
             insert_channel.lock().unwrap().single_write(
                 InsertEvent::DoubleExpCollectable {
                     position: spawn_position,
                 },
             );
         }
-        if rng.gen_range(0.0, 1.0) < 0.01 {
+        if rng.gen_range(0.0, 1.0) < 0.01 * drop_rate {
             insert_channel.lock().unwrap().single_write(
                 InsertEvent::ReflectBulletCollectable {
                     position: spawn_position,
                 },
             );
         }
+        if rng.gen_range(0.0, 1.0) < 0.005 * drop_rate {
+            insert_channel.lock().unwrap().single_write(
+                InsertEvent::DroneCollectable {
+                    position: spawn_position,
+                },
+            );
+        }
     }
     flame::end("asteroids");
 }
 
+/// ship stats with status effects applied
+pub fn effective_stats(
+    stats: &ShipStats,
+    effects: Option<&StatusEffects>,
+) -> ShipStats {
+    match effects {
+        Some(effects) => effects.affect(stats),
+        None => *stats,
+    }
+}
+
 /// returns true if killed
-fn process_damage(
+pub(crate) fn process_damage(
     life: &mut Lifes,
     mut shield: Option<&mut Shield>,
-    mut projectile_damage: usize,
+    projectile_damage: usize,
+    damage_type: DamageType,
+    resistances: Option<&Resistances>,
 ) -> bool {
+    let resistances = resistances.cloned().unwrap_or_default();
+    let mut hull_fraction = 1f32;
     match shield {
         Some(ref mut shield) if shield.0 > 0usize => {
-            if shield.0 > projectile_damage {
-                shield.0 -= projectile_damage;
-                projectile_damage = 0;
+            let shield_damage = projectile_damage as f32
+                * resistances.shield.get(damage_type);
+            if shield.0 as f32 > shield_damage {
+                shield.0 -= shield_damage as usize;
+                hull_fraction = 0.0;
             } else {
+                // part of the damage that wasn't absorbed goes to hull
+                hull_fraction = 1.0 - shield.0 as f32 / shield_damage;
                 shield.0 = 0;
-                projectile_damage -= shield.0;
             }
         }
         _ => {}
@@ -320,8 +538,11 @@ fn process_damage(
     if life.0 == 0 {
         return false;
     }
-    if life.0 > projectile_damage {
-        life.0 -= projectile_damage
+    let hull_damage = (hull_fraction
+        * projectile_damage as f32
+        * resistances.hull.get(damage_type)) as usize;
+    if life.0 > hull_damage {
+        life.0 -= hull_damage
     } else {
         life.0 = 0;
         return true;
@@ -329,6 +550,24 @@ fn process_damage(
     false
 }
 
+/// deletes ship which lost all lifes, debris and director react on it
+fn destroy_ship(
+    ship: Entity,
+    entities: &Entities,
+    destroyed_channel: &mut Write<EventChannel<ShipDestroyed>>,
+) {
+    destroyed_channel.single_write(ShipDestroyed(ship));
+    entities.delete(ship).unwrap();
+}
+
+/// damage increased by armor break
+fn affected_damage(effects: Option<&StatusEffects>, damage: usize) -> usize {
+    match effects {
+        Some(effects) => (damage as f32 * effects.damage_multiplier()) as usize,
+        None => damage,
+    }
+}
+
 fn ship_explode(
     ship_pos: Point2,
     insert_channel: &mut Write<EventChannel<InsertEvent>>,
@@ -420,12 +659,111 @@ fn blast_explode(
     ));
 }
 
-pub fn to_menu(
-    app_state: &mut Write<AppState>,
-    _progress: &mut Write<Progress>,
-    _score_table: &mut Vec<usize>,
+/// Lifetime of the ability prolonged by the shop perk
+pub fn ability_lifetime(secs: u64, upgrades_stats: &UpgradesStats) -> Lifetime {
+    let millis = secs as f32 * 1000.0 * upgrades_stats.ability_duration;
+    Lifetime::new(Duration::from_millis(millis as u64))
+}
+
+/// Replaces the last offer of upgrades with a new one for a reroll
+pub fn reroll_upgrades(
+    spawned_upgrades: &mut SpawnedUpgrades,
+    run_upgrades: &mut RunUpgrades,
+    avaliable_upgrades: &AvaliableUpgrades,
+    run_rngs: &mut RunRngs,
+) {
+    if run_upgrades.rerolls == 0 || spawned_upgrades.pop().is_none() {
+        return;
+    }
+    run_upgrades.rerolls -= 1;
+    spawned_upgrades.push(offer_upgrades(
+        avaliable_upgrades,
+        &run_upgrades.picked,
+        UPGRADE_CARDS_NUM,
+        &mut run_rngs.upgrades,
+    ));
+}
+
+/// Takes card from the current offer. Offers stacked behind it were drawn
+/// before this pick, so they are drawn again if some card became ineligible
+pub fn pick_upgrade(
+    upgrade: usize,
+    spawned_upgrades: &mut SpawnedUpgrades,
+    run_upgrades: &mut RunUpgrades,
+    avaliable_upgrades: &AvaliableUpgrades,
+    run_rngs: &mut RunRngs,
 ) {
+    spawned_upgrades.pop();
+    run_upgrades.picked.push(upgrade);
+    let eligible = eligible_upgrades(avaliable_upgrades, &run_upgrades.picked);
+    for offer in spawned_upgrades.iter_mut() {
+        if offer.iter().any(|id| !eligible.contains(id)) {
+            *offer = offer_upgrades(
+                avaliable_upgrades,
+                &run_upgrades.picked,
+                UPGRADE_CARDS_NUM,
+                &mut run_rngs.upgrades,
+            );
+        }
+    }
+    spawned_upgrades.retain(|offer| !offer.is_empty());
+}
+
+/// Character ran out of lifes. Returns true if it's downed and must be
+/// kept for revive, otherwise the run is over
+pub fn knock_out(
+    app_state: &mut Write<AppState>,
+    coop: &mut Write<Coop>,
+    character: Entity,
+) -> bool {
+    if coop.is_downed(character) {
+        return true;
+    }
+    if coop.standing() > 1 {
+        coop.downed.push((character, 0.0));
+        return true;
+    }
+    coop.remove(character);
     **app_state = AppState::DeadScreen;
+    false
+}
+
+/// Gives one more drone to the ship, bay is created with the first one
+pub fn add_drone(
+    drone_bays: &mut WriteStorage<DroneBay>,
+    ship: Entity,
+    kind: DroneKind,
+) {
+    match drone_bays.get_mut(ship) {
+        Some(bay) => bay.add(kind),
+        None => {
+            let mut bay = DroneBay::default();
+            bay.add(kind);
+            drone_bays.insert(ship, bay).unwrap();
+        }
+    }
+}
+
+/// Middle point of characters which are still standing
+pub fn players_center<'a>(
+    characters: impl Iterator<Item = (Entity, &'a Isometry)>,
+    coop: &Coop,
+) -> Option<Point2> {
+    let mut sum = Vector2::new(0f32, 0f32);
+    let mut cnt = 0;
+    for (entity, iso) in characters {
+        if coop.is_downed(entity) {
+            continue;
+        }
+        let position = iso.0.translation.vector;
+        sum += Vector2::new(position.x, position.y);
+        cnt += 1;
+    }
+    if cnt == 0 {
+        None
+    } else {
+        Some(Point2::from(sum / cnt as f32))
+    }
 }
 
 fn reflect(d: Vector2, n: Vector2) -> Vector2 {
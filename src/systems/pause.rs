@@ -0,0 +1,87 @@
+use super::*;
+
+/// Menu over the frozen run
+#[derive(Default)]
+pub struct PauseMenu;
+
+impl<'a> System<'a> for PauseMenu {
+    type SystemData = (
+        ReadExpect<'a, red::Viewport>,
+        Write<'a, UI>,
+        Write<'a, AppState>,
+        Write<'a, ExitGame>,
+        Read<'a, Mouse>,
+        ReadExpect<'a, PreloadedImages>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            viewport,
+            mut ui,
+            mut app_state,
+            mut exit_game,
+            mouse,
+            preloaded_images,
+        ) = data;
+        let dims = viewport.dimensions();
+        let (w, h) = (dims.0 as f32, dims.1 as f32);
+        // dark background
+        ui.primitives.push(Primitive {
+            kind: PrimitiveKind::Picture(Picture {
+                position: Point2::new(0f32, 0f32),
+                width: w,
+                height: h,
+                image: preloaded_images.transparent_sqr,
+            }),
+            with_projection: false,
+        });
+        ui.primitives.push(Primitive {
+            kind: PrimitiveKind::Text(Text {
+                position: Point2::new(w / 2.0, h / 4.0),
+                text: "Paused".to_string(),
+                color: (1.0, 1.0, 0.7, 1.0),
+                font_size: 1.0,
+            }),
+            with_projection: false,
+        });
+        let (button_w, button_h) = (w / 5f32, h / 12f32);
+        let shift_between = button_h / 4.0;
+        let buttons = [
+            ("Resume", Widgets::Resume),
+            ("Settings", Widgets::Settings),
+            ("Quit to menu", Widgets::QuitToMenu),
+            ("Quit game", Widgets::QuitGame),
+        ];
+        let mut clicked = None;
+        for (i, (text, widget)) in buttons.iter().enumerate() {
+            let button = Button::new(
+                Point2::new(
+                    w / 2.0 - button_w / 2.0,
+                    h / 3.0 + i as f32 * (button_h + shift_between),
+                ),
+                button_w,
+                button_h,
+                None,
+                false,
+                Some(preloaded_images.upg_bar),
+                text.to_string(),
+                *widget as usize,
+                None,
+                None,
+            );
+            if button.place_and_check(&mut ui, &*mouse) {
+                clicked = Some(*widget);
+            }
+        }
+        match clicked {
+            Some(Widgets::Resume) => {
+                *app_state = AppState::Play(PlayState::Action);
+            }
+            Some(Widgets::Settings) => *app_state = AppState::Controls,
+            // run is finished as if the ship was lost, so it's still recorded
+            Some(Widgets::QuitToMenu) => *app_state = AppState::DeadScreen,
+            Some(Widgets::QuitGame) => exit_game.0 = true,
+            _ => (),
+        }
+    }
+}
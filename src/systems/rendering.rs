@@ -35,9 +35,10 @@ pub enum Widgets {
     BackMenu,
     BlasterGun,
     LazerGun,
-    ShotGun,
+    RocketGun,
     LockedBlasterGun,
     LockedLazerGun,
+    LockedRocketGun,
     BasicShip,
     HeavyShip,
     SuperShip,
@@ -46,15 +47,50 @@ pub enum Widgets {
     LockedSuperShip,
     ScoreTable,
     Play,
+    Daily,
+    ClassicMode,
+    TimeAttackMode,
+    SurvivalMode,
+    ZenMode,
+    ModeSelector,
+    Coop,
+    DeadZone,
     Upgrade1,
     Upgrade2,
+    Upgrade3,
     Upgrade,
+    Reroll,
     Done,
     WeaponSelector,
     ShipsSelector,
-    UpgradeSelector
+    UpgradeSelector,
+    Shop,
+    Controls,
+    ResetBindings,
+    Resume,
+    Settings,
+    QuitToMenu,
+    QuitGame,
+    #[cfg(any(target_os = "android"))]
+    Ability1,
+    #[cfg(any(target_os = "android"))]
+    Ability2,
+    #[cfg(any(target_os = "android"))]
+    Ability3,
+    // shop items and bindings rows take ids starting from this one,
+    // keep it last
+    ShopItem,
 }
 
+/// widgets of offered upgrade cards
+pub const UPGRADE_WIDGETS: [Widgets; UPGRADE_CARDS_NUM] =
+    [Widgets::Upgrade1, Widgets::Upgrade2, Widgets::Upgrade3];
+
+/// touch buttons of active abilities
+#[cfg(any(target_os = "android"))]
+pub const ABILITY_WIDGETS: [Widgets; ABILITY_SLOTS] =
+    [Widgets::Ability1, Widgets::Ability2, Widgets::Ability3];
+
 pub fn render_primitives<'a>(
     mouse: &Read<'a, Mouse>,
     reader: &mut ReaderId<Primitive>,
@@ -180,14 +216,30 @@ pub fn render_primitives<'a>(
 
 pub struct RenderingSystem {
     reader: ReaderId<Primitive>,
+    /// animation phase of gravity wells rings, in [0, 1)
+    gravity_phase: f32,
 }
 
 impl RenderingSystem {
     pub fn new(reader: ReaderId<Primitive>) -> Self {
-        RenderingSystem { reader: reader }
+        RenderingSystem {
+            reader: reader,
+            gravity_phase: 0f32,
+        }
     }
 }
 
+fn circle_lines(center: Point2, radius: f32) -> Vec<(Point2, Point2)> {
+    let step = 2.0 * std::f32::consts::PI / GRAVITY_RING_SEGMENTS as f32;
+    let point = |i: usize| {
+        let angle = i as f32 * step;
+        center + radius * Vector2::new(angle.cos(), angle.sin())
+    };
+    (0..GRAVITY_RING_SEGMENTS)
+        .map(|i| (point(i), point(i + 1)))
+        .collect()
+}
+
 impl<'a> System<'a> for RenderingSystem {
     type SystemData = (
         (
@@ -235,6 +287,12 @@ impl<'a> System<'a> for RenderingSystem {
         ReadExpect<'a, DevInfo>,
         Write<'a, EventChannel<Sound>>,
         ReadExpect<'a, PreloadedImages>,
+        ReadStorage<'a, StatusEffects>,
+        ReadStorage<'a, Asteroid>,
+        Read<'a, AsteroidKinds>,
+        ReadStorage<'a, Debris>,
+        ReadStorage<'a, ThreadPin<AtlasPolygonData>>,
+        ReadStorage<'a, GravityWell>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -284,6 +342,12 @@ impl<'a> System<'a> for RenderingSystem {
             dev_info,
             mut sounds_channel,
             preloaded_images,
+            status_effects,
+            asteroid_kinds,
+            asteroids_description,
+            debris,
+            debris_datas,
+            gravity_wells,
         ) = data;
         let dims = viewport.dimensions();
         flame::start("rendering");
@@ -302,18 +366,36 @@ impl<'a> System<'a> for RenderingSystem {
         flame::end("stencil");
         telegraph.update();
         flame::end("clear");
-        if let Some((iso, vel, _)) =
-            (&isometries, &velocities, &character_markers).join().next()
-        {
+        let characters: Vec<(Point2, f32)> =
+            (&isometries, &velocities, &character_markers)
+                .join()
+                .map(|(iso, vel, _)| {
+                    let position = iso.0.translation.vector;
+                    (Point2::new(position.x, position.y), vel.0.norm())
+                })
+                .collect();
+        if !characters.is_empty() {
+            // camera looks at the middle of the team and zooms out
+            // when players fly apart
+            let mut center = Point2::origin();
+            for (position, _) in characters.iter() {
+                center += position.coords / characters.len() as f32;
+            }
+            let spread = characters
+                .iter()
+                .map(|(position, _)| (position - center).norm())
+                .fold(0.0, f32::max);
+            let speed = characters
+                .iter()
+                .map(|(_, speed)| *speed)
+                .fold(0.0, f32::max);
             canvas.update_observer(
-                Point2::new(
-                    iso.0.translation.vector.x,
-                    iso.0.translation.vector.y,
-                ),
-                vel.0.norm() / VELOCITY_MAX,
+                center,
+                speed / VELOCITY_MAX,
                 Vector2::new(mouse.x01, mouse.y01).normalize(),
+                spread,
             );
-            let char_pos = iso.0.translation.vector;
+            let char_pos = center.coords;
             flame::start("shadow rendering");
             let mut final_triangulation = Triangulation::new();
             for (_entity, iso, geom, _) in
@@ -451,7 +533,13 @@ impl<'a> System<'a> for RenderingSystem {
             } else {
                 0f32
             };
-            image.color = (1f32, 1f32, 1f32, intensity);
+            image.color = match status_effects
+                .get(entity)
+                .and_then(|effects| effects.color())
+            {
+                Some((r, g, b)) => (r, g, b, intensity.max(STATUS_TINT)),
+                None => (1f32, 1f32, 1f32, intensity),
+            };
             hide_foreground_batch.append(image, iso.0, size.0);
         }
         for (iso, size, animation) in
@@ -672,7 +760,7 @@ impl<'a> System<'a> for RenderingSystem {
         flame::end("other");
         flame::start("asteroids rendering");
         // let mut asteroids_triangulation = Triangulation::new();
-        for (_entity, iso, _size, geom_data, _asteroid) in (
+        for (entity, iso, _size, geom_data, _asteroid) in (
             &entities,
             &isometries,
             &sizes,
@@ -682,6 +770,10 @@ impl<'a> System<'a> for RenderingSystem {
             .join()
         {
             if visible(&*canvas, &iso.0, dims) {
+                let (r, g, b) = asteroid_kinds
+                    .get(entity)
+                    .and_then(|kind| asteroids_description.0.get(kind.kind))
+                    .map_or((0.8, 0.8, 0.8), |kind| kind.color);
                 canvas.render_geometry(
                     &gl,
                     &viewport,
@@ -689,11 +781,72 @@ impl<'a> System<'a> for RenderingSystem {
                     &geom_data,
                     &iso.0,
                     RenderMode::Draw,
-                    Point3::new(0.8, 0.8, 0.8),
+                    Point3::new(r, g, b),
                 )
             }
         }
         flame::end("asteroids rendering");
+        flame::start("debris rendering");
+        for (iso, debris, polygon_data, lifetime) in
+            (&isometries, &debris, &debris_datas, &lifetimes).join()
+        {
+            if visible(&*canvas, &iso.0, dims) {
+                // fade out with lifetime
+                let mut image = debris.image;
+                image.transparency = lifetime.rest_fraction().max(0.0);
+                canvas.render_atlas_polygon(
+                    &gl,
+                    &viewport,
+                    &mut frame,
+                    &image,
+                    &polygon_data,
+                    &iso.0,
+                    debris.size,
+                    true,
+                    Some(red::Blend),
+                );
+            }
+        }
+        flame::end("debris rendering");
+        flame::start("gravity rendering");
+        self.gravity_phase = (self.gravity_phase
+            + GRAVITY_RING_SPEED
+                * normalize_60frame(TRACKER.lock().unwrap().last_delta()))
+            % 1.0;
+        for (iso, well) in (&isometries, &gravity_wells).join() {
+            let center = Point2::new(
+                iso.0.translation.vector.x,
+                iso.0.translation.vector.y,
+            );
+            let inner = well.event_horizon.unwrap_or(0f32);
+            // rings are running into the well and get brighter near it
+            for ring in 0..GRAVITY_RINGS {
+                let t = ((ring as f32 + self.gravity_phase)
+                    / GRAVITY_RINGS as f32)
+                    % 1.0;
+                let radius = well.radius - t * (well.radius - inner);
+                let intensity = GRAVITY_RING_INTENSITY * t;
+                canvas.draw_lines(
+                    &circle_lines(center, radius),
+                    &gl,
+                    &mut frame,
+                    &viewport,
+                    Point3::new(intensity, intensity, intensity),
+                    GRAVITY_RING_WIDTH,
+                );
+            }
+            if let Some(event_horizon) = well.event_horizon {
+                canvas.draw_lines(
+                    &circle_lines(center, event_horizon),
+                    &gl,
+                    &mut frame,
+                    &viewport,
+                    Point3::new(1.0, 0.5, 0.2),
+                    2.0 * GRAVITY_RING_WIDTH,
+                );
+            }
+        }
+        flame::end("gravity rendering");
         let _render_line = |a: Point2, b: Point2| {
             let line_width = 0.05;
             let line_length = (b.coords - a.coords).norm();
@@ -0,0 +1,129 @@
+use super::*;
+use log::info;
+use physics::*;
+
+/// Target acquisition and guidance for rockets of both player and enemies
+#[derive(Default)]
+pub struct RocketGuidanceSystem;
+
+impl<'a> System<'a> for RocketGuidanceSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, PhysicsComponent>,
+        WriteStorage<'a, Spin>,
+        WriteStorage<'a, Rocket>,
+        WriteStorage<'a, Lifetime>,
+        ReadStorage<'a, CharacterMarker>,
+        ReadStorage<'a, EnemyMarker>,
+        Write<'a, World<f32>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: rockets started");
+        let (
+            entities,
+            isometries,
+            physics,
+            mut spins,
+            mut rockets,
+            mut lifetimes,
+            character_markers,
+            enemies,
+            mut world,
+        ) = data;
+        let time_scaler =
+            normalize_60frame(TRACKER.lock().unwrap().last_delta());
+        let players: Vec<(Entity, Point2)> =
+            (&entities, &isometries, &character_markers)
+                .join()
+                .map(|(entity, iso, _)| {
+                    let position = iso.0.translation.vector;
+                    (entity, Point2::new(position.x, position.y))
+                })
+                .collect();
+        let enemy_ships: Vec<(Entity, Point2)> =
+            (&entities, &isometries, &enemies)
+                .join()
+                .map(|(entity, iso, _)| {
+                    let position = iso.0.translation.vector;
+                    (entity, Point2::new(position.x, position.y))
+                })
+                .collect();
+        for (entity, iso, spin, phys, rocket) in
+            (&entities, &isometries, &mut spins, &physics, &mut rockets).join()
+        {
+            let rocket_vec = iso.0.translation.vector;
+            let rocket_pos = Point2::new(rocket_vec.x, rocket_vec.y);
+            let targets = match rocket.kind {
+                EntityType::Player => &enemy_ships,
+                EntityType::Enemy => &players,
+            };
+            // retarget if current target is dead
+            let mut target_pos = rocket.target.and_then(|target| {
+                targets
+                    .iter()
+                    .find(|(entity, _)| *entity == target)
+                    .map(|(_, pos)| *pos)
+            });
+            if target_pos.is_none() {
+                rocket.target = None;
+                // nearest in forward cone
+                let mut min_dist = ROCKET_SEEK_RANGE;
+                for (target, pos) in targets.iter() {
+                    let diff = *pos - rocket_pos;
+                    let dist = diff.norm();
+                    if dist < min_dist
+                        && is_aligned(
+                            diff,
+                            iso.rotation(),
+                            (ROCKET_SEEK_CONE / 2.0).to_radians(),
+                        )
+                    {
+                        min_dist = dist;
+                        rocket.target = Some(*target);
+                        target_pos = Some(*pos);
+                    }
+                }
+            }
+            let rigid_body = world.rigid_body_mut(phys.body_handle).unwrap();
+            let linear = rigid_body.velocity().linear;
+            let heading = if linear.norm() > EPS {
+                Vector2::new(linear.x, linear.y).normalize()
+            } else {
+                let dir = iso.0 * Vector3::new(0f32, -1f32, 0f32);
+                Vector2::new(dir.x, dir.y)
+            };
+            let new_heading = if let Some(target_pos) = target_pos {
+                let diff = target_pos - rocket_pos;
+                if diff.norm() < ROCKET_PROXIMITY_RADIUS {
+                    // proximity detonation, blast is processed with lifetime
+                    if let Some(lifetime) = lifetimes.get_mut(entity) {
+                        lifetime.expire();
+                    }
+                }
+                // turn rate limit
+                let max_turn = ROCKET_TURN_RATE * time_scaler;
+                let angle = Rotation2::rotation_between(&heading, &diff)
+                    .angle()
+                    .max(-max_turn)
+                    .min(max_turn);
+                Rotation2::new(angle) * heading
+            } else {
+                heading
+            };
+            let velocity = rocket.speed * new_heading;
+            rigid_body.set_linear_velocity(velocity);
+            let rocket_torque = DT
+                * calculate_player_ship_spin_for_aim(
+                    new_heading,
+                    iso.rotation(),
+                    spin.0,
+                );
+            spin.0 += rocket_torque.max(-MAX_TORQUE).min(MAX_TORQUE);
+            // TODO move to Kinematic system?
+            rigid_body.set_angular_velocity(spin.0);
+        }
+        info!("asteroids: rockets ended");
+    }
+}
@@ -46,19 +46,33 @@ impl<'a> System<'a> for ScoreTableRendering {
         let (w, h) = (dims.0 as f32, dims.1 as f32);
         let (button_w, button_h) = (w / 4f32, h / 4f32);
 
-        let mut current_h = h / 20.0;
         let text_gap_h = h / 20.0; // TODO somehow measure it
-        for score in macro_game.score_table.iter() {
-            current_h += text_gap_h;
+        let column_w = w / GameMode::ALL.len() as f32;
+        // section per mode
+        for (i, mode) in GameMode::ALL.iter().enumerate() {
+            let column_x = w / 20.0 + i as f32 * column_w;
+            let mut current_h = h / 20.0;
             ui.primitives.push(Primitive {
                 kind: PrimitiveKind::Text(Text {
-                    position: Point2::new(w / 20.0, current_h),
-                    color: (1.0, 1.0, 1.0, 1.0),
-                    text: format!("{}", score).to_string(),
+                    position: Point2::new(column_x, current_h),
+                    color: (1.0, 1.0, 0.7, 1.0),
+                    text: mode.name().to_string(),
                     font_size: 1.0,
                 }),
                 with_projection: false,
             });
+            for score in macro_game.scores(*mode).iter() {
+                current_h += text_gap_h;
+                ui.primitives.push(Primitive {
+                    kind: PrimitiveKind::Text(Text {
+                        position: Point2::new(column_x, current_h),
+                        color: (1.0, 1.0, 1.0, 1.0),
+                        text: format!("{}", score).to_string(),
+                        font_size: 1.0,
+                    }),
+                    with_projection: false,
+                });
+            }
         }
 
         let back_to_menu = Button::new(
@@ -73,7 +87,8 @@ impl<'a> System<'a> for ScoreTableRendering {
             None,
             None,
         );
-        if back_to_menu.place_and_check(&mut ui, &*mouse) {
+        let back = ui.take_back();
+        if back_to_menu.place_and_check(&mut ui, &*mouse) || back {
             *app_state = AppState::Menu;
         }
 
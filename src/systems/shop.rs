@@ -0,0 +1,147 @@
+use super::*;
+use gfx_h::{TextData, WorldTextData};
+
+/// Screen with permanent upgrades bought for coins
+pub struct ShopRendering {
+    reader: ReaderId<Primitive>,
+}
+
+impl ShopRendering {
+    pub fn new(reader: ReaderId<Primitive>) -> Self {
+        ShopRendering { reader: reader }
+    }
+}
+
+impl<'a> System<'a> for ShopRendering {
+    type SystemData = (
+        ReadExpect<'a, ThreadPin<red::GL>>,
+        WriteExpect<'a, Canvas>,
+        ReadExpect<'a, red::Viewport>,
+        Write<'a, EventChannel<Primitive>>,
+        Write<'a, UI>,
+        Read<'a, Mouse>,
+        WriteExpect<'a, ThreadPin<TextData<'static>>>,
+        WriteExpect<'a, ThreadPin<WorldTextData<'static>>>,
+        Write<'a, AppState>,
+        WriteExpect<'a, MacroGame>,
+        Read<'a, Shop>,
+        ReadExpect<'a, PreloadedImages>,
+        Write<'a, EventChannel<Sound>>,
+        ReadExpect<'a, PreloadedSounds>,
+    );
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            gl,
+            mut canvas,
+            viewport,
+            mut primitives_channel,
+            mut ui,
+            mouse,
+            mut text_data,
+            mut world_text_data,
+            mut app_state,
+            mut macro_game,
+            shop,
+            preloaded_images,
+            mut sounds_channel,
+            preloaded_sounds,
+        ) = data;
+        let mut frame = red::Frame::new(&gl);
+        frame.set_clear_color(0.0, 0.0, 0.0, 1.0);
+        frame.clear_color();
+        let dims = viewport.dimensions();
+        let (w, h) = (dims.0 as f32, dims.1 as f32);
+        ui.primitives.push(Primitive {
+            kind: PrimitiveKind::Text(Text {
+                position: Point2::new(w - w / 7.0, h / 20.0),
+                color: (1.0, 1.0, 1.0, 1.0),
+                text: format!("$ {}", macro_game.coins),
+                font_size: 1.0,
+            }),
+            with_projection: false,
+        });
+        let row_h = h / 10.0;
+        let (buy_button_w, buy_button_h) = (w / 6.0, 0.8 * row_h);
+        for (i, item) in shop.0.iter().enumerate() {
+            let level = macro_game.perk_level(item.perk);
+            let current_h = h / 10.0 + i as f32 * row_h;
+            ui.primitives.push(Primitive {
+                kind: PrimitiveKind::Text(Text {
+                    position: Point2::new(w / 3.0, current_h + row_h / 2.0),
+                    color: (1.0, 1.0, 1.0, 1.0),
+                    text: format!(
+                        "{} {}/{}: {}",
+                        item.name, level, item.max_level, item.description
+                    ),
+                    font_size: 1.0,
+                }),
+                with_projection: false,
+            });
+            let price = item.price(level);
+            let maxed = level >= item.max_level;
+            let buy_button = Button::new(
+                Point2::new(w - w / 4.0, current_h),
+                buy_button_w,
+                buy_button_h,
+                None,
+                false,
+                Some(preloaded_images.upg_bar),
+                if maxed {
+                    "Max".to_string()
+                } else {
+                    format!("{} $", price)
+                },
+                Widgets::ShopItem as usize + i,
+                Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+                None,
+            );
+            if buy_button.place_and_check(&mut ui, &*mouse) {
+                if !maxed && macro_game.coins >= price {
+                    macro_game.coins -= price;
+                    macro_game.perks.insert(item.perk, level + 1);
+                    sounds_channel.single_write(Sound(
+                        preloaded_sounds.buy,
+                        Point2::new(0f32, 0f32),
+                    ));
+                } else {
+                    sounds_channel.single_write(Sound(
+                        preloaded_sounds.deny,
+                        Point2::new(0f32, 0f32),
+                    ));
+                }
+            }
+        }
+
+        let (button_w, button_h) = (w / 4f32, h / 10f32);
+        let back_to_menu = Button::new(
+            Point2::new(w / 2.0 - button_w / 2.0, h - 1.5 * button_h),
+            button_w,
+            button_h,
+            Some(Point3::new(0f32, 0f32, 0f32)),
+            false,
+            None,
+            "Back to Menu".to_string(),
+            Widgets::BackMenu as usize,
+            None,
+            None,
+        );
+        let back = ui.take_back();
+        if back_to_menu.place_and_check(&mut ui, &*mouse) || back {
+            *app_state = AppState::Menu;
+        }
+
+        primitives_channel.iter_write(ui.primitives.drain(..));
+        sounds_channel.iter_write(ui.sounds.drain(..));
+        render_primitives(
+            &mouse,
+            &mut self.reader,
+            &mut frame,
+            &gl,
+            &mut canvas,
+            &viewport,
+            &mut primitives_channel,
+            &mut text_data,
+            &mut world_text_data,
+        );
+    }
+}
@@ -62,8 +62,13 @@ impl<'a> System<'a> for SoundSystem {
                 }
             }
         }
+        // lazer loop is silenced while the run is paused
+        let paused = match *app_state {
+            AppState::Paused => true,
+            _ => false,
+        };
         for (lazer, _character) in (&multy_lazers, &character_markers).join() {
-            if lazer.active() {
+            if lazer.active() && !paused {
                 if loop_sound.player_lazer_channel.is_none() {
                     let channel = sdl2::mixer::Channel::all()
                         .play(
@@ -82,7 +87,7 @@ impl<'a> System<'a> for SoundSystem {
             }
         }
         match *app_state {
-            AppState::Play(_) => {
+            AppState::Play(_) | AppState::Paused => {
                 if music.current_battle.is_none() {
                     let mut rng = thread_rng();
                     let music_id =
@@ -104,7 +109,7 @@ impl<'a> System<'a> for SoundSystem {
                     music.menu_play = true;
                 }
             }
-            AppState::ScoreTable => {}
+            AppState::ScoreTable | AppState::Shop | AppState::Controls => {}
         }
     }
 }
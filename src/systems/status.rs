@@ -0,0 +1,91 @@
+use super::*;
+use log::info;
+
+/// Ticks status effects of ships: periodic damage and expiration
+#[derive(Default)]
+pub struct StatusEffectsSystem;
+
+impl<'a> System<'a> for StatusEffectsSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Isometry>,
+        ReadStorage<'a, CharacterMarker>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, Lifes>,
+        WriteStorage<'a, Shield>,
+        ReadStorage<'a, Resistances>,
+        WriteStorage<'a, DamageFlash>,
+        Write<'a, EventChannel<InsertEvent>>,
+        Write<'a, EventChannel<Sound>>,
+        ReadExpect<'a, PreloadedSounds>,
+        Write<'a, Coop>,
+        Write<'a, AppState>,
+        WriteExpect<'a, GlobalParams>,
+        Write<'a, EventChannel<ShipDestroyed>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        info!("asteroids: status effects started");
+        let (
+            entities,
+            isometries,
+            character_markers,
+            mut status_effects,
+            mut lifes,
+            mut shields,
+            resistances,
+            mut flashes,
+            mut insert_channel,
+            mut sounds_channel,
+            preloaded_sounds,
+            mut coop,
+            mut app_state,
+            mut global_params,
+            mut destroyed_channel,
+        ) = data;
+        let now = TRACKER.lock().unwrap().now();
+        for (entity, iso, effects, life) in
+            (&entities, &isometries, &mut status_effects, &mut lifes).join()
+        {
+            let mut burn_damage = 0f32;
+            for status in effects.active.iter_mut() {
+                if status.effect.kind == StatusKind::Burn {
+                    burn_damage += status.ticks(now) as f32 * status.magnitude();
+                }
+            }
+            effects.update(now);
+            if burn_damage <= 0.0 || coop.is_downed(entity) {
+                continue;
+            }
+            let is_character = character_markers.get(entity).is_some();
+            if is_character {
+                global_params.damaged(DAMAGED_RED);
+            }
+            if let Some(flash) = flashes.get_mut(entity) {
+                flash.0 = (flash.0 + 0.5).min(1f32);
+            }
+            let damage = affected_damage(Some(effects), burn_damage as usize);
+            if process_damage(
+                life,
+                shields.get_mut(entity),
+                damage,
+                DamageType::Energy,
+                resistances.get(entity),
+            ) {
+                let position = iso.0.translation.vector;
+                ship_explode(
+                    Point2::new(position.x, position.y),
+                    &mut insert_channel,
+                    &mut sounds_channel,
+                    &preloaded_sounds,
+                );
+                if !is_character
+                    || !knock_out(&mut app_state, &mut coop, entity)
+                {
+                    destroy_ship(entity, &entities, &mut destroyed_channel);
+                }
+            }
+        }
+        info!("asteroids: status effects ended");
+    }
+}
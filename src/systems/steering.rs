@@ -0,0 +1,187 @@
+use super::*;
+
+// angle between forward ray and side "whiskers" for obstacle avoidance
+const WHISKER_ANGLE: f32 = 0.5;
+
+fn truncate(v: Vector2, max_len: f32) -> Vector2 {
+    let norm = v.norm();
+    if norm > max_len && norm > EPS {
+        v * (max_len / norm)
+    } else {
+        v
+    }
+}
+
+fn asteroids_collision_groups() -> CollisionGroups {
+    let mut collision_groups = CollisionGroups::new();
+    collision_groups.set_membership(&[CollisionId::EnemyBullet as usize]);
+    collision_groups.set_whitelist(&[CollisionId::Asteroid as usize]);
+    collision_groups
+}
+
+/// desired velocity to reach target at full speed
+pub fn seek(pos: Point2, target: Point2, max_speed: f32) -> Vector2 {
+    let diff = target - pos;
+    if diff.norm() < EPS {
+        return Vector2::new(0f32, 0f32);
+    }
+    max_speed * diff.normalize()
+}
+
+/// same as seek but slows down inside slowing_radius
+pub fn arrive(
+    pos: Point2,
+    target: Point2,
+    max_speed: f32,
+    slowing_radius: f32,
+) -> Vector2 {
+    let diff = target - pos;
+    let dist = diff.norm();
+    if dist < EPS {
+        return Vector2::new(0f32, 0f32);
+    }
+    let speed = if dist < slowing_radius {
+        max_speed * dist / slowing_radius
+    } else {
+        max_speed
+    };
+    speed * diff.normalize()
+}
+
+/// run away from target, only if it's closer than flee_radius
+pub fn flee(
+    pos: Point2,
+    target: Point2,
+    max_speed: f32,
+    flee_radius: f32,
+) -> Vector2 {
+    let diff = pos - target;
+    let dist = diff.norm();
+    if dist > flee_radius || dist < EPS {
+        return Vector2::new(0f32, 0f32);
+    }
+    max_speed * (1.0 - dist / flee_radius) * diff.normalize()
+}
+
+/// push away from neighbours so ships don't bunch up on top of each other
+pub fn separation(
+    entity: Entity,
+    pos: Point2,
+    neighbours: &[(Entity, Point2)],
+    separation_radius: f32,
+    max_speed: f32,
+) -> Vector2 {
+    let mut res = Vector2::new(0f32, 0f32);
+    for (other, other_pos) in neighbours.iter() {
+        if *other == entity {
+            continue;
+        }
+        let diff = pos - *other_pos;
+        let dist = diff.norm();
+        if dist < separation_radius && dist > EPS {
+            res += diff.normalize() * (1.0 - dist / separation_radius);
+        }
+    }
+    truncate(max_speed * res, max_speed)
+}
+
+/// cast forward ray and two whiskers against asteroids
+/// and steer along the hit normal
+pub fn obstacle_avoidance(
+    world: &World<f32>,
+    pos: Point2,
+    velocity: Vector2,
+    avoidance_distance: f32,
+    max_speed: f32,
+) -> Vector2 {
+    let mut res = Vector2::new(0f32, 0f32);
+    if velocity.norm() < EPS {
+        return res;
+    }
+    let forward = velocity.normalize();
+    let collision_groups = asteroids_collision_groups();
+    for angle in [0f32, -WHISKER_ANGLE, WHISKER_ANGLE].iter() {
+        let dir = Rotation2::new(*angle) * forward;
+        let ray = Ray::new(pos, dir);
+        let mut mintoi = avoidance_distance;
+        let mut normal = None;
+        for (b, inter) in world
+            .collider_world()
+            .interferences_with_ray(&ray, &collision_groups)
+        {
+            if !b.query_type().is_proximity_query()
+                && inter.toi < mintoi
+                && inter.toi > EPS
+            {
+                mintoi = inter.toi;
+                normal = Some(inter.normal);
+            }
+        }
+        if let Some(normal) = normal {
+            // closer obstacle -- stronger push
+            res += normal * (1.0 - mintoi / avoidance_distance);
+        }
+    }
+    truncate(max_speed * res, max_speed)
+}
+
+/// Steered ship and where it wants to go
+pub struct SteeringInput<'a> {
+    pub entity: Entity,
+    pub pos: Point2,
+    pub velocity: Vector2,
+    pub target: Option<Point2>,
+    /// false if ship shouldn't move to the target
+    pub chase: bool,
+    pub neighbours: &'a [(Entity, Point2)],
+    pub max_speed: f32,
+}
+
+/// Combine all behaviours into desired velocity of the ship.
+/// If ship doesn't chase the target it keeps its current velocity
+/// and only flees, avoids asteroids and neighbours
+pub fn steer(
+    steering: &Steering,
+    world: &World<f32>,
+    input: &SteeringInput,
+) -> Vector2 {
+    let SteeringInput {
+        entity,
+        pos,
+        velocity,
+        target,
+        chase,
+        neighbours,
+        max_speed,
+    } = *input;
+    let mut desired = match target {
+        Some(target) if chase => {
+            steering.seek * seek(pos, target, max_speed)
+                + steering.arrive
+                    * arrive(pos, target, max_speed, steering.slowing_radius)
+        }
+        _ => velocity,
+    };
+    if let Some(target) = target {
+        desired += steering.flee
+            * flee(pos, target, max_speed, steering.flee_radius);
+    }
+    desired += steering.separation
+        * separation(
+            entity,
+            pos,
+            neighbours,
+            steering.separation_radius,
+            max_speed,
+        );
+    let heading = if desired.norm() > EPS { desired } else { velocity };
+    desired += steering.avoidance
+        * obstacle_avoidance(
+            world,
+            pos,
+            heading,
+            steering.avoidance_distance,
+            max_speed,
+        );
+    truncate(desired, max_speed)
+}
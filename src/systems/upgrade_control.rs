@@ -19,6 +19,8 @@ impl<'a> System<'a> for UpgradeControlSystem {
         Read<'a, AvaliableUpgrades>,
 	    WriteExpect<'a, Vec<UpgradeType>>,
 	    Write<'a, AppState>,
+	    Write<'a, RunUpgrades>,
+	    Write<'a, RunRngs>,
 	);
 
     fn run(&mut self, data: Self::SystemData) {
@@ -29,10 +31,11 @@ impl<'a> System<'a> for UpgradeControlSystem {
     		mut ui_state,
     		avaliable_upgrades,
     		mut upgrade_types,
-    		mut app_state
+    		mut app_state,
+    		mut run_upgrades,
+    		mut run_rngs,
     	) = data;
     	let upgrades = spawned_upgrades.last().map(|x| x.clone());
-        let widget_ids = [Widgets::Upgrade1, Widgets::Upgrade2];
         let widget_selector = Widgets::UpgradeSelector as usize;
     	swap(&mut self.prev_keys, &mut self.new_keys);
     	self.new_keys.clear();
@@ -44,22 +47,40 @@ impl<'a> System<'a> for UpgradeControlSystem {
     	for key in new_pressed.iter() {
     		match key {
                 Keycode::Left | Keycode::Right => {
-			    	if let Some(upgrades) = upgrades {
-	                	if ui.selected(widget_selector, widget_ids[0] as usize) 
-	                			|| ui.free_selector(widget_selector) {
-							ui_state.choosed_upgrade = Some(upgrades[1]);
-	                		ui.select(widget_selector, widget_ids[1] as usize)
-	                	} else if ui.selected(widget_selector, widget_ids[1] as usize) {
-							ui_state.choosed_upgrade = Some(upgrades[0]);
-	                		ui.select(widget_selector, widget_ids[0] as usize)
-	                	}
+			    	if let Some(ref upgrades) = upgrades {
+			    		let cards_num = upgrades.len();
+			    		let current = (0..cards_num).find(|&i| {
+			    			ui.selected(widget_selector, UPGRADE_WIDGETS[i] as usize)
+			    		});
+			    		let next = match (current, key) {
+			    			(None, _) => 0,
+			    			(Some(i), Keycode::Left) => (i + cards_num - 1) % cards_num,
+			    			(Some(i), _) => (i + 1) % cards_num,
+			    		};
+						ui_state.choosed_upgrade = Some(upgrades[next]);
+                		ui.select(widget_selector, UPGRADE_WIDGETS[next] as usize)
 			    	}
                 }
+                Keycode::R => {
+                	ui_state.choosed_upgrade = None;
+                	reroll_upgrades(
+                		&mut spawned_upgrades,
+                		&mut run_upgrades,
+                		&avaliable_upgrades,
+                		&mut run_rngs,
+                	);
+                }
                 Keycode::Space => {
     	            if let Some(upgrade) = ui_state.choosed_upgrade {
 	                    ui_state.choosed_upgrade = None;
-	                    spawned_upgrades.pop();
 			            upgrade_types.push(avaliable_upgrades[upgrade].upgrade_type);
+	                    pick_upgrade(
+	                    	upgrade,
+	                    	&mut spawned_upgrades,
+	                    	&mut run_upgrades,
+	                    	&avaliable_upgrades,
+	                    	&mut run_rngs,
+	                    );
     	            } else {
         	            *app_state = AppState::Play(PlayState::Action);
     	            }
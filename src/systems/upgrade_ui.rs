@@ -1,5 +1,4 @@
 pub use super::*;
-use std::convert::TryFrom;
 
 #[derive(Default)]
 pub struct UpgradeGUI;
@@ -24,6 +23,8 @@ impl<'a> System<'a> for UpgradeGUI {
         ReadExpect<'a, Pallete>,
         ReadExpect<'a, PreloadedSounds>,
         WriteExpect<'a, Vec<UpgradeType>>,
+        Write<'a, RunUpgrades>,
+        Write<'a, RunRngs>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -47,6 +48,8 @@ impl<'a> System<'a> for UpgradeGUI {
             _pallete,
             preloaded_sounds,
             mut upgrade_types,
+            mut run_upgrades,
+            mut run_rngs,
         ) = data;
         let dims = viewport.dimensions();
         let (w, h) = (dims.0 as f32, dims.1 as f32);
@@ -72,14 +75,20 @@ impl<'a> System<'a> for UpgradeGUI {
             with_projection: false,
         });
         let mut buttons = vec![];
-        let upgrades = spawned_upgrades.last();
+        let upgrades = spawned_upgrades.last().cloned();
         // dbg!(&upgrades);
         if let Some(upgrades) = upgrades {
-            let widget_ids = [Widgets::Upgrade1, Widgets::Upgrade2];
+            let cards_w =
+                upgrades.len() as f32 * (upgrade_button_w + shift) - shift;
             for (i, upg_id) in upgrades.iter().enumerate() {
                 let upg = &avaliable_upgrades[*upg_id];
+                let rank = run_upgrades
+                    .picked
+                    .iter()
+                    .filter(|&picked| picked == upg_id)
+                    .count();
                 let current_point = Point2::new(
-                    w / 2.0 - upgrade_button_w - shift
+                    w / 2.0 - cards_w / 2.0
                         + i as f32 * (upgrade_button_w + shift),
                     shift,
                 );
@@ -91,7 +100,7 @@ impl<'a> System<'a> for UpgradeGUI {
                     false,
                     Some(upg.image),
                     "".to_string(),
-                    widget_ids[i] as usize,
+                    UPGRADE_WIDGETS[i] as usize,
                     Some(Sound(
                         preloaded_sounds.hover,
                         Point2::new(0f32, 0f32),
@@ -107,7 +116,12 @@ impl<'a> System<'a> for UpgradeGUI {
                             current_point.x + upgrade_button_h / 2.0,
                             upgrade_button_h + 2.0 * shift,
                         ),
-                        text: upg.name.clone(),
+                        text: format!(
+                            "{} {}/{}",
+                            upg.name,
+                            rank + 1,
+                            upg.max_rank
+                        ),
                         color: (1.0, 1.0, 1.0, 1.0),
                         font_size: 1.0,
                     }),
@@ -123,15 +137,11 @@ impl<'a> System<'a> for UpgradeGUI {
             if let Some(selected_id) =
                 upgrade_selector.place_and_check(&mut ui, &*mouse)
             {
-                match Widgets::try_from(selected_id).expect("unknown widget id")
+                if let Some(i) = UPGRADE_WIDGETS
+                    .iter()
+                    .position(|widget| *widget as usize == selected_id)
                 {
-                    Widgets::Upgrade1 => {
-                        ui_state.choosed_upgrade = Some(upgrades[0]);
-                    }
-                    Widgets::Upgrade2 => {
-                        ui_state.choosed_upgrade = Some(upgrades[1]);
-                    }
-                    _ => (),
+                    ui_state.choosed_upgrade = Some(upgrades[i]);
                 }
             }
         }
@@ -169,10 +179,42 @@ impl<'a> System<'a> for UpgradeGUI {
                     current_upgrade =
                         Some(avaliable_upgrades[upgrade].upgrade_type);
                     ui_state.choosed_upgrade = None;
-                    spawned_upgrades.pop();
+                    pick_upgrade(
+                        upgrade,
+                        &mut spawned_upgrades,
+                        &mut run_upgrades,
+                        &avaliable_upgrades,
+                        &mut run_rngs,
+                    );
                 }
             }
         }
+        if spawned_upgrades.len() > 0 && run_upgrades.rerolls > 0 {
+            let reroll_button = Button::new(
+                Point2::new(
+                    w / 2.0 - choose_button_w / 2.0,
+                    h - 2.0 * choose_button_h - shift,
+                ),
+                choose_button_w,
+                choose_button_h,
+                None,
+                false,
+                Some(preloaded_images.upg_bar),
+                format!("Reroll ({})", run_upgrades.rerolls),
+                Widgets::Reroll as usize,
+                Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
+                Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
+            );
+            if reroll_button.place_and_check(&mut ui, &*mouse) {
+                ui_state.choosed_upgrade = None;
+                reroll_upgrades(
+                    &mut spawned_upgrades,
+                    &mut run_upgrades,
+                    &avaliable_upgrades,
+                    &mut run_rngs,
+                );
+            }
+        }
         let done_button = Button::new(
             Point2::new(w / 2.0 + shift, h - 1.0 * choose_button_h),
             choose_button_w,
@@ -185,7 +227,8 @@ impl<'a> System<'a> for UpgradeGUI {
             Some(Sound(preloaded_sounds.hover, Point2::new(0f32, 0f32))),
             Some(Sound(preloaded_sounds.click, Point2::new(0f32, 0f32))),
         );
-        if done_button.place_and_check(&mut ui, &*mouse) {
+        let back = ui.take_back();
+        if done_button.place_and_check(&mut ui, &*mouse) || back {
             *app_state = AppState::Play(PlayState::Action);
         }
 
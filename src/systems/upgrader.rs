@@ -9,7 +9,11 @@ impl<'a> System<'a> for Upgrader {
         ReadStorage<'a, CharacterMarker>,
         WriteStorage<'a, ShipStats>,
         WriteStorage<'a, MultyLazer>,
-        WriteStorage<'a, ShotGun>,
+        WriteStorage<'a, Hardpoints>,
+        WriteStorage<'a, DroneBay>,
+        WriteStorage<'a, Dash>,
+        WriteStorage<'a, AbilityBar>,
+        ReadExpect<'a, Description>,
         WriteExpect<'a, Vec<UpgradeType>>,
 	);
 
@@ -19,71 +23,133 @@ impl<'a> System<'a> for Upgrader {
             character_markers,
             mut ships_stats,
             mut multiple_lazers,
-            mut shotguns,
+            mut hardpoints,
+            mut drone_bays,
+            mut dashes,
+            mut ability_bars,
+            description,
             mut upgrade_types
         ) = data;
-        let (character, ship_stats, _) =
-            (&entities, &mut ships_stats, &character_markers)
-                .join()
-                .next()
-                .unwrap();
+        // upgrades are shared by the whole team
+        let characters: Vec<Entity> = (&entities, &character_markers)
+            .join()
+            .map(|(entity, _)| entity)
+            .collect();
         for choosed_upgrade in upgrade_types.drain(..) {
         	dbg!("upgrading");
-            match choosed_upgrade {
-                UpgradeType::AttackSpeed => {
-                    if let Some(gun) = shotguns.get_mut(character) {
-                        let recharge_time_millis =
-                            (gun.recharge_time.as_millis() as f32 * 0.9) as u64;
-                        gun.recharge_time =
-                            Duration::from_millis(recharge_time_millis);
+            for &character in characters.iter() {
+                let ship_stats = ships_stats.get_mut(character).unwrap();
+                match choosed_upgrade {
+                    // every mounted gun of the kind is upgraded
+                    UpgradeType::AttackSpeed => {
+                        if let Some(mounts) = hardpoints.get_mut(character) {
+                            for gun in mounts.shotguns_mut() {
+                                let recharge_time_millis =
+                                    (gun.recharge_time.as_millis() as f32
+                                        * 0.9) as u64;
+                                gun.recharge_time =
+                                    Duration::from_millis(recharge_time_millis);
+                            }
+                            for gun in mounts.rocket_guns_mut() {
+                                let recharge_time_millis =
+                                    (gun.recharge_time.as_millis() as f32
+                                        * 0.9) as u64;
+                                gun.recharge_time =
+                                    Duration::from_millis(recharge_time_millis);
+                            }
+                        }
                     }
-                }
-                UpgradeType::BulletSpeed => {
-                    if let Some(gun) = shotguns.get_mut(character) {
-                        gun.bullet_speed += 0.1 * BULLET_SPEED_INIT;
+                    UpgradeType::BulletSpeed => {
+                        if let Some(mounts) = hardpoints.get_mut(character) {
+                            for gun in mounts.shotguns_mut() {
+                                gun.bullet_speed += 0.1 * BULLET_SPEED_INIT;
+                            }
+                        }
                     }
-                }
-                // UpgradeType::BulletReflection => {
-                //     if let Some(gun) = shotguns.get_mut(character) {
-                //         if let Some(ref mut reflection) = gun.reflection {
-                //             // reflection.speed += 0.5;
-                //             reflection.lifetime += Duration::from_millis(200);
-                //         } else {
-                //             gun.reflection = Some(Reflection {
-                //                 speed: 0.4,
-                //                 lifetime: Duration::from_millis(1500),
-                //                 times: None,
-                //             })
-                //         }
-                //     }
-                // }
-                UpgradeType::LazerLength => {
-                    if let Some(multy_lazer) =
-                        multiple_lazers.get_mut(character)
-                    {
-                        multy_lazer.upgrade_length(0.3);
+                    // UpgradeType::BulletReflection => {
+                    //     if let Some(gun) = shotguns.get_mut(character) {
+                    //         if let Some(ref mut reflection) = gun.reflection {
+                    //             // reflection.speed += 0.5;
+                    //             reflection.lifetime += Duration::from_millis(200);
+                    //         } else {
+                    //             gun.reflection = Some(Reflection {
+                    //                 speed: 0.4,
+                    //                 lifetime: Duration::from_millis(1500),
+                    //                 times: None,
+                    //             })
+                    //         }
+                    //     }
+                    // }
+                    UpgradeType::LazerLength => {
+                        if let Some(multy_lazer) =
+                            multiple_lazers.get_mut(character)
+                        {
+                            multy_lazer.upgrade_length(0.3);
+                        }
+                    }
+                    UpgradeType::ShipSpeed => {
+                        ship_stats.thrust_force += 0.1 * THRUST_FORCE_INIT;
+                    }
+                    UpgradeType::ShipRotationSpeed => {
+                        ship_stats.torque += 0.1 * SHIP_ROTATION_SPEED_INIT;
                     }
-                }
-                UpgradeType::ShipSpeed => {
-                    ship_stats.thrust_force += 0.1 * THRUST_FORCE_INIT;
-                }
-                UpgradeType::ShipRotationSpeed => {
-                    ship_stats.torque += 0.1 * SHIP_ROTATION_SPEED_INIT;
-                }
 
-                UpgradeType::ShieldRegen => {
-                    ship_stats.shield_regen += 1;
-                }
-                UpgradeType::HealthSize => {
-                    ship_stats.max_health = ship_stats.max_health + (0.05 * ship_stats.max_health as f32) as usize;
-                }
-                UpgradeType::ShieldSize => {
-                    ship_stats.max_shield = ship_stats.max_shield + (0.05 * ship_stats.max_shield as f32) as usize;
-                }
-                UpgradeType::Maneuverability => {
-                    *ship_stats.maneuverability.as_mut().unwrap() += 1.0;
+                    UpgradeType::ShieldRegen => {
+                        ship_stats.shield_regen += 1;
+                    }
+                    UpgradeType::HealthSize => {
+                        ship_stats.max_health = ship_stats.max_health + (0.05 * ship_stats.max_health as f32) as usize;
+                    }
+                    UpgradeType::ShieldSize => {
+                        ship_stats.max_shield = ship_stats.max_shield + (0.05 * ship_stats.max_shield as f32) as usize;
+                    }
+                    UpgradeType::Maneuverability => {
+                        *ship_stats.maneuverability.as_mut().unwrap() += 1.0;
+                    }
+                    UpgradeType::RocketDamage => {
+                        if let Some(mounts) = hardpoints.get_mut(character) {
+                            for gun in mounts.rocket_guns_mut() {
+                                gun.bullets_damage +=
+                                    (0.1 * gun.bullets_damage as f32) as usize;
+                            }
+                        }
+                    }
+                    UpgradeType::RocketFuel => {
+                        if let Some(mounts) = hardpoints.get_mut(character) {
+                            for gun in mounts.rocket_guns_mut() {
+                                gun.bullet_lifetime +=
+                                    Duration::from_millis(200);
+                            }
+                        }
+                    }
+                    UpgradeType::DashCharges => {
+                        if let Some(dash) = dashes.get_mut(character) {
+                            dash.max_charges += 1;
+                            dash.charges += 1;
+                        }
+                    }
+                    UpgradeType::DashCooldown => {
+                        if let Some(dash) = dashes.get_mut(character) {
+                            let cooldown_millis =
+                                (dash.cooldown.as_millis() as f32 * 0.8) as u64;
+                            dash.cooldown =
+                                Duration::from_millis(cooldown_millis);
+                        }
+                    }
+                    UpgradeType::ActiveAbility(kind) => {
+                        if let Some(bar) = ability_bars.get_mut(character) {
+                            bar.add(kind);
+                        }
+                    }
+                    UpgradeType::Drone(kind) => {
+                        add_drone(
+                            &mut drone_bays,
+                            character,
+                            description.drones[kind].clone(),
+                        );
+                    }
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,6 @@
 use crate::nalgebra::Rotation2;
+use crate::systems::process_damage;
+use components::*;
 
 use sdl2::mixer::{InitFlag, AUDIO_S16LSB, DEFAULT_CHANNELS};
 use std::path::Path;
@@ -31,3 +33,47 @@ fn sound() -> Result<(), String> {
     sdl2::mixer::Channel::all().play(&sound_chunk, 0)?;
     Ok(())
 }
+
+// damage goes through shield first, resistances per layer
+#[test]
+fn damage_resistances() {
+    let mut resistances = Resistances::default();
+    resistances.shield.energy = 0.5;
+    resistances.hull.kinetic = 0.0;
+    let mut life = Lifes(100);
+    let mut shield = Shield(10);
+    // shield absorbs halved energy damage
+    let killed = process_damage(
+        &mut life,
+        Some(&mut shield),
+        10,
+        DamageType::Energy,
+        Some(&resistances),
+    );
+    assert!(!killed);
+    assert_eq!(shield.0, 5);
+    assert_eq!(life.0, 100);
+    // not absorbed part goes to hull
+    process_damage(
+        &mut life,
+        Some(&mut shield),
+        20,
+        DamageType::Energy,
+        Some(&resistances),
+    );
+    assert_eq!(shield.0, 0);
+    assert_eq!(life.0, 90);
+    // hull is immune to kinetic
+    process_damage(
+        &mut life,
+        None,
+        50,
+        DamageType::Kinetic,
+        Some(&resistances),
+    );
+    assert_eq!(life.0, 90);
+    let killed =
+        process_damage(&mut life, None, 100, DamageType::Explosive, None);
+    assert!(killed);
+    assert_eq!(life.0, 0);
+}